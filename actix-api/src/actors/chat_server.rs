@@ -1,8 +1,10 @@
+use crate::congestion::CongestionMonitor;
 use crate::messages::{
     server::{ClientMessage, Connect, Disconnect, JoinRoom, Leave},
     session::Message,
 };
 
+use actix::prelude::SendError;
 use actix::{Actor, AsyncContext, Context, Handler, MessageResult, Recipient};
 use futures::StreamExt;
 use std::collections::HashMap;
@@ -133,13 +135,28 @@ impl Handler<JoinRoom> for ChatServer {
                         room,
                         session_2.trim(),
                     );
+                    let own_subject = format!("room.{}.{}", room, session_2).replace(' ', "_");
+                    let mut congestion = CongestionMonitor::new(&room, &own_subject);
                     while let Some(msg) = sub.next().await {
-                        if let Err(e) =
-                            handle_msg(session_recipient.clone(), room.clone(), session_2.clone())(
-                                msg,
-                            )
-                        {
-                            error!("{}", e);
+                        let subject = msg.subject.clone();
+                        let payload = msg.payload.clone();
+                        match handle_msg(session_recipient.clone(), room.clone(), session_2.clone())(
+                            msg,
+                        ) {
+                            Ok(()) => congestion.on_delivered(&subject, payload.len()),
+                            Err(e) => {
+                                error!("{}", e);
+                                // The session's mailbox is full, it can't keep up.
+                                if e.kind() == std::io::ErrorKind::WouldBlock {
+                                    if let Some((subject, feedback)) =
+                                        congestion.on_dropped(&subject, &payload)
+                                    {
+                                        if let Err(e) = nc.publish(subject, feedback.into()).await {
+                                            error!("error publishing congestion feedback: {}", e);
+                                        }
+                                    }
+                                }
+                            }
                         }
                     }
                 }
@@ -184,7 +201,12 @@ fn handle_msg(
 
         session_recipient.try_send(message).map_err(|e| {
             error!("error sending message to session {}: {}", session, e);
-            std::io::Error::new(std::io::ErrorKind::Other, e)
+            match e {
+                SendError::Full(_) => {
+                    std::io::Error::new(std::io::ErrorKind::WouldBlock, e.to_string())
+                }
+                SendError::Closed(_) => std::io::Error::other(e.to_string()),
+            }
         })
     }
 }
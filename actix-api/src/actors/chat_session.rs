@@ -1,3 +1,4 @@
+use crate::congestion::is_congestion_feedback;
 use crate::messages::server::{ClientMessage, Packet};
 use crate::messages::session::Message;
 use crate::{actors::chat_server::ChatServer, constants::CLIENT_TIMEOUT};
@@ -136,6 +137,9 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsChatSession {
 
         match msg {
             ws::Message::Binary(msg) => {
+                if is_congestion_feedback(&msg) {
+                    return;
+                }
                 ctx.notify(Packet {
                     data: Arc::new(msg.to_vec()),
                 });
@@ -0,0 +1,138 @@
+//
+// Congestion feedback for senders.
+//
+// Each connection to a receiver counts the media it delivers, by sender, and the packets it has
+// to drop because the receiver falls behind.  When a sender's packet is dropped, the sender is
+// told the bitrate the receiver can take from it: its fair share of what was delivered to the
+// receiver lately.  The feedback is a CONGESTION packet published to the whole room, like key
+// frame requests, which only the sender it names acts on.
+//
+// Only the server may send feedback, otherwise any participant could cap everyone else's
+// bitrate, so CONGESTION packets that clients send are dropped instead of relayed.
+//
+
+use protobuf::rt::WireType;
+use protobuf::{CodedInputStream, Enum, Message};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use videocall_types::protos::congestion_packet::CongestionPacket;
+use videocall_types::protos::packet_wrapper::packet_wrapper::PacketType;
+use videocall_types::protos::packet_wrapper::PacketWrapper;
+
+/// How often each sender may be told to slow down by the same receiver.
+const FEEDBACK_WINDOW: Duration = Duration::from_secs(1);
+/// Lowest bitrate a sender is asked to go down to, in bits per second.
+const MIN_BITRATE: u32 = 50_000;
+
+/// Field number of `PacketWrapper.packet_type`.
+const PACKET_TYPE_FIELD: u32 = 1;
+
+/// Whether `payload`, a packet received from a client, is congestion feedback, which the client
+/// isn't allowed to send.
+pub fn is_congestion_feedback(payload: &[u8]) -> bool {
+    packet_type(payload) == Some(PacketType::CONGESTION.value())
+}
+
+// Reads the packet_type of a PacketWrapper without parsing the rest, as this runs on every packet
+// a client sends.  The other fields, the media above all, are skipped rather than copied.  Like in
+// a full parse, the last packet_type wins, so a client can't sneak one past by repeating it.
+fn packet_type(payload: &[u8]) -> Option<i32> {
+    let mut input = CodedInputStream::from_bytes(payload);
+    let mut packet_type = 0;
+    while let Some(tag) = input.read_raw_tag_or_eof().ok()? {
+        let wire_type = WireType::new(tag & 7)?;
+        if tag >> 3 == PACKET_TYPE_FIELD && wire_type == WireType::Varint {
+            packet_type = input.read_int32().ok()?;
+        } else {
+            input.skip_field(wire_type).ok()?;
+        }
+    }
+    Some(packet_type)
+}
+
+pub struct CongestionMonitor {
+    room: String,
+    // Subject the receiver publishes its own media on, which it doesn't receive.
+    own_subject: String,
+    window_start: Instant,
+    // Bytes delivered to the receiver in the current window, by sender subject.
+    delivered: HashMap<String, u64>,
+    // Bytes delivered, number of senders and length of the previous window.
+    previous: Option<(u64, usize, Duration)>,
+    // Senders told to slow down in the current window.
+    notified: HashSet<String>,
+}
+
+impl CongestionMonitor {
+    pub fn new(room: &str, own_subject: &str) -> Self {
+        Self {
+            room: room.to_owned(),
+            own_subject: own_subject.to_owned(),
+            window_start: Instant::now(),
+            delivered: HashMap::new(),
+            previous: None,
+            notified: HashSet::new(),
+        }
+    }
+
+    pub fn on_delivered(&mut self, subject: &str, bytes: usize) {
+        if subject == self.own_subject {
+            return;
+        }
+        self.roll_window();
+        *self.delivered.entry(subject.to_owned()).or_default() += bytes as u64;
+    }
+
+    /// Counts a packet that was dropped on its way to the receiver.  Returns the subject and
+    /// payload of the feedback to publish if the packet is media and its sender hasn't been told
+    /// to slow down in this window yet.
+    pub fn on_dropped(&mut self, subject: &str, payload: &[u8]) -> Option<(String, Vec<u8>)> {
+        self.roll_window();
+        if subject == self.own_subject || self.notified.contains(subject) {
+            return None;
+        }
+        let packet = PacketWrapper::parse_from_bytes(payload).ok()?;
+        if packet.packet_type.enum_value() != Ok(PacketType::MEDIA) {
+            return None;
+        }
+        self.notified.insert(subject.to_owned());
+        let feedback = PacketWrapper {
+            packet_type: PacketType::CONGESTION.into(),
+            data: CongestionPacket {
+                target: packet.email,
+                max_bitrate: self.fair_share(),
+                ..Default::default()
+            }
+            .write_to_bytes()
+            .ok()?,
+            ..Default::default()
+        };
+        Some((
+            format!("room.{}.congestion-feedback", self.room).replace(' ', "_"),
+            feedback.write_to_bytes().ok()?,
+        ))
+    }
+
+    // What the receiver took in per second over the previous and the current window, split
+    // evenly between the senders.
+    fn fair_share(&self) -> u32 {
+        let (previous_bytes, previous_senders, previous_elapsed) =
+            self.previous.unwrap_or((0, 0, Duration::ZERO));
+        let bytes = previous_bytes + self.delivered.values().sum::<u64>();
+        let elapsed = previous_elapsed + self.window_start.elapsed();
+        let senders = previous_senders.max(self.delivered.len()).max(1);
+        let bitrate = bytes as f64 * 8.0 / elapsed.as_secs_f64().max(0.1) / senders as f64;
+        (bitrate as u32).max(MIN_BITRATE)
+    }
+
+    fn roll_window(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < FEEDBACK_WINDOW {
+            return;
+        }
+        self.previous = Some((self.delivered.values().sum(), self.delivered.len(), elapsed));
+        self.window_start = Instant::now();
+        self.delivered.clear();
+        self.notified.clear();
+    }
+}
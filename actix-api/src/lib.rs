@@ -1,5 +1,6 @@
 pub mod actors;
 pub mod auth;
+pub mod congestion;
 pub mod constants;
 pub mod db;
 pub mod messages;
@@ -1,3 +1,4 @@
+use crate::congestion::{is_congestion_feedback, CongestionMonitor};
 use anyhow::{anyhow, Context, Result};
 use futures::StreamExt;
 use protobuf::Message;
@@ -5,7 +6,7 @@ use quinn::crypto::rustls::HandshakeData;
 use quinn::VarInt;
 use rustls::pki_types::{CertificateDer, PrivateKeyDer};
 use std::io::Read;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::Duration;
 use std::{fs, io};
 use std::{net::SocketAddr, path::PathBuf, sync::Arc};
@@ -22,6 +23,10 @@ pub const QUIC_ALPN: &[u8] = b"hq-29";
 
 const MAX_UNIDIRECTIONAL_STREAM_SIZE: usize = 500_000;
 
+/// Streams being written to a session at once beyond which it is considered to fall behind, and
+/// further packets to it are dropped.
+const MAX_PENDING_STREAMS: usize = 100;
+
 #[derive(Debug)]
 pub struct WebTransportOpt {
     pub listen: SocketAddr,
@@ -223,6 +228,9 @@ async fn handle_session(
     let nats_receive_task = {
         let session = session.clone();
         let should_run = should_run.clone();
+        let nc = nc.clone();
+        let mut congestion = CongestionMonitor::new(lobby_id, &specific_subject);
+        let pending_streams = Arc::new(AtomicUsize::new(0));
         tokio::spawn(async move {
             while let Some(msg) = sub.next().await {
                 if !should_run.load(Ordering::SeqCst) {
@@ -233,7 +241,20 @@ async fn handle_session(
                 }
                 let session = session.read().await;
                 if msg.payload.len() > 400 {
+                    if pending_streams.load(Ordering::SeqCst) >= MAX_PENDING_STREAMS {
+                        if let Some((subject, feedback)) =
+                            congestion.on_dropped(&msg.subject, &msg.payload)
+                        {
+                            if let Err(e) = nc.publish(subject, feedback.into()).await {
+                                error!("Error publishing congestion feedback: {}", e);
+                            }
+                        }
+                        continue;
+                    }
+                    congestion.on_delivered(&msg.subject, msg.payload.len());
                     let stream = session.open_uni().await;
+                    let pending_streams = pending_streams.clone();
+                    pending_streams.fetch_add(1, Ordering::SeqCst);
                     tokio::spawn(async move {
                         match stream {
                             Ok(mut uni_stream) => {
@@ -245,9 +266,13 @@ async fn handle_session(
                                 error!("Error opening unidirectional stream: {}", e);
                             }
                         }
+                        pending_streams.fetch_sub(1, Ordering::SeqCst);
                     });
-                } else if let Err(e) = session.send_datagram(msg.payload) {
-                    error!("Error sending datagram: {}", e);
+                } else {
+                    congestion.on_delivered(&msg.subject, msg.payload.len());
+                    if let Err(e) = session.send_datagram(msg.payload) {
+                        error!("Error sending datagram: {}", e);
+                    }
                 }
             }
         })
@@ -265,6 +290,7 @@ async fn handle_session(
                 tokio::spawn(async move {
                     let result = uni_stream.read_to_end(1_000_000).await;
                     match result {
+                        Ok(buf) if is_congestion_feedback(&buf) => {}
                         Ok(buf) => {
                             tokio::spawn(async move {
                                 if let Err(e) =
@@ -290,6 +316,9 @@ async fn handle_session(
         tokio::spawn(async move {
             let session = session.read().await;
             while let Ok(buf) = session.read_datagram().await {
+                if is_congestion_feedback(&buf) {
+                    continue;
+                }
                 let nc = nc.clone();
                 if let Err(e) = nc.publish(specific_subject.clone(), buf).await {
                     error!("Error publishing to subject {}: {}", specific_subject, e);
@@ -398,7 +427,7 @@ async fn handle_quic_connection(
                                         .unwrap();
                                 }
                             }
-                        } else {
+                        } else if !is_congestion_feedback(&d) {
                             let specific_subject = specific_subject_rx.borrow().clone().unwrap();
                             if let Err(e) = nc.publish(specific_subject.clone(), d.into()).await {
                                 error!("Error publishing to subject {}: {}", &specific_subject, e);
@@ -420,6 +449,9 @@ async fn handle_quic_connection(
             }
             let specific_subject = specific_subject_rx.borrow().clone().unwrap();
             while let Ok(datagram) = session.read_datagram().await {
+                if is_congestion_feedback(&datagram) {
+                    continue;
+                }
                 let nc = nc.clone();
                 if let Err(e) = nc.publish(specific_subject.clone(), datagram).await {
                     error!("Error publishing to subject {}: {}", specific_subject, e);
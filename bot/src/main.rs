@@ -1,10 +1,14 @@
+mod reorder_buffer;
+
 use chrono::Utc;
 use futures::stream::FuturesUnordered;
 use futures::SinkExt;
 use futures::StreamExt;
 use protobuf::Message as ProtoMessage;
 use rand::Rng;
+use reorder_buffer::ReorderBuffer;
 use std::env;
+use std::time::{Duration, Instant};
 use tokio::task::JoinHandle;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use url::Url;
@@ -20,10 +24,15 @@ async fn main() {
     let room = env::var("ROOM").unwrap();
     let echo_user = env::var("ECHO_USER").unwrap();
     let email_prefix = env::var("EMAIL_PREFIX").unwrap_or_else(|_| "".to_string());
+    // When set, echoed packets are put back in timestamp order, holding each one this long.
+    let reorder_hold = env::var("REORDER_HOLD_MS")
+        .ok()
+        .map(|ms| Duration::from_millis(ms.parse().unwrap()));
 
     (0..n_clients)
         .map(|_| async {
-            let handle = create_client(&endpoint, &room, &echo_user, &email_prefix).await;
+            let handle =
+                create_client(&endpoint, &room, &echo_user, &email_prefix, reorder_hold).await;
             let _ = handle.await;
         })
         .collect::<FuturesUnordered<_>>()
@@ -36,6 +45,7 @@ async fn create_client(
     room: &str,
     echo_user: &str,
     email_prefix: &str,
+    reorder_hold: Option<Duration>,
 ) -> JoinHandle<()> {
     let email = generate_email(email_prefix);
     let url = format!("{}/lobby/{}/{}", endpoint, email, room);
@@ -55,13 +65,39 @@ async fn create_client(
     ws_stream.send(Message::Binary(buf)).await.unwrap();
     tokio::spawn(async move {
         let mut ws_stream = ws_stream;
-        while let Some(msg) = ws_stream.next().await {
-            let msg = msg.unwrap();
-            match msg {
-                Message::Text(text) => {
-                    if text == "Hello" {
-                        ws_stream.send("Hello".into()).await.unwrap();
+        let mut reorder_buffer = reorder_hold.map(ReorderBuffer::new);
+        let mut flush = tokio::time::interval(Duration::from_millis(10));
+        loop {
+            let msg = tokio::select! {
+                msg = ws_stream.next() => match msg {
+                    Some(msg) => msg.unwrap(),
+                    None => break,
+                },
+                _ = flush.tick(), if reorder_buffer.is_some() => {
+                    let buffer = reorder_buffer.as_mut().unwrap();
+                    let dropped_before = buffer.stats().dropped_late;
+                    for media_packet in buffer.pop_ready(Instant::now()) {
+                        let mut buf = Vec::new();
+                        media_packet.write_to_vec(&mut buf).unwrap();
+                        ws_stream.send(Message::Binary(buf)).await.unwrap();
+                    }
+                    let stats = buffer.stats();
+                    if stats.dropped_late > dropped_before {
+                        println!(
+                            "{}: dropped {} late packets, depth {}, {} tracks, {:?}",
+                            email,
+                            stats.dropped_late - dropped_before,
+                            buffer.depth(),
+                            buffer.tracks(),
+                            stats
+                        );
                     }
+                    continue;
+                }
+            };
+            match msg {
+                Message::Text(text) if text == "Hello" => {
+                    ws_stream.send("Hello".into()).await.unwrap();
                 }
                 Message::Binary(bin) => {
                     // decode bin as protobuf
@@ -72,6 +108,10 @@ async fn create_client(
                     if media_packet.email == echo_user {
                         media_packet.email.clone_from(&email);
 
+                        if let Some(buffer) = reorder_buffer.as_mut() {
+                            buffer.push(media_packet, Instant::now());
+                            continue;
+                        }
                         // send the protobuf back to the server
                         let mut buf = Vec::new();
                         media_packet.write_to_vec(&mut buf).unwrap();
@@ -0,0 +1,253 @@
+// Holds incoming media packets for a short while and releases them in timestamp order, per
+// sender and media type, for consumers like a recorder that need ordered packets more than
+// they need low latency.
+//
+// Each packet is held for at least `hold` after it arrived. Once that is over it is released,
+// together with every packet of its track that has an earlier timestamp. A packet that arrives
+// after a later one of its track was released can no longer be put in order, so it is dropped.
+//
+// Tracks that have received nothing for TRACK_IDLE_TIMEOUT and hold no packets are forgotten, so
+// that senders who left don't use memory for as long as the bot runs.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use videocall_types::protos::media_packet::MediaPacket;
+
+const TRACK_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReorderStats {
+    /// Packets released in order.
+    pub released: u64,
+    /// Packets that arrived with an earlier timestamp than a packet already received on their
+    /// track, and were put back in order.
+    pub reordered: u64,
+    /// Packets dropped because a later packet of their track had already been released.
+    pub dropped_late: u64,
+    /// Largest number of packets held at once.
+    pub max_depth: usize,
+}
+
+// Orders by timestamp, then by arrival for packets with the same timestamp.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct OrderKey {
+    timestamp: f64,
+    arrival: u64,
+}
+
+impl Eq for OrderKey {}
+
+impl PartialOrd for OrderKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.timestamp
+            .total_cmp(&other.timestamp)
+            .then(self.arrival.cmp(&other.arrival))
+    }
+}
+
+struct Track {
+    pending: BTreeMap<OrderKey, (Instant, MediaPacket)>,
+    newest_received: Option<f64>,
+    last_released: Option<f64>,
+    last_arrival: Instant,
+}
+
+impl Track {
+    fn new(now: Instant) -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            newest_received: None,
+            last_released: None,
+            last_arrival: now,
+        }
+    }
+}
+
+pub struct ReorderBuffer {
+    hold: Duration,
+    // Keyed by sender and media type.
+    tracks: HashMap<(String, i32), Track>,
+    arrivals: u64,
+    depth: usize,
+    stats: ReorderStats,
+}
+
+impl ReorderBuffer {
+    pub fn new(hold: Duration) -> Self {
+        Self {
+            hold,
+            tracks: HashMap::new(),
+            arrivals: 0,
+            depth: 0,
+            stats: ReorderStats::default(),
+        }
+    }
+
+    pub fn push(&mut self, packet: MediaPacket, now: Instant) {
+        let track = self
+            .tracks
+            .entry((packet.email.clone(), packet.media_type.value()))
+            .or_insert_with(|| Track::new(now));
+        track.last_arrival = now;
+        let timestamp = packet.timestamp;
+        if track.last_released.is_some_and(|last| timestamp < last) {
+            self.stats.dropped_late += 1;
+            return;
+        }
+        match track.newest_received {
+            Some(newest) if timestamp < newest => self.stats.reordered += 1,
+            _ => track.newest_received = Some(timestamp),
+        }
+        let key = OrderKey {
+            timestamp,
+            arrival: self.arrivals,
+        };
+        self.arrivals += 1;
+        track.pending.insert(key, (now, packet));
+        self.depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth);
+    }
+
+    /// Returns the packets whose hold time is over, in timestamp order within each track, and
+    /// forgets the tracks that have been idle for too long.
+    pub fn pop_ready(&mut self, now: Instant) -> Vec<MediaPacket> {
+        let hold = self.hold;
+        let mut ready = Vec::new();
+        for track in self.tracks.values_mut() {
+            let cutoff = track
+                .pending
+                .iter()
+                .filter(|(_, (arrived, _))| now.duration_since(*arrived) >= hold)
+                .map(|(key, _)| *key)
+                .max();
+            let Some(cutoff) = cutoff else {
+                continue;
+            };
+            let mut later = track.pending.split_off(&cutoff);
+            let last = later.pop_first();
+            let earlier = std::mem::replace(&mut track.pending, later);
+            for (_, (_, packet)) in earlier.into_iter().chain(last) {
+                ready.push(packet);
+            }
+            track.last_released = Some(cutoff.timestamp);
+        }
+        self.tracks.retain(|_, track| {
+            !track.pending.is_empty() || now.duration_since(track.last_arrival) < TRACK_IDLE_TIMEOUT
+        });
+        self.depth -= ready.len();
+        self.stats.released += ready.len() as u64;
+        ready
+    }
+
+    /// Number of tracks currently known.
+    pub fn tracks(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Number of packets currently held.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn stats(&self) -> ReorderStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use videocall_types::protos::media_packet::media_packet::MediaType;
+
+    fn packet(email: &str, media_type: MediaType, timestamp: f64) -> MediaPacket {
+        MediaPacket {
+            email: email.to_string(),
+            media_type: media_type.into(),
+            timestamp,
+            ..Default::default()
+        }
+    }
+
+    fn timestamps(packets: &[MediaPacket]) -> Vec<f64> {
+        packets.iter().map(|packet| packet.timestamp).collect()
+    }
+
+    #[test]
+    fn releases_in_timestamp_order_after_the_hold_time() {
+        let hold = Duration::from_millis(100);
+        let mut buffer = ReorderBuffer::new(hold);
+        let start = Instant::now();
+        for (i, timestamp) in [10.0, 30.0, 20.0, 40.0].iter().enumerate() {
+            let arrival = start + Duration::from_millis(i as u64 * 10);
+            buffer.push(packet("alice", MediaType::VIDEO, *timestamp), arrival);
+        }
+        assert!(buffer
+            .pop_ready(start + Duration::from_millis(50))
+            .is_empty());
+        assert_eq!(buffer.depth(), 4);
+
+        // 30 and 20 arrived at 10ms and 20ms, so both are due at 120ms, along with 10.
+        let ready = buffer.pop_ready(start + Duration::from_millis(120));
+        assert_eq!(timestamps(&ready), vec![10.0, 20.0, 30.0]);
+        let ready = buffer.pop_ready(start + Duration::from_millis(130));
+        assert_eq!(timestamps(&ready), vec![40.0]);
+        assert_eq!(
+            buffer.stats(),
+            ReorderStats {
+                released: 4,
+                reordered: 1,
+                dropped_late: 0,
+                max_depth: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn drops_packets_behind_what_was_released() {
+        let mut buffer = ReorderBuffer::new(Duration::ZERO);
+        let now = Instant::now();
+        buffer.push(packet("alice", MediaType::AUDIO, 20.0), now);
+        assert_eq!(timestamps(&buffer.pop_ready(now)), vec![20.0]);
+
+        buffer.push(packet("alice", MediaType::AUDIO, 10.0), now);
+        assert!(buffer.pop_ready(now).is_empty());
+        assert_eq!(buffer.stats().dropped_late, 1);
+        assert_eq!(buffer.depth(), 0);
+    }
+
+    #[test]
+    fn tracks_are_ordered_independently() {
+        let mut buffer = ReorderBuffer::new(Duration::ZERO);
+        let now = Instant::now();
+        buffer.push(packet("alice", MediaType::VIDEO, 50.0), now);
+        assert_eq!(buffer.pop_ready(now).len(), 1);
+
+        // Earlier timestamps on other tracks aren't late.
+        buffer.push(packet("alice", MediaType::AUDIO, 10.0), now);
+        buffer.push(packet("bob", MediaType::VIDEO, 10.0), now);
+        assert_eq!(buffer.pop_ready(now).len(), 2);
+        assert_eq!(buffer.stats().dropped_late, 0);
+    }
+
+    #[test]
+    fn forgets_idle_tracks() {
+        let mut buffer = ReorderBuffer::new(Duration::ZERO);
+        let start = Instant::now();
+        buffer.push(packet("alice", MediaType::VIDEO, 10.0), start);
+        buffer.push(packet("bob", MediaType::VIDEO, 10.0), start);
+        assert_eq!(buffer.pop_ready(start).len(), 2);
+        assert_eq!(buffer.tracks(), 2);
+
+        let later = start + TRACK_IDLE_TIMEOUT;
+        buffer.push(packet("bob", MediaType::VIDEO, 20.0), later);
+        assert_eq!(buffer.pop_ready(later).len(), 1);
+        assert_eq!(buffer.tracks(), 1);
+    }
+}
@@ -0,0 +1,89 @@
+//
+// Audio level measurement, shared by the microphone level meter and the active speaker
+// detection on decoded audio.
+//
+
+use web_sys::{AudioData, AudioDataCopyToOptions, AudioSampleFormat};
+
+// Weight of the newest block of samples in the smoothed level.
+const LEVEL_SMOOTHING: f32 = 0.3;
+
+/// Root mean square of `samples`, from 0.0 for silence to 1.0 for a full-scale square wave.
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = samples.iter().map(|sample| sample * sample).sum();
+    (sum / samples.len() as f32).sqrt()
+}
+
+/// Moves the smoothed level `level` towards the level `rms` of the newest samples.
+pub fn smooth_level(level: f32, rms: f32) -> f32 {
+    level + LEVEL_SMOOTHING * (rms - level)
+}
+
+/// RMS of the first channel of `audio_data`, which is all a level needs for mono audio.
+pub fn audio_data_rms(audio_data: &AudioData) -> f32 {
+    let samples = js_sys::Float32Array::new_with_length(audio_data.number_of_frames());
+    let mut options = AudioDataCopyToOptions::new(0);
+    options.format(AudioSampleFormat::F32Planar);
+    audio_data.copy_to_with_buffer_source(&samples, &options);
+    rms(&samples.to_vec())
+}
+
+/// Smooths the levels of consecutive blocks of audio and reports them at a limited rate.
+#[derive(Debug)]
+pub struct LevelMeter {
+    // In microseconds, like AudioData timestamps.
+    interval: f64,
+    level: f32,
+    last_report: Option<f64>,
+}
+
+impl LevelMeter {
+    pub fn new(reports_per_second: u32) -> Self {
+        Self {
+            interval: 1_000_000.0 / reports_per_second as f64,
+            level: 0.0,
+            last_report: None,
+        }
+    }
+
+    /// Adds the level `rms` of the block of audio at `timestamp` in microseconds, and returns the
+    /// smoothed level, clamped to 0.0..=1.0, when it is time to report it.
+    pub fn push(&mut self, rms: f32, timestamp: f64) -> Option<f32> {
+        self.level = smooth_level(self.level, rms);
+        match self.last_report {
+            Some(last) if timestamp - last < self.interval => None,
+            _ => {
+                self.last_report = Some(timestamp);
+                Some(self.level.clamp(0.0, 1.0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rms_of_full_scale_and_silence() {
+        assert_eq!(rms(&[1.0, -1.0, 1.0, -1.0]), 1.0);
+        assert_eq!(rms(&[0.0; 4]), 0.0);
+        assert_eq!(rms(&[]), 0.0);
+    }
+
+    #[test]
+    fn level_meter_reports_smoothed_levels_at_its_rate() {
+        let mut meter = LevelMeter::new(20);
+        // 10ms blocks of full-scale audio.
+        let reports: Vec<Option<f32>> = (0..6)
+            .map(|block| meter.push(1.0, block as f64 * 10_000.0))
+            .collect();
+        assert_eq!(reports[0], Some(0.3));
+        assert_eq!(&reports[1..5], &[None; 4]);
+        let level = reports[5].unwrap();
+        assert!(level > 0.8 && level < 1.0, "{}", level);
+    }
+}
@@ -1,3 +1,3 @@
 mod video_call_client;
 
-pub use video_call_client::{VideoCallClient, VideoCallClientOptions};
+pub use video_call_client::{display_name_hash, VideoCallClient, VideoCallClientOptions};
@@ -1,30 +1,61 @@
-use super::super::connection::{ConnectOptions, Connection};
-use super::super::decode::{PeerDecodeManager, PeerStatus};
-use crate::crypto::aes::Aes128State;
+use super::super::connection::{ConnectOptions, Connection, TransportPolicy, TransportType};
+use super::super::decode::{
+    ActiveSpeakerDetector, MediaState, PeerDecodeError, PeerDecodeManager, PeerStats, PeerStatus,
+    RenderTarget,
+};
+use crate::clock::{BrowserClock, Clock, Timer};
+use crate::codec::{decodable_codecs, VideoCodec};
+use crate::constants::{
+    ACTIVE_SPEAKER_HOLD, ACTIVE_SPEAKER_THRESHOLD, BITRATE_CAP_DURATION,
+    EXPECTED_HEARTBEATS_PER_PEER, KEY_ROTATION_DELAY,
+};
+use crate::crypto::aes::{Aes128State, DecryptErrorKind, MediaKey};
 use crate::crypto::rsa::RsaWrapper;
+use crate::diagnostics::start_drop_log;
+use crate::encode::transform::{transform_app_data, transform_keyframe_request};
+use crate::encode::SenderStats;
+use crate::network_quality::{NetworkQuality, NetworkQualityThresholds};
 use anyhow::{anyhow, Result};
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use protobuf::Message;
 use rsa::pkcs8::{DecodePublicKey, EncodePublicKey};
 use rsa::RsaPublicKey;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::{Rc, Weak};
+use std::time::Duration;
 use videocall_types::protos::aes_packet::AesPacket;
+use videocall_types::protos::congestion_packet::CongestionPacket;
 use videocall_types::protos::media_packet::media_packet::MediaType;
+use videocall_types::protos::media_packet::HeartbeatMetadata;
 use videocall_types::protos::packet_wrapper::packet_wrapper::PacketType;
 use videocall_types::protos::packet_wrapper::PacketWrapper;
 use videocall_types::protos::rsa_packet::RsaPacket;
 use wasm_bindgen::JsValue;
+use web_sys::MediaStreamTrack;
 use yew::prelude::Callback;
 
 /// Options struct for constructing a client via [VideoCallClient::new(options)][VideoCallClient::new]
+///
+/// All options but the userid and the urls have a default, so only the ones that matter need to
+/// be set:
+///
+/// ```ignore
+/// let options = VideoCallClientOptions {
+///     userid,
+///     websocket_url,
+///     webtransport_url,
+///     on_connected,
+///     ..Default::default()
+/// };
+/// ```
 #[derive(Clone, Debug, PartialEq)]
 pub struct VideoCallClientOptions {
     /// `true` to use end-to-end encription; `false` to send data unencrypted
     pub enable_e2ee: bool,
 
-    /// `true` to use webtransport, `false` to use websocket
-    pub enable_webtransport: bool,
+    /// Which of WebTransport and WebSocket to connect with, see [TransportPolicy]
+    pub transport_policy: TransportPolicy,
 
     /// Callback will be called as `callback(peer_userid)` when a new peer is added
     pub on_peer_added: Callback<String>,
@@ -32,14 +63,29 @@ pub struct VideoCallClientOptions {
     /// Callback will be called as `callback(peer_userid, media_type)` immediately after the first frame of a given peer & media type is decoded
     pub on_peer_first_frame: Callback<(String, MediaType)>,
 
+    /// Callback will be called as `callback(peer_userid, media_type)` when a peer stops sending a
+    /// given media type, e.g. when it turns off its camera or stops sharing its screen.  The peer's
+    /// canvas has already been cleared by then.
+    pub on_peer_stream_ended: Callback<(String, MediaType)>,
+
     /// Callback will be called as `callback(peer_userid)` and must return the DOM id of the
-    /// `HtmlCanvasElement` into which the peer video should be rendered
+    /// `HtmlCanvasElement` into which the peer video should be rendered.
+    ///
+    /// It is called again whenever the peer is re-added, e.g. after a reconnect, so the id it
+    /// returns must depend on `peer_userid` only (not on the order in which peers showed up) for
+    /// each peer to keep its canvas.
     pub get_peer_video_canvas_id: Callback<String, String>,
 
     /// Callback will be called as `callback(peer_userid)` and must return the DOM id of the
-    /// `HtmlCanvasElement` into which the peer screen image should be rendered
+    /// `HtmlCanvasElement` into which the peer screen image should be rendered.  The same rules
+    /// as for [`get_peer_video_canvas_id`](Self::get_peer_video_canvas_id) apply.
     pub get_peer_screen_canvas_id: Callback<String, String>,
 
+    /// Where the peers' decoded media goes: painted onto the canvases above and played, which is
+    /// the default, or handed to a callback as raw `VideoFrame`s and `AudioData`, in which case
+    /// the canvas ids aren't used and no DOM elements are needed.
+    pub render_target: RenderTarget,
+
     /// The current client's userid.  This userid will appear as this client's `peer_userid` in the
     /// remote peers' clients.
     pub userid: String,
@@ -53,23 +99,185 @@ pub struct VideoCallClientOptions {
     /// Callback will be called as `callback(())` after a new connection is made
     pub on_connected: Callback<()>,
 
-    /// Callback will be called as `callback(())` if a connection gets dropped
+    /// Callback will be called as `callback(transport_type)` after a new connection is made, and
+    /// again if the client migrates from WebTransport to WebSocket because WebTransport degraded.
+    /// With [TransportPolicy::Race] it reports the transport that won.
+    pub on_transport_selected: Callback<TransportType>,
+
+    /// Callback will be called as `callback(())` if a connection gets dropped.  It isn't called
+    /// when the client falls back to WebSocket instead, see [TransportPolicy].
     pub on_connection_lost: Callback<JsValue>,
+
+    /// If set, the connection is closed once nothing has been received from the server and no
+    /// media has been sent for this long.  Outgoing heartbeats don't count as activity.
+    pub idle_timeout: Option<Duration>,
+
+    /// Callback will be called as `callback(())` when the connection was closed because of
+    /// [`idle_timeout`](Self::idle_timeout).  `on_connection_lost` is not called in that case.
+    pub on_idle_timeout: Callback<()>,
+
+    /// Callback will be called as `callback(settings)` with a description of the camera
+    /// encoder's settings, e.g. `"codec: VP9"`, each time it starts encoding.
+    pub on_encoder_settings_update: Callback<String>,
+
+    /// Callback will be called as `callback(stats)` every second while the camera or screen
+    /// encoder is running, and once more when it stops, with the frames it encoded and dropped.
+    /// Frames dropped before encoding mean that the encoder can't keep up, as opposed to the
+    /// network.
+    pub on_sender_stats_update: Callback<SenderStats>,
+
+    /// Callback will be called as `callback(peer_userid, channel, data)` for each message a peer
+    /// sent with [`send_app_data`](VideoCallClient::send_app_data).
+    pub on_app_data: Callback<(String, u32, Vec<u8>)>,
+
+    /// Callback will be called as `callback(peer_userid, media_state)` with the media a peer
+    /// sends, after its first heartbeat and whenever it turns a media type on or off, e.g. mutes
+    /// its microphone.  Peers announce such changes right away, so this is the authoritative
+    /// source for mute indicators.
+    pub on_peer_media_state: Callback<(String, MediaState)>,
+
+    /// Callback will be called as `callback(peer_userid)` when a peer becomes the active speaker,
+    /// i.e. the loudest peer whose audio level is at least
+    /// [`active_speaker_threshold`](Self::active_speaker_threshold) for
+    /// [`active_speaker_hold`](Self::active_speaker_hold).  The active speaker stays the same
+    /// while nobody speaks.
+    pub on_active_speaker: Callback<String>,
+
+    /// RMS level of a peer's decoded audio, from 0.0 to 1.0, from which it may become the active
+    /// speaker.  Around 0.02 tells speech from background noise on a typical microphone.
+    pub active_speaker_threshold: f32,
+
+    /// For how long a peer must be the loudest before it becomes the active speaker, so that
+    /// short noises don't steal the spotlight.
+    pub active_speaker_hold: Duration,
+
+    /// Callback will be called as `callback(peer_userid, quality)` with a rating of the network
+    /// path from a peer, e.g. for signal bars, after the peer's first 5 seconds and whenever the
+    /// rating changes.  It is based on the peer's packet loss and heartbeat jitter, as rated by
+    /// [`network_quality_thresholds`](Self::network_quality_thresholds).
+    pub on_network_quality_update: Callback<(String, NetworkQuality)>,
+
+    /// Loss and jitter limits of the network quality ratings; see [NetworkQualityThresholds]
+    /// for their defaults.
+    pub network_quality_thresholds: NetworkQualityThresholds,
+
+    /// Callback will be called as `callback(generation)` each time the client starts encrypting
+    /// its media with a new key.  With E2EE the key is replaced whenever a participant joins or
+    /// leaves, so that they can't decrypt what is sent while they aren't in the call.
+    pub on_key_rotated: Callback<u32>,
+
+    /// Callback will be called as `callback(peer_userid, kind)` when media from a peer can't be
+    /// decrypted, e.g. because its key hasn't arrived yet or the packet was altered.  It is called
+    /// for the first such packet and then at most every 5 seconds while the peer's media keeps
+    /// failing; see [peer_stats](VideoCallClient::peer_stats) for the counts.
+    pub on_decryption_error: Callback<(String, DecryptErrorKind)>,
+
+    /// If `true`, the client re-dials the server when an established connection is lost, waiting
+    /// from 1 up to 30 seconds between attempts, and carries on with the call once it is back:
+    /// the encoders keep running, the media state and the peers' keys are kept, and key frames are
+    /// requested from the peers.  [`on_connection_lost`](Self::on_connection_lost) isn't called
+    /// in that case.
+    pub enable_auto_reconnect: bool,
+
+    /// Callback will be called as `callback(attempt)` when the client is about to re-dial the
+    /// server, with attempts numbered from 1, if
+    /// [`enable_auto_reconnect`](Self::enable_auto_reconnect) is set.
+    pub on_reconnecting: Callback<u32>,
+
+    /// Callback will be called after [`on_connected`](Self::on_connected) when the client got
+    /// connected again after [`on_reconnecting`](Self::on_reconnecting).
+    pub on_reconnected: Callback<()>,
+}
+
+/// Unencrypted, preferring WebTransport, without idle timeout nor auto-reconnect, and with
+/// callbacks that do nothing.  A peer's video is rendered into the canvas whose id is its userid,
+/// and its screen into `screen-share-<userid>`.
+impl Default for VideoCallClientOptions {
+    fn default() -> Self {
+        Self {
+            enable_e2ee: false,
+            transport_policy: TransportPolicy::default(),
+            on_peer_added: Callback::noop(),
+            on_peer_first_frame: Callback::noop(),
+            on_peer_stream_ended: Callback::noop(),
+            get_peer_video_canvas_id: Callback::from(|userid| userid),
+            get_peer_screen_canvas_id: Callback::from(|userid| format!("screen-share-{}", userid)),
+            render_target: RenderTarget::default(),
+            userid: String::new(),
+            websocket_url: String::new(),
+            webtransport_url: String::new(),
+            on_connected: Callback::noop(),
+            on_transport_selected: Callback::noop(),
+            on_connection_lost: Callback::noop(),
+            idle_timeout: None,
+            on_idle_timeout: Callback::noop(),
+            on_encoder_settings_update: Callback::noop(),
+            on_sender_stats_update: Callback::noop(),
+            on_app_data: Callback::noop(),
+            on_peer_media_state: Callback::noop(),
+            on_active_speaker: Callback::noop(),
+            active_speaker_threshold: ACTIVE_SPEAKER_THRESHOLD,
+            active_speaker_hold: ACTIVE_SPEAKER_HOLD,
+            on_network_quality_update: Callback::noop(),
+            network_quality_thresholds: NetworkQualityThresholds::default(),
+            on_key_rotated: Callback::noop(),
+            on_decryption_error: Callback::noop(),
+            enable_auto_reconnect: false,
+            on_reconnecting: Callback::noop(),
+            on_reconnected: Callback::noop(),
+        }
+    }
 }
 
 #[derive(Debug)]
 struct InnerOptions {
     enable_e2ee: bool,
     userid: String,
+    transport_policy: TransportPolicy,
     on_peer_added: Callback<String>,
+    on_transport_selected: Callback<TransportType>,
+    on_decryption_error: Callback<(String, DecryptErrorKind)>,
+    enable_auto_reconnect: bool,
+    on_reconnecting: Callback<u32>,
+    on_reconnected: Callback<()>,
 }
 
+// Number of monitor runs in a row with more than half the heartbeats missing before the client
+// moves off WebTransport.
+const DEGRADED_WINDOWS_BEFORE_MIGRATION: u8 = 3;
+
+// Shortest and longest wait before re-dialing the server, doubling with each failed attempt.
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
 #[derive(Debug)]
 struct Inner {
     options: InnerOptions,
     connection: Option<Connection>,
-    aes: Rc<Aes128State>,
+    // WebSocket connection being established to replace a degraded WebTransport connection, or
+    // racing the WebTransport connection.
+    pending_connection: Option<Connection>,
+    racing: bool,
+    connect_options: Option<ConnectOptions>,
+    degraded_windows: u8,
+    // Number of the current attempt to reconnect, 0 when not reconnecting, and the timer of the
+    // next one.
+    reconnect_attempt: u32,
+    reconnect_timer: Option<Timer>,
+    // Summarizes the frames the client dropped, as long as it exists.
+    _drop_log_timer: Timer,
+    // Heartbeat loss measured by the last peer monitor run, numbered so that encoders can tell
+    // new measurements from the ones they've seen.
+    link_loss: Option<(u32, f64)>,
+    // Bitrate cap from the last congestion feedback for us, with when it arrived.
+    bitrate_cap: Option<(u32, f64)>,
+    clock: Rc<dyn Clock>,
+    aes: Rc<MediaKey>,
     rsa: Rc<RsaWrapper>,
+    // Public keys of the peers, to send them our new key when it is rotated.
+    peer_public_keys: HashMap<String, RsaPublicKey>,
+    heartbeat_metadata: Rc<RefCell<HeartbeatMetadata>>,
+    preferred_codecs: Vec<VideoCodec>,
     peer_decode_manager: PeerDecodeManager,
 }
 
@@ -83,7 +291,11 @@ struct Inner {
 pub struct VideoCallClient {
     options: VideoCallClientOptions,
     inner: Rc<RefCell<Inner>>,
-    aes: Rc<Aes128State>,
+    aes: Rc<MediaKey>,
+    heartbeat_metadata: Rc<RefCell<HeartbeatMetadata>>,
+    clock: Rc<dyn Clock>,
+    // Number of key frame requests received, for the encoders to tell new ones from seen ones.
+    keyframe_requests: Rc<Cell<u32>>,
 }
 
 impl PartialEq for VideoCallClient {
@@ -98,22 +310,73 @@ impl VideoCallClient {
     /// See [VideoCallClientOptions] for description of the options.
     ///
     pub fn new(options: VideoCallClientOptions) -> Self {
-        let aes = Rc::new(Aes128State::new(options.enable_e2ee));
+        Self::with_clock(options, Rc::new(BrowserClock))
+    }
+
+    /// Like [new](Self::new), with the heartbeats, the peer monitor, the idle timeout,
+    /// decrypt-failure reporting, the dropped frames log and recordings timed by `clock` instead
+    /// of the browser's clock.  Tests can pass a [ManualClock](crate::ManualClock) to run them
+    /// without waiting.
+    pub fn with_clock(options: VideoCallClientOptions, clock: Rc<dyn Clock>) -> Self {
+        let aes = Rc::new(MediaKey::new(
+            options.enable_e2ee,
+            KEY_ROTATION_DELAY,
+            Rc::clone(&clock),
+            options.on_key_rotated.clone(),
+        ));
+        let heartbeat_metadata = Rc::new(RefCell::new(HeartbeatMetadata::default()));
+        let keyframe_requests = Rc::new(Cell::new(0));
         let inner = Rc::new(RefCell::new(Inner {
             options: InnerOptions {
                 enable_e2ee: options.enable_e2ee,
                 userid: options.userid.clone(),
+                transport_policy: options.transport_policy,
                 on_peer_added: options.on_peer_added.clone(),
+                on_transport_selected: options.on_transport_selected.clone(),
+                on_decryption_error: options.on_decryption_error.clone(),
+                enable_auto_reconnect: options.enable_auto_reconnect,
+                on_reconnecting: options.on_reconnecting.clone(),
+                on_reconnected: options.on_reconnected.clone(),
             },
             connection: None,
+            pending_connection: None,
+            racing: false,
+            connect_options: None,
+            degraded_windows: 0,
+            reconnect_attempt: 0,
+            reconnect_timer: None,
+            _drop_log_timer: start_drop_log(Rc::clone(&clock)),
+            link_loss: None,
+            bitrate_cap: None,
+            clock: Rc::clone(&clock),
             aes: aes.clone(),
             rsa: Rc::new(RsaWrapper::new(options.enable_e2ee)),
-            peer_decode_manager: Self::create_peer_decoder_manager(&options),
+            peer_public_keys: HashMap::new(),
+            heartbeat_metadata: heartbeat_metadata.clone(),
+            preferred_codecs: vec![VideoCodec::VP9],
+            peer_decode_manager: Self::create_peer_decoder_manager(
+                &options,
+                &clock,
+                &keyframe_requests,
+            ),
         }));
+        {
+            let heartbeat_metadata = heartbeat_metadata.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                heartbeat_metadata.borrow_mut().video_codecs = decodable_codecs()
+                    .await
+                    .iter()
+                    .map(|codec| codec.codec_string().to_owned())
+                    .collect();
+            });
+        }
         Self {
             options,
             aes,
             inner,
+            heartbeat_metadata,
+            clock,
+            keyframe_requests,
         }
     }
 
@@ -121,8 +384,8 @@ impl VideoCallClient {
     ///
     /// Initiates a connection using WebTransport (to
     /// [`options.webtransport_url`](VideoCallClientOptions::webtransport_url)) or WebSocket (to
-    /// [`options.websocket_url`](VideoCallClientOptions::websocket_url)), based on the value of
-    /// [`options.enable_webtransport`](VideoCallClientOptions::enable_webtransport).
+    /// [`options.websocket_url`](VideoCallClientOptions::websocket_url)), or both, based on the
+    /// value of [`options.transport_policy`](VideoCallClientOptions::transport_policy).
     ///
     /// Note that this method's success means only that it succesfully *attempted* initiation of the
     /// connection.  The connection cannot actually be considered to have been succesful until the
@@ -158,8 +421,8 @@ impl VideoCallClient {
                 let callback = self.options.on_connected.clone();
                 Callback::from(move |_| {
                     if let Some(inner) = Weak::upgrade(&inner) {
-                        match inner.try_borrow() {
-                            Ok(inner) => inner.send_public_key(),
+                        match inner.try_borrow_mut() {
+                            Ok(mut inner) => inner.on_connected(),
                             Err(_) => {
                                 error!("Unable to borrow inner -- not sending public key");
                             }
@@ -168,7 +431,33 @@ impl VideoCallClient {
                     callback.emit(());
                 })
             },
-            on_connection_lost: self.options.on_connection_lost.clone(),
+            on_connection_lost: {
+                let inner = Rc::downgrade(&self.inner);
+                let callback = self.options.on_connection_lost.clone();
+                Callback::from(move |e: JsValue| {
+                    let inner = inner.clone();
+                    let callback = callback.clone();
+                    // The connection can't be dropped from within its own callback, so defer it.
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let handled = match Weak::upgrade(&inner) {
+                            Some(strong) => match strong.try_borrow_mut() {
+                                Ok(mut borrowed) => {
+                                    borrowed.on_connection_lost()
+                                        || borrowed.schedule_reconnect(&inner)
+                                }
+                                Err(_) => {
+                                    error!("Unable to borrow inner -- not falling back");
+                                    false
+                                }
+                            },
+                            None => false,
+                        };
+                        if !handled {
+                            callback.emit(e);
+                        }
+                    });
+                })
+            },
             peer_monitor: {
                 let inner = Rc::downgrade(&self.inner);
                 let on_connection_lost = self.options.on_connection_lost.clone();
@@ -176,7 +465,7 @@ impl VideoCallClient {
                     if let Some(inner) = Weak::upgrade(&inner) {
                         match inner.try_borrow_mut() {
                             Ok(mut inner) => {
-                                inner.peer_decode_manager.run_peer_monitor();
+                                inner.run_peer_monitor();
                             }
                             Err(_) => {
                                 on_connection_lost.emit(JsValue::from_str(
@@ -187,31 +476,74 @@ impl VideoCallClient {
                     }
                 })
             },
+            idle_timeout: self.options.idle_timeout,
+            on_idle_timeout: {
+                let inner = Rc::downgrade(&self.inner);
+                let callback = self.options.on_idle_timeout.clone();
+                Callback::from(move |_| {
+                    let inner = inner.clone();
+                    // The connection can't be dropped from within its own timer, so defer it.
+                    wasm_bindgen_futures::spawn_local(async move {
+                        if let Some(inner) = Weak::upgrade(&inner) {
+                            match inner.try_borrow_mut() {
+                                Ok(mut inner) => {
+                                    inner.connection.take();
+                                }
+                                Err(_) => {
+                                    error!("Unable to borrow inner -- not closing idle connection");
+                                }
+                            }
+                        }
+                    });
+                    callback.emit(());
+                })
+            },
+            clock: Rc::clone(&self.clock),
         };
-        info!(
-            "webtransport connect = {}",
-            self.options.enable_webtransport
-        );
+        info!("transport policy = {:?}", self.options.transport_policy);
         info!(
             "end to end encryption enabled = {}",
             self.options.enable_e2ee
         );
 
         let mut borrowed = self.inner.try_borrow_mut()?;
-        borrowed.connection.replace(Connection::connect(
-            self.options.enable_webtransport,
-            options,
-            self.aes.clone(),
-        )?);
+        borrowed.connect_options = Some(options);
+        borrowed.reconnect_attempt = 0;
+        borrowed.reconnect_timer = None;
+        borrowed.dial()?;
         info!("Connected to server");
         Ok(())
     }
 
-    fn create_peer_decoder_manager(opts: &VideoCallClientOptions) -> PeerDecodeManager {
+    fn create_peer_decoder_manager(
+        opts: &VideoCallClientOptions,
+        clock: &Rc<dyn Clock>,
+        keyframe_requests: &Rc<Cell<u32>>,
+    ) -> PeerDecodeManager {
         let mut peer_decode_manager = PeerDecodeManager::new();
         peer_decode_manager.on_first_frame = opts.on_peer_first_frame.clone();
+        peer_decode_manager.on_stream_ended = opts.on_peer_stream_ended.clone();
         peer_decode_manager.get_video_canvas_id = opts.get_peer_video_canvas_id.clone();
         peer_decode_manager.get_screen_canvas_id = opts.get_peer_screen_canvas_id.clone();
+        peer_decode_manager.render_target = opts.render_target.clone();
+        peer_decode_manager.on_app_data = opts.on_app_data.clone();
+        peer_decode_manager.on_media_state = opts.on_peer_media_state.clone();
+        peer_decode_manager.on_active_speaker = opts.on_active_speaker.clone();
+        peer_decode_manager.on_network_quality = opts.on_network_quality_update.clone();
+        peer_decode_manager.network_quality_thresholds = opts.network_quality_thresholds;
+        peer_decode_manager.active_speaker =
+            ActiveSpeakerDetector::new(opts.active_speaker_threshold, opts.active_speaker_hold);
+        peer_decode_manager.clock = Rc::clone(clock);
+        peer_decode_manager.enable_e2ee = opts.enable_e2ee;
+        peer_decode_manager.on_keyframe_request = {
+            let userid = opts.userid.clone();
+            let keyframe_requests = Rc::clone(keyframe_requests);
+            Callback::from(move |target: String| {
+                if target == userid {
+                    keyframe_requests.set(keyframe_requests.get().wrapping_add(1));
+                }
+            })
+        };
         peer_decode_manager
     }
 
@@ -224,6 +556,22 @@ impl VideoCallClient {
         }
     }
 
+    /// Sends an application-defined message to all peers in the meeting, who receive it through
+    /// their [`on_app_data`](VideoCallClientOptions::on_app_data) callback.  `channel` is up to the
+    /// application, e.g. to tell chat messages from reactions.
+    ///
+    /// The message is encrypted like media when E2EE is enabled.  With `reliable` set to `false`
+    /// it is sent as a WebTransport datagram, which is faster but may be lost or reordered; this
+    /// suits state that is resent frequently, like cursor positions.  WebSocket connections, and
+    /// messages too large for a datagram, are always sent reliably.
+    pub fn send_app_data(&self, channel: u32, data: Vec<u8>, reliable: bool) {
+        let packet = transform_app_data(channel, data, self.userid(), self.aes());
+        match self.inner.try_borrow() {
+            Ok(inner) => inner.send_app_data(packet, reliable),
+            Err(_) => error!("Unable to borrow inner -- dropping app data"),
+        }
+    }
+
     /// Returns `true` if the client is currently connected to a server.
     pub fn is_connected(&self) -> bool {
         if let Ok(inner) = self.inner.try_borrow() {
@@ -258,7 +606,270 @@ impl VideoCallClient {
         false
     }
 
-    pub(crate) fn aes(&self) -> Rc<Aes128State> {
+    /// Plays the audio of all remote peers on the given output device:
+    ///
+    /// * `device_id` - The value of `entry.device_id` for some entry in
+    ///   [`media_device_list.audio_outputs.devices()`](crate::MediaDeviceList::audio_outputs)
+    ///
+    /// Does nothing on browsers without output selection, see
+    /// [`MediaDeviceList::audio_output_selection_supported()`](crate::MediaDeviceList::audio_output_selection_supported).
+    pub fn set_audio_output(&self, device_id: &str) {
+        match self.inner.try_borrow_mut() {
+            Ok(mut inner) => inner.peer_decode_manager.set_audio_output(device_id),
+            Err(_) => error!("Unable to borrow inner -- not setting audio output"),
+        }
+    }
+
+    /// Announces our display name to the peers, as its [display_name_hash] in our heartbeats.
+    /// Peers see it in [peer_heartbeat_metadata](Self::peer_heartbeat_metadata) and can compare it
+    /// with the hash of the name they know to find out that it changed.
+    pub fn set_display_name(&self, name: &str) {
+        let hash = display_name_hash(name);
+        let previous = std::mem::replace(
+            &mut self.heartbeat_metadata.borrow_mut().display_name_hash,
+            hash,
+        );
+        if previous != hash {
+            self.send_heartbeat();
+        }
+    }
+
+    /// Returns the presence state last announced by the given peer in its heartbeats, or `None`
+    /// if the peer is unknown or its client doesn't send it.
+    pub fn peer_heartbeat_metadata(&self, key: &String) -> Option<HeartbeatMetadata> {
+        if let Ok(inner) = self.inner.try_borrow() {
+            if let Some(peer) = inner.peer_decode_manager.get(key) {
+                return peer.heartbeat_metadata.clone();
+            }
+        }
+        None
+    }
+
+    /// Returns the current active speaker, see
+    /// [`on_active_speaker`](VideoCallClientOptions::on_active_speaker).
+    pub fn active_speaker(&self) -> Option<String> {
+        match self.inner.try_borrow() {
+            Ok(inner) => inner
+                .peer_decode_manager
+                .active_speaker
+                .active_speaker()
+                .cloned(),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the quality of the network path from `peer_userid` as last rated, or `None` if
+    /// there is no such peer or it hasn't been rated yet.
+    pub fn peer_network_quality(&self, peer_userid: &str) -> Option<NetworkQuality> {
+        match self.inner.try_borrow() {
+            Ok(inner) => inner
+                .peer_decode_manager
+                .get(&peer_userid.to_owned())
+                .and_then(|peer| peer.network_quality()),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the codec `peer_userid`'s video is currently decoded with, or `None` if there is
+    /// no such peer.
+    pub fn peer_video_codec(&self, peer_userid: &str) -> Option<VideoCodec> {
+        match self.inner.try_borrow() {
+            Ok(inner) => inner
+                .peer_decode_manager
+                .get(&peer_userid.to_owned())
+                .map(|peer| peer.video.current_codec()),
+            Err(_) => None,
+        }
+    }
+
+    /// Stops or resumes decoding the camera video of `peer_userid`, e.g. for peers that aren't on
+    /// screen in a large meeting.  Their audio keeps playing.  The setting also applies if the
+    /// peer joins later or reconnects.
+    ///
+    /// The server keeps forwarding the video, which is dropped undecoded.  Once resumed, the video
+    /// is decoded from the next key frame on, which is requested from the peer right away, and
+    /// [`options.on_peer_first_frame`](VideoCallClientOptions::on_peer_first_frame) is called
+    /// again for it.
+    pub fn set_peer_video_enabled(&self, peer_userid: &str, enabled: bool) {
+        match self.inner.try_borrow_mut() {
+            Ok(mut inner) => inner
+                .peer_decode_manager
+                .set_peer_video_enabled(peer_userid, enabled),
+            Err(_) => error!("Unable to borrow inner -- not setting peer video"),
+        }
+    }
+
+    /// Asks `peer_userid` to send a key frame of its camera and screen share right away, rather
+    /// than waiting for the next periodic one.  The client already does so whenever a peer's
+    /// frames are dropped because its decoder awaits a key frame, e.g. after joining mid-stream.
+    ///
+    /// Requests are limited to one per second per peer; others are ignored.
+    pub fn request_keyframe(&self, peer_userid: &str) {
+        match self.inner.try_borrow_mut() {
+            Ok(mut inner) => inner.request_keyframe(peer_userid),
+            Err(_) => error!("Unable to borrow inner -- not requesting key frame"),
+        }
+    }
+
+    // Number of key frame requests received from peers so far.  The encoders send a key frame
+    // whenever it changes.
+    pub(crate) fn keyframe_requests(&self) -> u32 {
+        self.keyframe_requests.get()
+    }
+
+    // For each of `peers`, the id of its camera canvas and the tracks its decoded audio plays
+    // from, or None if it isn't connected.  Used by the recorder.
+    pub(crate) fn recording_sources(
+        &self,
+        peers: &[String],
+    ) -> Vec<Option<(String, Vec<MediaStreamTrack>)>> {
+        let Ok(inner) = self.inner.try_borrow() else {
+            return vec![None; peers.len()];
+        };
+        peers
+            .iter()
+            .map(|key| {
+                let peer = inner.peer_decode_manager.get(key)?;
+                let tracks = [&peer.audio, &peer.screen_audio]
+                    .into_iter()
+                    .filter_map(|decoder| decoder.audio_track().cloned())
+                    .collect();
+                Some((peer.video_canvas_id.clone(), tracks))
+            })
+            .collect()
+    }
+
+    /// Returns the peers whose media can't be decrypted, e.g. because their key got out of sync,
+    /// with the number of packets dropped since their key was last set.  Their media is dropped
+    /// until a new key arrives, which the client keeps requesting; other peers aren't affected.
+    pub fn peer_decrypt_failures(&self) -> Vec<(String, u32)> {
+        match self.inner.try_borrow() {
+            Ok(inner) => inner.peer_decode_manager.decrypt_failures(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the statistics of a peer, or `None` if it isn't connected.
+    pub fn peer_stats(&self, peer_userid: &str) -> Option<PeerStats> {
+        let inner = self.inner.try_borrow().ok()?;
+        let peer = inner.peer_decode_manager.get(&peer_userid.to_owned())?;
+        Some(peer.stats())
+    }
+
+    /// Sets the video codecs the camera encoder may use, most preferred first.  Defaults to
+    /// `[VideoCodec::VP9]`.
+    ///
+    /// When the camera encoder starts, it uses the first codec of the list that the browser can
+    /// encode and that every current peer announced it can decode, falling back to VP9 if there
+    /// is none.  The chosen codec is reported through
+    /// [`options.on_encoder_settings_update`](VideoCallClientOptions::on_encoder_settings_update).
+    pub fn set_preferred_codec_order(&self, codecs: &[VideoCodec]) {
+        match self.inner.try_borrow_mut() {
+            Ok(mut inner) => inner.preferred_codecs = codecs.to_vec(),
+            Err(_) => error!("Unable to borrow inner -- not setting preferred codecs"),
+        }
+    }
+
+    // The preferred codecs that all current peers can decode.  Peers that don't announce their
+    // codecs are assumed to only decode VP9.
+    pub(crate) fn video_codec_candidates(&self) -> Vec<VideoCodec> {
+        let Ok(inner) = self.inner.try_borrow() else {
+            return vec![VideoCodec::VP9];
+        };
+        let manager = &inner.peer_decode_manager;
+        inner
+            .preferred_codecs
+            .iter()
+            .copied()
+            .filter(|codec| {
+                manager.sorted_keys().iter().all(|key| {
+                    let metadata = manager
+                        .get(key)
+                        .and_then(|peer| peer.heartbeat_metadata.as_ref());
+                    match metadata {
+                        Some(metadata) if !metadata.video_codecs.is_empty() => metadata
+                            .video_codecs
+                            .iter()
+                            .any(|peer_codec| peer_codec == codec.codec_string()),
+                        _ => *codec == VideoCodec::VP9,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn report_encoder_settings(&self, settings: String) {
+        self.options.on_encoder_settings_update.emit(settings);
+    }
+
+    pub(crate) fn report_sender_stats(&self, stats: SenderStats) {
+        self.options.on_sender_stats_update.emit(stats);
+    }
+
+    // The client's clock time, in milliseconds.
+    pub(crate) fn now(&self) -> f64 {
+        self.clock.now()
+    }
+
+    pub(crate) fn clock(&self) -> Rc<dyn Clock> {
+        Rc::clone(&self.clock)
+    }
+
+    // The share of the peers' heartbeats that went missing on their way to us during the last
+    // peer monitor window, with the number of the window.  None until there is a peer to measure.
+    pub(crate) fn link_loss(&self) -> Option<(u32, f64)> {
+        self.inner.try_borrow().ok()?.link_loss
+    }
+
+    // The video bitrate in bits per second that the server's congestion feedback asked us to
+    // keep to, until BITRATE_CAP_DURATION has passed without more feedback.
+    pub(crate) fn bitrate_cap(&self) -> Option<u32> {
+        let (cap, received) = self.inner.try_borrow().ok()?.bitrate_cap?;
+        (self.clock.now() - received < BITRATE_CAP_DURATION.as_millis() as f64).then_some(cap)
+    }
+
+    // Records whether a local media type is being sent, announced to peers in our heartbeats.
+    // Changes are announced right away so that peers don't mistake them for a network stall.
+    pub(crate) fn set_media_enabled(&self, media_type: MediaType, enabled: bool) {
+        let changed = {
+            let mut metadata = self.heartbeat_metadata.borrow_mut();
+            if media_type == MediaType::AUDIO && !enabled {
+                metadata.speaking = false;
+            }
+            let flag = match media_type {
+                MediaType::AUDIO => &mut metadata.audio_enabled,
+                MediaType::VIDEO => &mut metadata.video_enabled,
+                MediaType::SCREEN => &mut metadata.screen_enabled,
+                // Screen audio is announced as part of the screen share.
+                MediaType::MEDIA_TYPE_UNSPECIFIED
+                | MediaType::HEARTBEAT
+                | MediaType::DATA
+                | MediaType::KEYFRAME_REQUEST
+                | MediaType::SCREEN_AUDIO => return,
+            };
+            std::mem::replace(flag, enabled) != enabled
+        };
+        if changed {
+            self.send_heartbeat();
+        }
+    }
+
+    // Records whether the microphone's smoothed audio level `level` is that of speech, announced
+    // to peers in our next heartbeat.  It changes too often to be announced right away.
+    pub(crate) fn set_speaking(&self, level: f32) {
+        self.heartbeat_metadata.borrow_mut().speaking =
+            level >= self.options.active_speaker_threshold;
+    }
+
+    fn send_heartbeat(&self) {
+        if let Ok(inner) = self.inner.try_borrow() {
+            if let Some(connection) = &inner.connection {
+                connection.send_heartbeat();
+            }
+        }
+    }
+
+    pub(crate) fn aes(&self) -> Rc<MediaKey> {
         self.aes.clone()
     }
 
@@ -275,13 +886,295 @@ impl Inner {
         }
     }
 
+    fn send_app_data(&self, packet: PacketWrapper, reliable: bool) {
+        if let Some(connection) = &self.connection {
+            if reliable {
+                connection.send_packet(packet);
+            } else {
+                connection.send_packet_unreliable(packet);
+            }
+        }
+    }
+
+    fn on_connected(&mut self) {
+        // A migration or race completes once the replacement is up; dropping the old connection
+        // closes it.  A race also completes when the WebTransport connection wins.
+        if let Some(pending) = &self.pending_connection {
+            if pending.is_connected() {
+                self.connection = self.pending_connection.take();
+            } else if self.racing {
+                self.pending_connection = None;
+            }
+        }
+        self.racing = false;
+        self.send_public_key();
+        if let Some(connection) = &self.connection {
+            self.options
+                .on_transport_selected
+                .emit(connection.transport_type());
+        }
+        if self.reconnect_attempt > 0 {
+            info!("Reconnected after {} attempts", self.reconnect_attempt);
+            self.reconnect_attempt = 0;
+            // The peers' frames sent during the gap are gone, so their decoders need a fresh
+            // start.
+            for peer_userid in self.peer_decode_manager.sorted_keys().clone() {
+                self.request_keyframe(&peer_userid);
+            }
+            self.options.on_reconnected.emit(());
+        }
+    }
+
+    // Schedules the next attempt to re-dial the server once a connection that had been
+    // established is lost.  Returns whether the client is reconnecting, in which case the
+    // application isn't told about the loss.
+    fn schedule_reconnect(&mut self, this: &Weak<RefCell<Inner>>) -> bool {
+        if !self.options.enable_auto_reconnect {
+            return false;
+        }
+        if self.reconnect_attempt == 0
+            && !self
+                .connection
+                .as_ref()
+                .is_some_and(Connection::was_connected)
+        {
+            return false;
+        }
+        if self.connect_options.is_none() {
+            return false;
+        }
+        self.reconnect_attempt += 1;
+        let delay = RECONNECT_BACKOFF_MIN
+            .saturating_mul(1 << (self.reconnect_attempt - 1).min(16))
+            .min(RECONNECT_BACKOFF_MAX);
+        warn!(
+            "Connection lost, reconnecting in {:?} (attempt {})",
+            delay, self.reconnect_attempt
+        );
+        let this = Weak::clone(this);
+        self.reconnect_timer = Some(self.clock.every(
+            delay,
+            Box::new(move || {
+                let this = Weak::clone(&this);
+                // The timer can't be dropped from within its own callback, so defer it.
+                wasm_bindgen_futures::spawn_local(async move {
+                    if let Some(inner) = Weak::upgrade(&this) {
+                        match inner.try_borrow_mut() {
+                            Ok(mut inner) => inner.reconnect(&this),
+                            Err(_) => error!("Unable to borrow inner -- not reconnecting"),
+                        }
+                    }
+                });
+            }),
+        ));
+        self.connection = None;
+        self.pending_connection = None;
+        self.racing = false;
+        self.options.on_reconnecting.emit(self.reconnect_attempt);
+        true
+    }
+
+    fn reconnect(&mut self, this: &Weak<RefCell<Inner>>) {
+        if self.reconnect_timer.take().is_none() {
+            // connect() was called in the meantime.
+            return;
+        }
+        if let Err(e) = self.dial() {
+            error!("Failed to reconnect: {}", e.to_string());
+            self.schedule_reconnect(this);
+        }
+    }
+
+    // Connects to the server with the stored connect options according to the transport policy.
+    fn dial(&mut self) -> anyhow::Result<()> {
+        let options = self
+            .connect_options
+            .clone()
+            .ok_or_else(|| anyhow!("connect() was never called"))?;
+        self.pending_connection = None;
+        self.racing = false;
+        self.degraded_windows = 0;
+        let connect = |transport| {
+            Connection::connect(
+                transport,
+                options.clone(),
+                self.aes.clone(),
+                self.heartbeat_metadata.clone(),
+            )
+        };
+        let (connection, pending) = match self.options.transport_policy {
+            TransportPolicy::WebSocketOnly => (connect(TransportType::WebSocket)?, None),
+            TransportPolicy::WebTransportOnly => (connect(TransportType::WebTransport)?, None),
+            TransportPolicy::PreferWebTransport => match connect(TransportType::WebTransport) {
+                Ok(connection) => (connection, None),
+                Err(e) => {
+                    warn!(
+                        "WebTransport connect failed, falling back to WebSocket: {}",
+                        e
+                    );
+                    (connect(TransportType::WebSocket)?, None)
+                }
+            },
+            TransportPolicy::Race => match (
+                connect(TransportType::WebTransport),
+                connect(TransportType::WebSocket),
+            ) {
+                (Ok(webtransport), Ok(websocket)) => (webtransport, Some(websocket)),
+                (Ok(connection), Err(e)) | (Err(e), Ok(connection)) => {
+                    warn!("Not racing, one transport failed to connect: {}", e);
+                    (connection, None)
+                }
+                (Err(e), Err(_)) => return Err(e),
+            },
+        };
+        self.racing = pending.is_some();
+        self.pending_connection = pending;
+        self.connection.replace(connection);
+        Ok(())
+    }
+
+    fn run_peer_monitor(&mut self) {
+        // While migrating or racing, peers' heartbeats may arrive over both connections and be
+        // counted twice.  That can't hide a peer who left, so peers are still pruned, but the
+        // measurements based on the counts wait until the pending connection is settled.
+        let measure = self.pending_connection.is_none();
+        let received = self.peer_decode_manager.heartbeats_received();
+        let peers = self.peer_decode_manager.sorted_keys().len() as u32;
+        self.peer_decode_manager.run_peer_monitor(measure);
+        if self.peer_decode_manager.sorted_keys().len() as u32 != peers {
+            let manager = &self.peer_decode_manager;
+            self.peer_public_keys
+                .retain(|email, _| manager.get(email).is_some());
+            // Those who left must not be able to decrypt what we send from now on, not even with a
+            // key they were handed while it waited to be used.
+            self.rotate_key(true);
+        }
+        if measure {
+            self.measure_link_loss(received, peers);
+            self.check_transport_health(received, peers * EXPECTED_HEARTBEATS_PER_PEER);
+        }
+    }
+
+    // Allows each peer one heartbeat of slack, as its timer and the monitor drift apart.
+    fn measure_link_loss(&mut self, received: u32, peers: u32) {
+        if peers == 0 {
+            return;
+        }
+        let expected = peers * EXPECTED_HEARTBEATS_PER_PEER;
+        let missing = expected.saturating_sub(received + peers);
+        let window = self
+            .link_loss
+            .map_or(0, |(window, _)| window.wrapping_add(1));
+        self.link_loss = Some((window, missing as f64 / expected as f64));
+    }
+
+    // Returns whether the loss was handled by carrying on with another connection, in which case
+    // the application isn't told about it.
+    fn on_connection_lost(&mut self) -> bool {
+        let lost = |connection: &Option<Connection>| {
+            connection.as_ref().is_some_and(Connection::is_closed)
+        };
+        if lost(&self.pending_connection) {
+            // The migration or race is over, carry on with the current connection.
+            self.pending_connection = None;
+            self.racing = false;
+            return !lost(&self.connection);
+        }
+        if !lost(&self.connection) {
+            return false;
+        }
+        if self.racing {
+            self.racing = false;
+            self.connection = self.pending_connection.take();
+            return self.connection.is_some();
+        }
+        match &self.connection {
+            Some(connection)
+                if self.options.transport_policy == TransportPolicy::PreferWebTransport
+                    && connection.transport_type() == TransportType::WebTransport
+                    && !connection.was_connected() => {}
+            _ => return false,
+        }
+        let options = match &self.connect_options {
+            Some(options) if !options.websocket_url.is_empty() => options.clone(),
+            _ => return false,
+        };
+        warn!("WebTransport failed to connect, falling back to WebSocket");
+        match Connection::connect(
+            TransportType::WebSocket,
+            options,
+            self.aes.clone(),
+            self.heartbeat_metadata.clone(),
+        ) {
+            Ok(connection) => {
+                self.connection = Some(connection);
+                true
+            }
+            Err(e) => {
+                error!("Failed to connect WebSocket: {}", e.to_string());
+                false
+            }
+        }
+    }
+
+    // The server relays small packets such as heartbeats as datagrams over WebTransport, so
+    // missing peer heartbeats are a measure of the datagram loss on our path.
+    fn check_transport_health(&mut self, received: u32, expected: u32) {
+        if self.options.transport_policy == TransportPolicy::WebTransportOnly {
+            return;
+        }
+        match &self.connection {
+            Some(connection)
+                if connection.is_connected()
+                    && connection.transport_type() == TransportType::WebTransport => {}
+            _ => return,
+        }
+        if expected > 0 && received * 2 < expected {
+            self.degraded_windows += 1;
+        } else {
+            self.degraded_windows = 0;
+        }
+        if self.degraded_windows >= DEGRADED_WINDOWS_BEFORE_MIGRATION {
+            self.degraded_windows = 0;
+            self.migrate_to_websocket();
+        }
+    }
+
+    // Media keeps flowing over the old connection until the WebSocket is connected.
+    fn migrate_to_websocket(&mut self) {
+        let options = match &self.connect_options {
+            Some(options) if !options.websocket_url.is_empty() => options.clone(),
+            _ => return,
+        };
+        warn!("WebTransport is degraded, migrating to WebSocket");
+        match Connection::connect(
+            TransportType::WebSocket,
+            options,
+            self.aes.clone(),
+            self.heartbeat_metadata.clone(),
+        ) {
+            Ok(connection) => self.pending_connection = Some(connection),
+            Err(e) => error!("Failed to connect WebSocket: {}", e.to_string()),
+        }
+    }
+
     fn on_inbound_media(&mut self, response: PacketWrapper) {
         debug!(
             "<< Received {:?} from {}",
             response.packet_type.enum_value(),
             response.email
         );
+        // Sent by the server rather than by a peer.
+        if let Ok(PacketType::CONGESTION) = response.packet_type.enum_value() {
+            self.on_congestion(&response.data);
+            return;
+        }
         let peer_status = self.peer_decode_manager.ensure_peer(&response.email);
+        if let PeerStatus::Added(_) = peer_status {
+            // The new key is handed to the newcomer as soon as it sends its public key, while what
+            // was sent before it joined stays encrypted with a key it never gets.
+            self.rotate_key(false);
+        }
         match response.packet_type.enum_value() {
             Ok(PacketType::AES_KEY) => {
                 if !self.options.enable_e2ee {
@@ -293,6 +1186,7 @@ impl Inner {
                         Ok(aes_packet) => {
                             if let Err(e) = self.peer_decode_manager.set_peer_aes(
                                 &response.email,
+                                aes_packet.generation,
                                 Aes128State::from_vecs(
                                     aes_packet.key,
                                     aes_packet.iv,
@@ -312,25 +1206,12 @@ impl Inner {
                 if !self.options.enable_e2ee {
                     return;
                 }
-                let encrypted_aes_packet = parse_rsa_packet(&response.data)
-                    .and_then(parse_public_key)
-                    .and_then(|pub_key| {
-                        self.serialize_aes_packet()
-                            .map(|aes_packet| (aes_packet, pub_key))
-                    })
-                    .and_then(|(aes_packet, pub_key)| {
-                        self.encrypt_aes_packet(&aes_packet, &pub_key)
-                    });
-
-                match encrypted_aes_packet {
-                    Ok(data) => {
-                        debug!(">> {} sending AES key", self.options.userid);
-                        self.send_packet(PacketWrapper {
-                            packet_type: PacketType::AES_KEY.into(),
-                            email: self.options.userid.clone(),
-                            data,
-                            ..Default::default()
-                        });
+                match parse_rsa_packet(&response.data).and_then(parse_public_key) {
+                    Ok(pub_key) => {
+                        let (generation, key) = self.aes.newest();
+                        self.send_aes_key(generation, &key, &pub_key);
+                        self.peer_public_keys
+                            .insert(response.email.clone(), pub_key);
                     }
                     Err(e) => {
                         error!("Failed to send AES_KEY to peer: {}", e.to_string());
@@ -339,15 +1220,36 @@ impl Inner {
             }
             Ok(PacketType::MEDIA) => {
                 let email = response.email.clone();
-                if let Err(e) = self.peer_decode_manager.decode(response) {
-                    error!("error decoding packet: {}", e.to_string());
-                    self.peer_decode_manager.delete_peer(&email);
+                match self.peer_decode_manager.decode(response) {
+                    Ok(()) => {
+                        if self.peer_decode_manager.keyframe_needed(&email) {
+                            self.request_keyframe(&email);
+                        }
+                    }
+                    Err(PeerDecodeError::AwaitingKey) | Err(PeerDecodeError::RetiredKey) => {}
+                    Err(PeerDecodeError::AesDecryptError(kind)) => {
+                        let failures = self
+                            .peer_decode_manager
+                            .get(&email)
+                            .map_or(0, |peer| peer.decrypt_failures());
+                        warn!(
+                            "unable to decrypt media from {} ({}, {} packets dropped), requesting its key",
+                            email, kind, failures
+                        );
+                        self.options.on_decryption_error.emit((email, kind));
+                        // Peers answer our public key with their AES key.
+                        self.send_public_key();
+                    }
+                    Err(e) => {
+                        error!("error decoding packet: {}", e.to_string());
+                        self.peer_decode_manager.delete_peer(&email);
+                    }
                 }
             }
             Ok(PacketType::CONNECTION) => {
                 error!("Not implemented: CONNECTION packet type");
             }
-            Err(_) => {}
+            Ok(PacketType::CONGESTION) | Ok(PacketType::PACKET_TYPE_UNSPECIFIED) | Err(_) => {}
         }
         if let PeerStatus::Added(peer_userid) = peer_status {
             debug!("added peer {}", peer_userid);
@@ -356,6 +1258,31 @@ impl Inner {
         }
     }
 
+    fn on_congestion(&mut self, data: &[u8]) {
+        match CongestionPacket::parse_from_bytes(data) {
+            Ok(packet) if packet.target == self.options.userid => {
+                debug!("<< congestion feedback, max bitrate {}", packet.max_bitrate);
+                self.bitrate_cap = Some((packet.max_bitrate, self.clock.now()));
+            }
+            Ok(_) => {}
+            Err(e) => error!("Failed to parse congestion packet: {}", e.to_string()),
+        }
+    }
+
+    fn request_keyframe(&mut self, peer_userid: &str) {
+        if self.peer_decode_manager.request_keyframe(peer_userid) {
+            debug!(
+                ">> {} requesting key frame from {}",
+                self.options.userid, peer_userid
+            );
+            self.send_packet(transform_keyframe_request(
+                peer_userid,
+                &self.options.userid,
+                self.aes.clone(),
+            ));
+        }
+    }
+
     fn send_public_key(&self) {
         if !self.options.enable_e2ee {
             return;
@@ -390,10 +1317,45 @@ impl Inner {
         }
     }
 
-    fn serialize_aes_packet(&self) -> Result<Vec<u8>> {
+    // Prepares a new key and sends it to the peers whose public keys we have, see
+    // MediaKey::rotate for `fresh`.
+    fn rotate_key(&self, fresh: bool) {
+        if let Some((generation, key)) = self.aes.rotate(fresh) {
+            debug!(
+                ">> {} rotating AES key to generation {}",
+                self.options.userid, generation
+            );
+            for pub_key in self.peer_public_keys.values() {
+                self.send_aes_key(generation, &key, pub_key);
+            }
+        }
+    }
+
+    fn send_aes_key(&self, generation: u32, key: &Aes128State, pub_key: &RsaPublicKey) {
+        match self
+            .serialize_aes_packet(generation, key)
+            .and_then(|aes_packet| self.encrypt_aes_packet(&aes_packet, pub_key))
+        {
+            Ok(data) => {
+                debug!(">> {} sending AES key", self.options.userid);
+                self.send_packet(PacketWrapper {
+                    packet_type: PacketType::AES_KEY.into(),
+                    email: self.options.userid.clone(),
+                    data,
+                    ..Default::default()
+                });
+            }
+            Err(e) => {
+                error!("Failed to send AES_KEY to peer: {}", e.to_string());
+            }
+        }
+    }
+
+    fn serialize_aes_packet(&self, generation: u32, key: &Aes128State) -> Result<Vec<u8>> {
         AesPacket {
-            key: self.aes.key.to_vec(),
-            iv: self.aes.iv.to_vec(),
+            key: key.key.to_vec(),
+            iv: key.iv.to_vec(),
+            generation,
             ..Default::default()
         }
         .write_to_bytes()
@@ -407,6 +1369,17 @@ impl Inner {
     }
 }
 
+/// 32-bit FNV-1a hash of a display name, as announced by
+/// [set_display_name](VideoCallClient::set_display_name), or 0 for an empty name.
+pub fn display_name_hash(name: &str) -> u32 {
+    if name.is_empty() {
+        return 0;
+    }
+    name.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
 fn parse_rsa_packet(response_data: &[u8]) -> Result<RsaPacket> {
     RsaPacket::parse_from_bytes(response_data)
         .map_err(|e| anyhow!("Failed to parse rsa packet: {}", e.to_string()))
@@ -0,0 +1,198 @@
+//
+// Clock is the source of time for the client's interval-based logic: heartbeats, the peer
+// monitor, the idle timeout, decrypt-failure reporting, the dropped frames log and recordings.
+//
+// BrowserClock, the default, uses Date.now() and gloo intervals.  ManualClock only moves when
+// told to, so that timing behavior can be tested without waiting for real time to pass.
+//
+
+use gloo::timers::callback::Interval;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+/// A source of time, see [`VideoCallClient::with_clock`](crate::VideoCallClient::with_clock).
+pub trait Clock: fmt::Debug {
+    /// Current time in milliseconds, like `Date.now()`.
+    fn now(&self) -> f64;
+
+    /// Calls `callback` every `period` until the returned [Timer] is dropped.
+    fn every(&self, period: Duration, callback: Box<dyn FnMut()>) -> Timer;
+}
+
+/// A callback scheduled with [Clock::every].  Dropping it cancels the callback.
+#[must_use = "the callback is cancelled when the timer is dropped"]
+pub struct Timer {
+    cancel: Option<Box<dyn FnOnce()>>,
+}
+
+impl Timer {
+    pub fn new(cancel: impl FnOnce() + 'static) -> Self {
+        Self {
+            cancel: Some(Box::new(cancel)),
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            cancel();
+        }
+    }
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timer").finish_non_exhaustive()
+    }
+}
+
+/// The browser's clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BrowserClock;
+
+impl Clock for BrowserClock {
+    fn now(&self) -> f64 {
+        js_sys::Date::now()
+    }
+
+    fn every(&self, period: Duration, callback: Box<dyn FnMut()>) -> Timer {
+        let interval = Interval::new(period.as_millis() as u32, callback);
+        Timer::new(move || {
+            interval.cancel();
+        })
+    }
+}
+
+type SharedCallback = Rc<RefCell<Box<dyn FnMut()>>>;
+
+struct ScheduledCallback {
+    id: u64,
+    period: f64,
+    due: f64,
+    callback: SharedCallback,
+}
+
+#[derive(Default)]
+struct ManualClockState {
+    now: f64,
+    next_id: u64,
+    scheduled: Vec<ScheduledCallback>,
+}
+
+/// A clock that only moves on [advance](Self::advance), for tests.  Clones share the same time.
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    state: Rc<RefCell<ManualClockState>>,
+}
+
+impl ManualClock {
+    /// A clock starting at time 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the time forward by `duration`, running the callbacks that come due on the way in
+    /// order, each one as many times as its period fits.
+    pub fn advance(&self, duration: Duration) {
+        let target = self.now() + duration.as_millis() as f64;
+        loop {
+            let callback = {
+                let mut state = self.state.borrow_mut();
+                let Some(next) = state
+                    .scheduled
+                    .iter_mut()
+                    .filter(|scheduled| scheduled.due <= target)
+                    .min_by(|a, b| a.due.total_cmp(&b.due).then(a.id.cmp(&b.id)))
+                else {
+                    break;
+                };
+                let due = next.due;
+                next.due += next.period;
+                let callback = Rc::clone(&next.callback);
+                state.now = due;
+                callback
+            };
+            // The state isn't borrowed while the callback runs, so it may use the clock.
+            (callback.borrow_mut())();
+        }
+        self.state.borrow_mut().now = target;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        self.state.borrow().now
+    }
+
+    fn every(&self, period: Duration, callback: Box<dyn FnMut()>) -> Timer {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id;
+        state.next_id += 1;
+        let period = period.as_millis() as f64;
+        let due = state.now + period;
+        state.scheduled.push(ScheduledCallback {
+            id,
+            period,
+            due,
+            callback: Rc::new(RefCell::new(callback)),
+        });
+        let state: Weak<RefCell<ManualClockState>> = Rc::downgrade(&self.state);
+        Timer::new(move || {
+            if let Some(state) = state.upgrade() {
+                state
+                    .borrow_mut()
+                    .scheduled
+                    .retain(|scheduled| scheduled.id != id);
+            }
+        })
+    }
+}
+
+impl fmt::Debug for ManualClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManualClock")
+            .field("now", &self.now())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn recorder(
+        clock: &ManualClock,
+        log: &Rc<RefCell<Vec<(&'static str, f64)>>>,
+        name: &'static str,
+    ) -> Box<dyn FnMut()> {
+        let clock = clock.clone();
+        let log = Rc::clone(log);
+        Box::new(move || log.borrow_mut().push((name, clock.now())))
+    }
+
+    #[test]
+    fn manual_clock_runs_due_callbacks_in_order() {
+        let clock = ManualClock::new();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let _fast = clock.every(Duration::from_millis(1000), recorder(&clock, &log, "fast"));
+        let slow = clock.every(Duration::from_millis(2500), recorder(&clock, &log, "slow"));
+
+        clock.advance(Duration::from_millis(2999));
+        assert_eq!(
+            *log.borrow(),
+            vec![("fast", 1000.0), ("fast", 2000.0), ("slow", 2500.0)]
+        );
+        assert_eq!(clock.now(), 2999.0);
+
+        drop(slow);
+        log.borrow_mut().clear();
+        clock.advance(Duration::from_millis(3000));
+        assert_eq!(
+            *log.borrow(),
+            vec![("fast", 3000.0), ("fast", 4000.0), ("fast", 5000.0)]
+        );
+    }
+}
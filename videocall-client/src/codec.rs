@@ -0,0 +1,106 @@
+//
+// VideoCodec lists the video codecs that clients can negotiate, and probes which of them the
+// browser can encode or decode.
+//
+// Peers announce the codecs they can decode in their heartbeats, and each video frame carries
+// the codec it was encoded with, so the sender's choice is all the negotiation there is.
+//
+
+use crate::constants::VIDEO_CODEC;
+use js_sys::Reflect;
+use std::cell::RefCell;
+use std::fmt;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{VideoDecoder, VideoDecoderConfig, VideoEncoder, VideoEncoderConfig};
+
+/// A video codec, see [`VideoCallClient::set_preferred_codec_order`](crate::VideoCallClient::set_preferred_codec_order).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoCodec {
+    VP8,
+    VP9,
+    AV1,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 3] = [VideoCodec::VP8, VideoCodec::VP9, VideoCodec::AV1];
+
+    /// The WebCodecs codec string used to configure encoders and decoders.
+    pub fn codec_string(&self) -> &'static str {
+        match self {
+            VideoCodec::VP8 => "vp8",
+            VideoCodec::VP9 => VIDEO_CODEC,
+            // main profile, level 2.1, main tier, 8 bits
+            VideoCodec::AV1 => "av01.0.01M.08",
+        }
+    }
+
+    pub fn from_codec_string(codec: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|video_codec| video_codec.codec_string() == codec)
+    }
+}
+
+impl fmt::Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VideoCodec::VP8 => write!(f, "VP8"),
+            VideoCodec::VP9 => write!(f, "VP9"),
+            VideoCodec::AV1 => write!(f, "AV1"),
+        }
+    }
+}
+
+thread_local! {
+    // Result of the last decodable_codecs() probe, None until one finished.
+    static DECODABLE: RefCell<Option<Vec<VideoCodec>>> = const { RefCell::new(None) };
+}
+
+/// Returns the codecs the browser can decode.
+pub(crate) async fn decodable_codecs() -> Vec<VideoCodec> {
+    let mut codecs = Vec::new();
+    for codec in VideoCodec::ALL {
+        let config = VideoDecoderConfig::new(codec.codec_string());
+        if is_supported(VideoDecoder::is_config_supported(&config)).await {
+            codecs.push(codec);
+        }
+    }
+    DECODABLE.with(|decodable| *decodable.borrow_mut() = Some(codecs.clone()));
+    codecs
+}
+
+/// Returns `true` if [decodable_codecs] found that the browser can't decode `codec`.  Before
+/// the probe finished, every codec is assumed to be decodable.
+pub(crate) fn is_known_undecodable(codec: VideoCodec) -> bool {
+    DECODABLE.with(|decodable| {
+        decodable
+            .borrow()
+            .as_ref()
+            .is_some_and(|codecs| !codecs.contains(&codec))
+    })
+}
+
+/// Returns the first of `candidates` that the browser can encode at the given size.
+pub(crate) async fn first_encodable_codec(
+    candidates: &[VideoCodec],
+    width: u32,
+    height: u32,
+) -> Option<VideoCodec> {
+    for codec in candidates {
+        let config = VideoEncoderConfig::new(codec.codec_string(), height, width);
+        if is_supported(VideoEncoder::is_config_supported(&config)).await {
+            return Some(*codec);
+        }
+    }
+    None
+}
+
+/// Resolves a `VideoEncoder.isConfigSupported()` or `VideoDecoder.isConfigSupported()` promise.
+pub(crate) async fn is_supported(promise: js_sys::Promise) -> bool {
+    match JsFuture::from(promise).await {
+        Ok(support) => Reflect::get(&support, &"supported".into())
+            .map(|supported| supported.is_truthy())
+            .unwrap_or(false),
+        Err(_) => false,
+    }
+}
@@ -2,15 +2,16 @@
 /// Connection struct wraps the lower-level "Task" (task.rs), providing a heartbeat and keeping
 /// track of connection status.
 ///
-use super::task::Task;
+use super::task::{Task, TransportType};
 use super::ConnectOptions;
-use crate::crypto::aes::Aes128State;
-use gloo::timers::callback::Interval;
+use crate::clock::{Clock, Timer};
+use crate::crypto::aes::MediaKey;
 use protobuf::Message;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::time::Duration;
 use videocall_types::protos::media_packet::media_packet::MediaType;
-use videocall_types::protos::media_packet::MediaPacket;
+use videocall_types::protos::media_packet::{HeartbeatMetadata, MediaPacket};
 use videocall_types::protos::packet_wrapper::packet_wrapper::PacketType;
 use videocall_types::protos::packet_wrapper::PacketWrapper;
 use yew::prelude::Callback;
@@ -19,52 +20,92 @@ use yew::prelude::Callback;
 enum Status {
     Connecting,
     Connected,
+    // Lost, either after it was connected or while connecting.
     Closed,
+    // Closed by us (idle timeout, replaced or dropped), as opposed to by the network or the
+    // server.  The close events that follow are not reported.
+    Released,
 }
 
 #[derive(Debug)]
 pub struct Connection {
     task: Rc<Task>,
-    heartbeat: Option<Interval>,
-    heartbeat_monitor: Option<Interval>,
+    heartbeat: Option<Timer>,
+    heartbeat_monitor: Option<Timer>,
+    idle_monitor: Option<Timer>,
+    clock: Rc<dyn Clock>,
     status: Rc<Cell<Status>>,
-    aes: Rc<Aes128State>,
+    was_connected: Rc<Cell<bool>>,
+    last_activity: Rc<Cell<f64>>,
+    aes: Rc<MediaKey>,
+    heartbeat_metadata: Rc<RefCell<HeartbeatMetadata>>,
+    userid: String,
 }
 
 impl Connection {
     pub fn connect(
-        webtransport: bool,
+        transport: TransportType,
         options: ConnectOptions,
-        aes: Rc<Aes128State>,
+        aes: Rc<MediaKey>,
+        heartbeat_metadata: Rc<RefCell<HeartbeatMetadata>>,
     ) -> anyhow::Result<Self> {
         let mut options = options;
         let userid = options.userid.clone();
+        let clock = Rc::clone(&options.clock);
         let status = Rc::new(Cell::new(Status::Connecting));
+        let was_connected = Rc::new(Cell::new(false));
+        let last_activity = Rc::new(Cell::new(clock.now()));
         {
             let status = Rc::clone(&status);
+            let was_connected = Rc::clone(&was_connected);
             options.on_connected = tap_callback(
                 options.on_connected,
-                Callback::from(move |_| status.set(Status::Connected)),
+                Callback::from(move |_| {
+                    status.set(Status::Connected);
+                    was_connected.set(true);
+                }),
             );
         }
         {
             let status = Rc::clone(&status);
-            options.on_connection_lost = tap_callback(
-                options.on_connection_lost,
-                Callback::from(move |_| status.set(Status::Closed)),
+            let on_connection_lost = options.on_connection_lost;
+            options.on_connection_lost = Callback::from(move |e| {
+                if !matches!(status.get(), Status::Released) {
+                    status.set(Status::Closed);
+                    on_connection_lost.emit(e);
+                }
+            });
+        }
+        {
+            let last_activity = Rc::clone(&last_activity);
+            let clock = Rc::clone(&clock);
+            options.on_inbound_media = tap_callback(
+                options.on_inbound_media,
+                Callback::from(move |_| last_activity.set(clock.now())),
             );
         }
         let monitor = options.peer_monitor.clone();
+        let idle_timeout = options.idle_timeout;
+        let on_idle_timeout = options.on_idle_timeout.clone();
         let mut connection = Self {
-            task: Rc::new(Task::connect(webtransport, options)?),
+            task: Rc::new(Task::connect(transport, options)?),
             heartbeat: None,
-            heartbeat_monitor: Some(Interval::new(5000, move || {
-                monitor.emit(());
-            })),
+            heartbeat_monitor: Some(
+                clock.every(Duration::from_secs(5), Box::new(move || monitor.emit(()))),
+            ),
+            idle_monitor: None,
+            clock,
             status,
+            was_connected,
+            last_activity,
             aes,
+            heartbeat_metadata,
+            userid,
         };
-        connection.start_heartbeat(userid);
+        connection.start_heartbeat();
+        if let Some(idle_timeout) = idle_timeout {
+            connection.start_idle_monitor(idle_timeout, on_idle_timeout);
+        }
 
         Ok(connection)
     }
@@ -73,53 +114,129 @@ impl Connection {
         matches!(self.status.get(), Status::Connected)
     }
 
-    fn start_heartbeat(&mut self, userid: String) {
+    /// Whether the connection failed, as opposed to being connected or still connecting.
+    pub fn is_closed(&self) -> bool {
+        matches!(self.status.get(), Status::Closed)
+    }
+
+    /// Whether the connection got connected at some point, even if it is closed now.
+    pub fn was_connected(&self) -> bool {
+        self.was_connected.get()
+    }
+
+    pub fn transport_type(&self) -> TransportType {
+        self.task.transport_type()
+    }
+
+    fn start_heartbeat(&mut self) {
         let task = Rc::clone(&self.task);
         let status = Rc::clone(&self.status);
         let aes = Rc::clone(&self.aes);
+        let heartbeat_metadata = Rc::clone(&self.heartbeat_metadata);
+        let userid = self.userid.clone();
 
-        self.heartbeat = Some(Interval::new(1000, move || {
-            let packet = MediaPacket {
-                media_type: MediaType::HEARTBEAT.into(),
-                email: userid.clone(),
-                timestamp: js_sys::Date::now(),
-                ..Default::default()
-            };
-            let data = aes.encrypt(&packet.write_to_bytes().unwrap()).unwrap();
-            let packet = PacketWrapper {
-                data,
-                email: userid.clone(),
-                packet_type: PacketType::MEDIA.into(),
-                ..Default::default()
-            };
+        let clock = Rc::clone(&self.clock);
+
+        let heartbeat = move || {
+            let packet = heartbeat_packet(&userid, clock.now(), &heartbeat_metadata, &aes);
             if let Status::Connected = status.get() {
                 task.send_packet(packet);
             }
-        }));
+        };
+        self.heartbeat = Some(
+            self.clock
+                .every(Duration::from_secs(1), Box::new(heartbeat)),
+        );
+    }
+
+    // Our own heartbeats don't count as activity, only inbound packets and outbound media do.
+    fn start_idle_monitor(&mut self, idle_timeout: Duration, on_idle_timeout: Callback<()>) {
+        let status = Rc::clone(&self.status);
+        let last_activity = Rc::clone(&self.last_activity);
+        let clock = Rc::clone(&self.clock);
+        let idle_timeout = idle_timeout.as_millis() as f64;
+        let idle_monitor = move || {
+            if !matches!(status.get(), Status::Connected) {
+                return;
+            }
+            if clock.now() - last_activity.get() > idle_timeout {
+                status.set(Status::Released);
+                on_idle_timeout.emit(());
+            }
+        };
+        self.idle_monitor = Some(
+            self.clock
+                .every(Duration::from_secs(1), Box::new(idle_monitor)),
+        );
     }
 
     fn stop_heartbeat(&mut self) {
-        if let Some(heartbeat) = self.heartbeat.take() {
-            heartbeat.cancel();
-        }
-        if let Some(heartbeat_monitor) = self.heartbeat_monitor.take() {
-            heartbeat_monitor.cancel();
+        // Dropping the timers cancels them.
+        self.heartbeat = None;
+        self.heartbeat_monitor = None;
+        self.idle_monitor = None;
+    }
+
+    /// Sends a heartbeat right away, e.g. so that peers learn of a change to its metadata without
+    /// waiting for the next one.  Like the periodic heartbeats, it doesn't count as activity.
+    pub fn send_heartbeat(&self) {
+        if let Status::Connected = self.status.get() {
+            self.task.send_packet(heartbeat_packet(
+                &self.userid,
+                self.clock.now(),
+                &self.heartbeat_metadata,
+                &self.aes,
+            ));
         }
     }
 
     pub fn send_packet(&self, packet: PacketWrapper) {
         if let Status::Connected = self.status.get() {
+            self.last_activity.set(self.clock.now());
             self.task.send_packet(packet);
         }
     }
+
+    /// Like [send_packet](Self::send_packet), but over WebTransport the packet is sent as a
+    /// datagram, which may be lost.
+    pub fn send_packet_unreliable(&self, packet: PacketWrapper) {
+        if let Status::Connected = self.status.get() {
+            self.last_activity.set(self.clock.now());
+            self.task.send_packet_unreliable(packet);
+        }
+    }
 }
 
 impl Drop for Connection {
     fn drop(&mut self) {
+        self.status.set(Status::Released);
         self.stop_heartbeat();
     }
 }
 
+fn heartbeat_packet(
+    userid: &str,
+    now: f64,
+    heartbeat_metadata: &RefCell<HeartbeatMetadata>,
+    aes: &MediaKey,
+) -> PacketWrapper {
+    let packet = MediaPacket {
+        media_type: MediaType::HEARTBEAT.into(),
+        email: userid.to_owned(),
+        timestamp: now,
+        heartbeat_metadata: Some(heartbeat_metadata.borrow().clone()).into(),
+        ..Default::default()
+    };
+    let (key_generation, data) = aes.encrypt(&packet.write_to_bytes().unwrap()).unwrap();
+    PacketWrapper {
+        data,
+        key_generation,
+        email: userid.to_owned(),
+        packet_type: PacketType::MEDIA.into(),
+        ..Default::default()
+    }
+}
+
 fn tap_callback<IN: 'static, OUT: 'static>(
     callback: Callback<IN, OUT>,
     tap: Callback<()>,
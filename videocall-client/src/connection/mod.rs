@@ -6,4 +6,5 @@ mod websocket;
 mod webtransport;
 
 pub use connection::Connection;
+pub use task::{TransportPolicy, TransportType};
 pub use webmedia::ConnectOptions;
@@ -1,15 +1,36 @@
 //
 // Generic Task that can be a WebSocketTask or WebTransportTask.
 //
-// Handles rollover of connection from WebTransport to WebSocket
-//
-use log::{debug, error};
+use log::debug;
 use videocall_types::protos::packet_wrapper::PacketWrapper;
 use yew_websocket::websocket::WebSocketTask;
 use yew_webtransport::webtransport::WebTransportTask;
 
 use super::webmedia::{ConnectOptions, WebMedia};
 
+/// The kind of transport a connection was made with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransportType {
+    WebSocket,
+    WebTransport,
+}
+
+/// Which transports the client connects with, see
+/// [`VideoCallClientOptions::transport_policy`](crate::VideoCallClientOptions::transport_policy).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransportPolicy {
+    /// WebTransport, falling back to WebSocket if it fails before it is connected, e.g. on
+    /// networks that block UDP.
+    #[default]
+    PreferWebTransport,
+    /// WebSocket only.
+    WebSocketOnly,
+    /// WebTransport only, without falling back to WebSocket or migrating to it when degraded.
+    WebTransportOnly,
+    /// Both at once, keeping whichever connects first and closing the other.
+    Race,
+}
+
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub(super) enum Task {
@@ -18,16 +39,21 @@ pub(super) enum Task {
 }
 
 impl Task {
-    pub fn connect(webtransport: bool, options: ConnectOptions) -> anyhow::Result<Self> {
-        if webtransport {
-            debug!("Task::connect trying WebTransport");
-            match WebTransportTask::connect(options.clone()) {
-                Ok(task) => return Ok(Task::WebTransport(task)),
-                Err(_e) => error!("WebTransport connect failed, falling back to WebSocket"),
+    pub fn connect(transport: TransportType, options: ConnectOptions) -> anyhow::Result<Self> {
+        debug!("Task::connect trying {:?}", transport);
+        match transport {
+            TransportType::WebSocket => WebSocketTask::connect(options).map(Task::WebSocket),
+            TransportType::WebTransport => {
+                WebTransportTask::connect(options).map(Task::WebTransport)
             }
         }
-        debug!("Task::connect trying WebSocket");
-        WebSocketTask::connect(options).map(Task::WebSocket)
+    }
+
+    pub fn transport_type(&self) -> TransportType {
+        match self {
+            Task::WebSocket(_) => TransportType::WebSocket,
+            Task::WebTransport(_) => TransportType::WebTransport,
+        }
     }
 
     pub fn send_packet(&self, packet: PacketWrapper) {
@@ -36,4 +62,20 @@ impl Task {
             Task::WebTransport(wt) => wt.send_packet(packet),
         }
     }
+
+    pub fn send_packet_unreliable(&self, packet: PacketWrapper) {
+        match self {
+            Task::WebSocket(ws) => ws.send_packet_with(packet, false),
+            Task::WebTransport(wt) => wt.send_packet_with(packet, false),
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        // Unlike WebSocketTask, WebTransportTask leaves the session open when it is dropped.
+        if let Task::WebTransport(wt) = self {
+            wt.transport.close();
+        }
+    }
 }
@@ -3,13 +3,16 @@
 //
 // Implemented both for WebSockets (websocket.rs) and WebTransport (webtransport.rs)
 //
+use crate::clock::Clock;
 use log::error;
 use protobuf::Message;
+use std::rc::Rc;
+use std::time::Duration;
 use videocall_types::protos::packet_wrapper::PacketWrapper;
 use wasm_bindgen::JsValue;
 use yew::prelude::Callback;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct ConnectOptions {
     pub userid: String,
     pub websocket_url: String,
@@ -18,18 +21,32 @@ pub struct ConnectOptions {
     pub on_connected: Callback<()>,
     pub on_connection_lost: Callback<JsValue>,
     pub peer_monitor: Callback<()>,
+    pub idle_timeout: Option<Duration>,
+    pub on_idle_timeout: Callback<()>,
+    pub clock: Rc<dyn Clock>,
 }
 
 pub(super) trait WebMedia<TASK> {
     fn connect(options: ConnectOptions) -> anyhow::Result<TASK>;
     fn send_bytes(&self, bytes: Vec<u8>);
 
+    // Sends without retransmission where the transport supports it.  WebSocket has no such mode,
+    // so by default this is the same as send_bytes.
+    fn send_bytes_unreliable(&self, bytes: Vec<u8>) {
+        self.send_bytes(bytes)
+    }
+
     fn send_packet(&self, packet: PacketWrapper) {
+        self.send_packet_with(packet, true)
+    }
+
+    fn send_packet_with(&self, packet: PacketWrapper, reliable: bool) {
         match packet
             .write_to_bytes()
             .map_err(|w| JsValue::from(format!("{w:?}")))
         {
-            Ok(bytes) => self.send_bytes(bytes),
+            Ok(bytes) if reliable => self.send_bytes(bytes),
+            Ok(bytes) => self.send_bytes_unreliable(bytes),
             Err(e) => {
                 let packet_type = packet.packet_type.enum_value_or_default();
                 error!("error sending {} packet: {:?}", format!("{packet_type}"), e);
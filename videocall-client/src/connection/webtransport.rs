@@ -22,6 +22,9 @@ use web_sys::WebTransportReceiveStream;
 use yew::prelude::Callback;
 use yew_webtransport::webtransport::{WebTransportService, WebTransportStatus, WebTransportTask};
 
+// Conservative bound on the payload of a QUIC datagram, which must fit in a single UDP packet.
+const MAX_DATAGRAM_SIZE: usize = 1024;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum MessageType {
     Datagram,
@@ -77,6 +80,15 @@ impl WebMedia<WebTransportTask> for WebTransportTask {
     fn send_bytes(&self, bytes: Vec<u8>) {
         WebTransportTask::send_unidirectional_stream(self.transport.clone(), bytes);
     }
+
+    fn send_bytes_unreliable(&self, bytes: Vec<u8>) {
+        // Packets that don't fit in a datagram go over a stream instead of being dropped.
+        if bytes.len() > MAX_DATAGRAM_SIZE {
+            self.send_bytes(bytes);
+        } else {
+            WebTransportTask::send_datagram(self.transport.clone(), bytes);
+        }
+    }
 }
 
 fn handle_unidirectional_stream(
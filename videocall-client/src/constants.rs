@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 pub static AUDIO_CODEC: &str = "opus"; // https://www.w3.org/TR/webcodecs-codec-registry/#audio-codec-registry
 pub static VIDEO_CODEC: &str = "vp09.00.10.08"; // profile 0,level 1.0, bit depth 8,
 
@@ -23,4 +25,25 @@ pub const VIDEO_WIDTH: i32 = 1280i32;
 pub const SCREEN_HEIGHT: u32 = 1080u32;
 pub const SCREEN_WIDTH: u32 = 1920u32;
 
+// Video frames are dropped while the encoder has more than this many frames queued.
+pub const MAX_ENCODE_QUEUE_SIZE: u32 = 3;
+
 pub const RSA_BITS: usize = 1024;
+
+// Defaults of the active speaker detection: audio level a peer must reach to become the active
+// speaker, and for how long it must stay the loudest.
+pub const ACTIVE_SPEAKER_THRESHOLD: f32 = 0.02;
+pub const ACTIVE_SPEAKER_HOLD: Duration = Duration::from_millis(500);
+
+// Peers send a heartbeat every second and the peer monitor runs every 5 seconds.
+pub const EXPECTED_HEARTBEATS_PER_PEER: u32 = 5;
+
+// A new E2EE key is handed to the peers this long before it is used, so that it usually reaches
+// them before the first packet encrypted with it.
+pub const KEY_ROTATION_DELAY: Duration = Duration::from_secs(1);
+
+// How long the camera encoder keeps to the bitrate cap of the server's last congestion feedback.
+pub const BITRATE_CAP_DURATION: Duration = Duration::from_secs(10);
+
+// Frames between the camera encoder's periodic key frames, unless set otherwise.
+pub const VIDEO_KEYFRAME_INTERVAL: u32 = 50;
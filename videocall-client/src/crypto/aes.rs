@@ -1,7 +1,13 @@
 use anyhow::anyhow;
 
+use crate::clock::Clock;
 use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
 use rand::RngCore;
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+use yew::prelude::Callback;
 
 type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
 type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
@@ -74,12 +80,140 @@ impl Aes128State {
     }
 }
 
+/// Why a peer's packet couldn't be decrypted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecryptErrorKind {
+    /// The key the packet was encrypted with hasn't arrived yet.
+    NoKey,
+    /// The packet didn't decrypt with the key it was meant for, i.e. the key is wrong or the
+    /// packet was altered.  AES-CBC has no authentication tag, so this is what the padding check
+    /// catches in its place.
+    InvalidData,
+}
+
+impl fmt::Display for DecryptErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecryptErrorKind::NoKey => write!(f, "no key"),
+            DecryptErrorKind::InvalidData => write!(f, "invalid data"),
+        }
+    }
+}
+
+/// Number of packets that couldn't be decrypted, by [DecryptErrorKind].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecryptionFailures {
+    pub no_key: u32,
+    pub invalid_data: u32,
+}
+
+impl DecryptionFailures {
+    pub fn record(&mut self, kind: DecryptErrorKind) {
+        match kind {
+            DecryptErrorKind::NoKey => self.no_key += 1,
+            DecryptErrorKind::InvalidData => self.invalid_data += 1,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.no_key + self.invalid_data
+    }
+}
+
+/// Our own media key, which is replaced when participants join or leave.
+///
+/// [rotate](Self::rotate) prepares the next key, which is handed to the peers right away but only
+/// used to encrypt from `delay` later on, so that it usually reaches them before the first packet
+/// encrypted with it.  The switch happens at the first packet encrypted after that, and each
+/// packet carries the generation of its key, so peers always know which key to decrypt it with.
+#[derive(Debug)]
+pub struct MediaKey {
+    enabled: bool,
+    current: Cell<(u32, Aes128State)>,
+    // Generation, key and the time from which it is used, in milliseconds.
+    next: Cell<Option<(u32, Aes128State, f64)>>,
+    delay: Duration,
+    clock: Rc<dyn Clock>,
+    on_rotated: Callback<u32>,
+}
+
+impl MediaKey {
+    /// A key of generation 0.  `on_rotated` is called with the new generation each time the key
+    /// in use changes.
+    pub fn new(
+        enabled: bool,
+        delay: Duration,
+        clock: Rc<dyn Clock>,
+        on_rotated: Callback<u32>,
+    ) -> Self {
+        Self {
+            enabled,
+            current: Cell::new((0, Aes128State::new(enabled))),
+            next: Cell::new(None),
+            delay,
+            clock,
+            on_rotated,
+        }
+    }
+
+    /// Prepares the next key and returns it with its generation.  Returns `None` without
+    /// encryption.
+    ///
+    /// A key already waiting to be used is kept, unless `fresh` is set because someone who may
+    /// have received it left.  It is then replaced by a new key of the generation after it, and
+    /// never used.
+    pub fn rotate(&self, fresh: bool) -> Option<(u32, Aes128State)> {
+        if !self.enabled {
+            return None;
+        }
+        let next = match self.next.get() {
+            Some(next) if !fresh => next,
+            pending => {
+                let generation =
+                    pending.map_or(self.current.get().0, |(generation, ..)| generation);
+                let next = (
+                    generation.wrapping_add(1),
+                    Aes128State::new(true),
+                    self.clock.now() + self.delay.as_millis() as f64,
+                );
+                self.next.set(Some(next));
+                next
+            }
+        };
+        Some((next.0, next.1))
+    }
+
+    /// The key to give a peer that asks for it: the next key if one is waiting to be used, so
+    /// that peers joining just now can't decrypt what was sent before, and the current one
+    /// otherwise.
+    pub fn newest(&self) -> (u32, Aes128State) {
+        match self.next.get() {
+            Some((generation, key, _)) => (generation, key),
+            None => self.current.get(),
+        }
+    }
+
+    /// Encrypts `data` with the key in use, switching to the next key first if it is due.
+    /// Returns the generation of the key along with the encrypted data.
+    pub fn encrypt(&self, data: &[u8]) -> anyhow::Result<(u32, Vec<u8>)> {
+        if let Some((generation, key, from)) = self.next.get() {
+            if self.clock.now() >= from {
+                self.current.set((generation, key));
+                self.next.set(None);
+                self.on_rotated.emit(generation);
+            }
+        }
+        let (generation, key) = self.current.get();
+        Ok((generation, key.encrypt(data)?))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use wasm_bindgen_test::*;
+    use crate::clock::ManualClock;
 
-    #[wasm_bindgen_test]
+    #[test]
     fn test_aes() {
         let aes = Aes128State::new(true);
         let data = aes.encrypt(b"hello world").unwrap();
@@ -87,7 +221,7 @@ mod test {
         assert_eq!(data2, b"hello world");
     }
 
-    #[wasm_bindgen_test]
+    #[test]
     fn test_aes_large_payload() {
         let aes = Aes128State::new(true);
         let mut data = Vec::new();
@@ -99,7 +233,7 @@ mod test {
         assert_eq!(data2, data);
     }
 
-    #[wasm_bindgen_test]
+    #[test]
     fn test_aes_disabled() {
         let aes = Aes128State::new(false);
         let mut data = Vec::new();
@@ -110,4 +244,70 @@ mod test {
         let data2 = aes.decrypt(&enc_data).unwrap();
         assert_eq!(data2, data);
     }
+
+    #[test]
+    fn media_key_switches_after_the_delay() {
+        let clock = ManualClock::new();
+        let rotations = Rc::new(Cell::new(0));
+        let key = MediaKey::new(true, Duration::from_secs(1), Rc::new(clock.clone()), {
+            let rotations = Rc::clone(&rotations);
+            Callback::from(move |generation| rotations.set(generation))
+        });
+        let (generation, first) = key.newest();
+        assert_eq!(generation, 0);
+
+        let (generation, next) = key.rotate(false).unwrap();
+        assert_eq!(generation, 1);
+        // Rotating again before the switch keeps the same next key.
+        assert_eq!(key.rotate(false), Some((1, next)));
+        assert_eq!(key.newest(), (1, next));
+
+        let (generation, data) = key.encrypt(b"hello").unwrap();
+        assert_eq!(generation, 0);
+        assert_eq!(first.decrypt(&data).unwrap(), b"hello");
+
+        clock.advance(Duration::from_secs(1));
+        let (generation, data) = key.encrypt(b"hello").unwrap();
+        assert_eq!(generation, 1);
+        assert_eq!(next.decrypt(&data).unwrap(), b"hello");
+        assert_eq!(rotations.get(), 1);
+    }
+
+    #[test]
+    fn media_key_fresh_rotation_replaces_the_next_key() {
+        let clock = ManualClock::new();
+        let key = MediaKey::new(
+            true,
+            Duration::from_secs(1),
+            Rc::new(clock.clone()),
+            Callback::noop(),
+        );
+        let (_, pending) = key.rotate(false).unwrap();
+        clock.advance(Duration::from_millis(500));
+        let (generation, fresh) = key.rotate(true).unwrap();
+        assert_eq!(generation, 2);
+        assert_ne!(fresh, pending);
+        assert_eq!(key.newest(), (2, fresh));
+
+        // The replacement waits the full delay from when it was made.
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(key.encrypt(b"hello").unwrap().0, 0);
+        clock.advance(Duration::from_millis(500));
+        let (generation, data) = key.encrypt(b"hello").unwrap();
+        assert_eq!(generation, 2);
+        assert_eq!(fresh.decrypt(&data).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn media_key_without_encryption_never_rotates() {
+        let key = MediaKey::new(
+            false,
+            Duration::from_secs(1),
+            Rc::new(ManualClock::new()),
+            Callback::noop(),
+        );
+        assert_eq!(key.rotate(false), None);
+        assert_eq!(key.rotate(true), None);
+        assert_eq!(key.encrypt(b"hello").unwrap(), (0, b"hello".to_vec()));
+    }
 }
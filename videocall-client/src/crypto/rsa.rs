@@ -60,9 +60,8 @@ impl RsaWrapper {
 #[cfg(test)]
 mod test {
     use super::*;
-    use wasm_bindgen_test::*;
 
-    #[wasm_bindgen_test]
+    #[test]
     fn test_rsa_thread_rng() {
         let key = RsaWrapper::new(true);
         let data = b"hello world";
@@ -71,7 +70,7 @@ mod test {
         assert_eq!(data, decrypted.as_slice());
     }
 
-    #[wasm_bindgen_test]
+    #[test]
     fn test_rsa_disabled() {
         let key = RsaWrapper::new(false);
         let data = b"hello world";
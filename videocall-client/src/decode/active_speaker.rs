@@ -0,0 +1,112 @@
+//
+// Active speaker detection from the audio levels of the peers' decoded audio.
+//
+// The loudest peer whose level is at or above the threshold becomes the active speaker once it
+// has stayed the loudest for the hold time, so that short noises and crosstalk don't make the
+// speaker flicker.  When nobody speaks, the last speaker stays active.
+//
+
+use std::time::Duration;
+
+#[derive(Debug)]
+pub struct ActiveSpeakerDetector {
+    threshold: f32,
+    // In milliseconds.
+    hold: f64,
+    current: Option<String>,
+    // The loudest peer if it isn't the current speaker, and since when it has been the loudest.
+    candidate: Option<(String, f64)>,
+}
+
+impl ActiveSpeakerDetector {
+    pub fn new(threshold: f32, hold: Duration) -> Self {
+        Self {
+            threshold,
+            hold: hold.as_millis() as f64,
+            current: None,
+            candidate: None,
+        }
+    }
+
+    pub fn active_speaker(&self) -> Option<&String> {
+        self.current.as_ref()
+    }
+
+    /// Takes the peers' current audio levels at time `now` in milliseconds, and returns the new
+    /// active speaker if it changed.
+    pub fn update<'a>(
+        &mut self,
+        levels: impl IntoIterator<Item = (&'a String, f32)>,
+        now: f64,
+    ) -> Option<String> {
+        let loudest = levels
+            .into_iter()
+            .filter(|(_, level)| *level >= self.threshold)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(email, _)| email);
+        let Some(loudest) = loudest.filter(|email| self.current.as_ref() != Some(*email)) else {
+            self.candidate = None;
+            return None;
+        };
+        let since = match &self.candidate {
+            Some((candidate, since)) if candidate == loudest => *since,
+            _ => now,
+        };
+        if now - since >= self.hold {
+            self.candidate = None;
+            self.current = Some(loudest.clone());
+            return self.current.clone();
+        }
+        self.candidate = Some((loudest.clone(), since));
+        None
+    }
+
+    /// Forgets the peer `email`, e.g. because it left.
+    pub fn remove_peer(&mut self, email: &String) {
+        if self.current.as_ref() == Some(email) {
+            self.current = None;
+        }
+        if self
+            .candidate
+            .as_ref()
+            .is_some_and(|(candidate, _)| candidate == email)
+        {
+            self.candidate = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn loudest_peer_becomes_active_after_the_hold_time() {
+        let mut detector = ActiveSpeakerDetector::new(0.1, Duration::from_millis(300));
+        let (alice, bob) = ("alice".to_owned(), "bob".to_owned());
+
+        assert_eq!(detector.update([(&alice, 0.5), (&bob, 0.2)], 0.0), None);
+        assert_eq!(detector.update([(&alice, 0.5), (&bob, 0.2)], 200.0), None);
+        assert_eq!(
+            detector.update([(&alice, 0.5), (&bob, 0.2)], 300.0),
+            Some(alice.clone())
+        );
+        assert_eq!(detector.update([(&alice, 0.5), (&bob, 0.2)], 400.0), None);
+
+        // A short burst from bob doesn't take over.
+        assert_eq!(detector.update([(&alice, 0.2), (&bob, 0.6)], 500.0), None);
+        assert_eq!(detector.update([(&alice, 0.5), (&bob, 0.2)], 600.0), None);
+        assert_eq!(detector.update([(&alice, 0.2), (&bob, 0.6)], 700.0), None);
+        assert_eq!(detector.active_speaker(), Some(&alice));
+
+        // Silence keeps the last speaker.
+        assert_eq!(detector.update([(&alice, 0.0), (&bob, 0.05)], 2000.0), None);
+        assert_eq!(detector.active_speaker(), Some(&alice));
+
+        assert_eq!(detector.update([(&bob, 0.6)], 3000.0), None);
+        assert_eq!(detector.update([(&bob, 0.6)], 3300.0), Some(bob.clone()));
+
+        detector.remove_peer(&bob);
+        assert_eq!(detector.active_speaker(), None);
+    }
+}
@@ -0,0 +1,152 @@
+use super::super::wrappers::EncodedAudioChunkTypeWrapper;
+use super::config::{configure_audio_context, set_audio_sink};
+use super::frame_decoder::{AudioFrameDecoder, FrameDecoder};
+use crate::audio_level::{audio_data_rms, smooth_level};
+use crate::constants::{AUDIO_CHANNELS, AUDIO_SAMPLE_RATE};
+use log::error;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+use videocall_types::protos::media_packet::MediaPacket;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AudioContext, AudioData, AudioDecoder, AudioDecoderConfig, AudioDecoderInit};
+use web_sys::{CodecState, EncodedAudioChunk, EncodedAudioChunkInit};
+use web_sys::{MediaStreamTrack, MediaStreamTrackGenerator, MediaStreamTrackGeneratorInit};
+use yew::prelude::Callback;
+
+/// The browser's WebCodecs audio decoder, with the closures it calls kept alive alongside it.
+///
+/// This is important https://plnkr.co/edit/1yQd8ozGXlV9bwK6?preview
+/// https://github.com/WebAudio/web-audio-api-v2/issues/133
+#[derive(Debug)]
+pub struct AudioDecoderWrapper {
+    decoder: AudioDecoder,
+    audio_context: Option<AudioContext>, // only set when the audio is played
+    audio_level: Rc<Cell<f32>>,
+    audio_track: Option<MediaStreamTrack>, // only set when the audio is played
+    _error: Closure<dyn FnMut(JsValue)>, // member exists to keep the closure in scope for the life of the struct
+    _output: Closure<dyn FnMut(AudioData)>, // member exists to keep the closure in scope for the life of the struct
+}
+
+impl AudioDecoderWrapper {
+    /// Plays the decoded audio to the standard audio stream, or to the output device given by
+    /// `sink_id`.
+    pub fn new(sink_id: Option<&str>) -> Self {
+        let audio_stream_generator =
+            MediaStreamTrackGenerator::new(&MediaStreamTrackGeneratorInit::new("audio")).unwrap();
+        // The audio context is used to reproduce audio.
+        let audio_context = configure_audio_context(&audio_stream_generator).unwrap();
+        let audio_track = audio_stream_generator
+            .clone()
+            .unchecked_into::<MediaStreamTrack>();
+        if let Some(sink_id) = sink_id {
+            set_audio_sink(&audio_context, sink_id);
+        }
+
+        let play = move |audio_data: AudioData| {
+            let writable = audio_stream_generator.writable();
+            if writable.locked() {
+                return;
+            }
+            if let Err(e) = writable.get_writer().map(|writer| {
+                wasm_bindgen_futures::spawn_local(async move {
+                    if let Err(e) = JsFuture::from(writer.ready()).await {
+                        error!("write chunk error {:?}", e);
+                    }
+                    if let Err(e) = JsFuture::from(writer.write_with_chunk(&audio_data)).await {
+                        error!("write chunk error {:?}", e);
+                    };
+                    writer.release_lock();
+                });
+            }) {
+                error!("error {:?}", e);
+            }
+        };
+        Self::with_output(play, Some(audio_context), Some(audio_track))
+    }
+
+    /// Hands the decoded audio to `on_audio` instead of playing it.  The callback owns the
+    /// `AudioData` and must close it.
+    pub fn with_callback(on_audio: Callback<AudioData>) -> Self {
+        Self::with_output(move |audio_data| on_audio.emit(audio_data), None, None)
+    }
+
+    fn with_output(
+        mut on_audio: impl FnMut(AudioData) + 'static,
+        audio_context: Option<AudioContext>,
+        audio_track: Option<MediaStreamTrack>,
+    ) -> Self {
+        let error = Closure::wrap(Box::new(move |e: JsValue| {
+            error!("{:?}", e);
+        }) as Box<dyn FnMut(JsValue)>);
+        let audio_level = Rc::new(Cell::new(0.0));
+        let level = Rc::clone(&audio_level);
+        let output = Closure::wrap(Box::new(move |audio_data: AudioData| {
+            level.set(smooth_level(level.get(), audio_data_rms(&audio_data)));
+            on_audio(audio_data);
+        }) as Box<dyn FnMut(AudioData)>);
+        let decoder = AudioDecoder::new(&AudioDecoderInit::new(
+            error.as_ref().unchecked_ref(),
+            output.as_ref().unchecked_ref(),
+        ))
+        .unwrap();
+        Self {
+            decoder,
+            audio_context,
+            audio_level,
+            audio_track,
+            _error: error,
+            _output: output,
+        }
+    }
+}
+
+impl FrameDecoder for AudioDecoderWrapper {
+    fn configure(&self, codec: &str) {
+        self.decoder.configure(&AudioDecoderConfig::new(
+            codec,
+            AUDIO_CHANNELS,
+            AUDIO_SAMPLE_RATE,
+        ));
+    }
+
+    fn reset(&self) {
+        self.decoder.reset();
+    }
+
+    fn decode(&self, packet: Arc<MediaPacket>) {
+        let chunk_type = EncodedAudioChunkTypeWrapper::from(packet.frame_type.clone()).0;
+        let audio_data = &packet.data;
+        let audio_data_js: js_sys::Uint8Array =
+            js_sys::Uint8Array::new_with_length(audio_data.len() as u32);
+        audio_data_js.copy_from(audio_data.as_slice());
+        let mut audio_chunk =
+            EncodedAudioChunkInit::new(&audio_data_js.into(), packet.timestamp, chunk_type);
+        audio_chunk.duration(packet.duration);
+        self.decoder
+            .decode(&EncodedAudioChunk::new(&audio_chunk).unwrap());
+    }
+
+    fn state(&self) -> CodecState {
+        self.decoder.state()
+    }
+}
+
+impl AudioFrameDecoder for AudioDecoderWrapper {
+    fn audio_level(&self) -> f32 {
+        self.audio_level.get()
+    }
+
+    fn audio_track(&self) -> Option<&MediaStreamTrack> {
+        self.audio_track.as_ref()
+    }
+
+    fn set_sink_id(&self, sink_id: &str) {
+        if let Some(audio_context) = &self.audio_context {
+            set_audio_sink(audio_context, sink_id);
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use crate::constants::AUDIO_SAMPLE_RATE;
 use js_sys::Array;
+use js_sys::Reflect;
+use log::error;
+use wasm_bindgen_futures::JsFuture;
 use web_sys::{AudioContext, AudioContextOptions};
 use web_sys::{MediaStream, MediaStreamTrackGenerator};
 
@@ -23,3 +26,25 @@ pub fn configure_audio_context(
         .unwrap();
     Ok(audio_context)
 }
+
+/// Returns `true` if the browser can route an `AudioContext` to a chosen output device
+/// (`AudioContext.setSinkId`).
+pub fn audio_sink_selection_supported() -> bool {
+    Reflect::get(&js_sys::global(), &"AudioContext".into())
+        .and_then(|audio_context| Reflect::get(&audio_context, &"prototype".into()))
+        .and_then(|prototype| Reflect::has(&prototype, &"setSinkId".into()))
+        .unwrap_or(false)
+}
+
+/// Routes the audio context to the output device `sink_id`, if the browser supports it.
+pub fn set_audio_sink(audio_context: &AudioContext, sink_id: &str) {
+    if !audio_sink_selection_supported() {
+        return;
+    }
+    let promise = audio_context.set_sink_id_with_str(sink_id);
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) = JsFuture::from(promise).await {
+            error!("failed to set audio output: {:?}", e);
+        }
+    });
+}
@@ -0,0 +1,56 @@
+// The decoders of the peers' tracks are reached through the traits in this submodule, so that
+// everything in front of them - the jitter buffer, key frame handling, the peers' bookkeeping -
+// doesn't depend on WebCodecs and can be tested natively against a mock.
+//
+// The WebCodecs implementations are VideoDecoderWrapper and AudioDecoderWrapper, which are created
+// by WebDecoders for the RenderTarget of the PeerDecodeManager.
+
+use super::peer_decode_manager::RenderTarget;
+use std::fmt::Debug;
+use std::sync::Arc;
+use videocall_types::protos::media_packet::media_packet::MediaType;
+use videocall_types::protos::media_packet::MediaPacket;
+use web_sys::{CodecState, MediaStreamTrack};
+
+/// The decoder of a single track.  What it does with the decoded frames is up to whoever created
+/// it, see [DecoderFactory].
+pub trait FrameDecoder: Debug {
+    /// Configures the decoder for the WebCodecs codec string `codec`.
+    fn configure(&self, codec: &str);
+    fn reset(&self);
+    fn decode(&self, packet: Arc<MediaPacket>);
+    fn state(&self) -> CodecState;
+}
+
+/// An audio [FrameDecoder], which also plays what it decoded unless it hands it to a callback.
+pub trait AudioFrameDecoder: FrameDecoder {
+    /// Smoothed RMS level of the decoded audio, from 0.0 for silence up to 1.0.
+    fn audio_level(&self) -> f32;
+    /// The track the decoded audio is played from, if it is played.
+    fn audio_track(&self) -> Option<&MediaStreamTrack>;
+    /// Switches playback to the output device `sink_id`.
+    fn set_sink_id(&self, sink_id: &str);
+}
+
+/// Creates the decoders of the peers' tracks for a [RenderTarget].
+pub trait DecoderFactory {
+    type Video: FrameDecoder;
+    type Audio: AudioFrameDecoder;
+
+    fn video_decoder(
+        target: &RenderTarget,
+        email: &str,
+        media_type: MediaType,
+        canvas_id: &str,
+    ) -> Self::Video;
+
+    fn audio_decoder(
+        target: &RenderTarget,
+        email: &str,
+        media_type: MediaType,
+        audio_output: Option<&str>,
+    ) -> Self::Audio;
+
+    /// Blanks what was last rendered for a track that ended.
+    fn clear_video(target: &RenderTarget, canvas_id: &str);
+}
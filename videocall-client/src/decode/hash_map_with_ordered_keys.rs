@@ -40,6 +40,10 @@ impl<K: Ord + Hash + Clone, V> HashMapWithOrderedKeys<K, V> {
         self.map.get_mut(k)
     }
 
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.map.values()
+    }
+
     pub fn contains_key<Q>(&self, k: &Q) -> bool
     where
         K: Borrow<Q>,
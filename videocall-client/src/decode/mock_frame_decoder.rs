@@ -0,0 +1,91 @@
+// A FrameDecoder implementation for tests, which records what it is asked to do instead of
+// decoding anything.  It needs no browser, so the tests using it run natively.
+
+use super::frame_decoder::{AudioFrameDecoder, DecoderFactory, FrameDecoder};
+use super::peer_decode_manager::RenderTarget;
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
+use videocall_types::protos::media_packet::media_packet::MediaType;
+use videocall_types::protos::media_packet::MediaPacket;
+use web_sys::{CodecState, MediaStreamTrack};
+
+#[derive(Debug)]
+pub struct MockFrameDecoder {
+    pub chunks: RefCell<Vec<Arc<MediaPacket>>>,
+    pub codecs: RefCell<Vec<String>>,
+    pub resets: Cell<u32>,
+    pub state: Cell<CodecState>,
+    pub audio_level: Cell<f32>,
+}
+
+impl MockFrameDecoder {
+    pub fn new() -> Self {
+        MockFrameDecoder {
+            chunks: RefCell::new(Vec::new()),
+            codecs: RefCell::new(Vec::new()),
+            resets: Cell::new(0),
+            state: Cell::new(CodecState::Unconfigured),
+            audio_level: Cell::new(0.0),
+        }
+    }
+
+    /// The sequence numbers of the frames passed to the decoder, in order.
+    pub fn decoded_sequences(&self) -> Vec<u64> {
+        self.chunks
+            .borrow()
+            .iter()
+            .map(|chunk| chunk.video_metadata.sequence)
+            .collect()
+    }
+}
+
+impl FrameDecoder for MockFrameDecoder {
+    fn configure(&self, codec: &str) {
+        self.codecs.borrow_mut().push(codec.to_owned());
+        self.state.set(CodecState::Configured);
+    }
+
+    fn reset(&self) {
+        self.resets.set(self.resets.get() + 1);
+        self.state.set(CodecState::Unconfigured);
+    }
+
+    fn decode(&self, image: Arc<MediaPacket>) {
+        self.chunks.borrow_mut().push(image);
+    }
+
+    fn state(&self) -> CodecState {
+        self.state.get()
+    }
+}
+
+impl AudioFrameDecoder for MockFrameDecoder {
+    fn audio_level(&self) -> f32 {
+        self.audio_level.get()
+    }
+
+    fn audio_track(&self) -> Option<&MediaStreamTrack> {
+        None
+    }
+
+    fn set_sink_id(&self, _sink_id: &str) {}
+}
+
+/// Creates a [MockFrameDecoder] for every track, whatever the render target.
+#[derive(Debug)]
+pub struct MockDecoders;
+
+impl DecoderFactory for MockDecoders {
+    type Video = MockFrameDecoder;
+    type Audio = MockFrameDecoder;
+
+    fn video_decoder(_: &RenderTarget, _: &str, _: MediaType, _: &str) -> MockFrameDecoder {
+        MockFrameDecoder::new()
+    }
+
+    fn audio_decoder(_: &RenderTarget, _: &str, _: MediaType, _: Option<&str>) -> MockFrameDecoder {
+        MockFrameDecoder::new()
+    }
+
+    fn clear_video(_: &RenderTarget, _: &str) {}
+}
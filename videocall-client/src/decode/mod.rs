@@ -1,8 +1,18 @@
+mod active_speaker;
+mod audio_decoder_wrapper;
 mod config;
+mod frame_decoder;
 mod hash_map_with_ordered_keys;
+#[cfg(test)]
+mod mock_frame_decoder;
 mod peer_decode_manager;
 mod peer_decoder;
 mod video_decoder_with_buffer;
 mod video_decoder_wrapper;
 
-pub use peer_decode_manager::{PeerDecodeManager, PeerStatus};
+pub use active_speaker::ActiveSpeakerDetector;
+pub(crate) use config::audio_sink_selection_supported;
+pub use peer_decode_manager::{
+    DecodedMedia, MediaState, PeerDecodeError, PeerDecodeManager, PeerStats, PeerStatus,
+    RenderTarget,
+};
@@ -1,21 +1,33 @@
 use super::hash_map_with_ordered_keys::HashMapWithOrderedKeys;
 use log::debug;
 use protobuf::Message;
+use std::collections::HashSet;
+use std::rc::Rc;
 use std::{fmt::Display, sync::Arc};
-use videocall_types::protos::media_packet::MediaPacket;
+use videocall_types::protos::media_packet::{HeartbeatMetadata, MediaPacket};
 use videocall_types::protos::packet_wrapper::packet_wrapper::PacketType;
 use videocall_types::protos::{
     media_packet::media_packet::MediaType, packet_wrapper::PacketWrapper,
 };
+use web_sys::{AudioData, VideoFrame};
 use yew::prelude::Callback;
 
-use crate::crypto::aes::Aes128State;
+use crate::clock::{BrowserClock, Clock};
+use crate::constants::{
+    ACTIVE_SPEAKER_HOLD, ACTIVE_SPEAKER_THRESHOLD, EXPECTED_HEARTBEATS_PER_PEER,
+};
+use crate::crypto::aes::{Aes128State, DecryptErrorKind, DecryptionFailures};
+use crate::network_quality::{NetworkQuality, NetworkQualityThresholds, NetworkStats};
 
+use super::active_speaker::ActiveSpeakerDetector;
+use super::audio_decoder_wrapper::AudioDecoderWrapper;
+use super::frame_decoder::DecoderFactory;
 use super::peer_decoder::{AudioPeerDecoder, DecodeStatus, PeerDecode, VideoPeerDecoder};
+use super::video_decoder_wrapper::VideoDecoderWrapper;
 
 #[derive(Debug)]
 pub enum PeerDecodeError {
-    AesDecryptError,
+    AesDecryptError(DecryptErrorKind),
     IncorrectPacketType,
     AudioDecodeError,
     ScreenDecodeError,
@@ -24,6 +36,10 @@ pub enum PeerDecodeError {
     NoMediaType,
     NoPacketType,
     PacketParseError,
+    // The peer's key is known to be wrong; the packet was dropped without an attempt to decrypt.
+    AwaitingKey,
+    // The packet was encrypted with a key the peer has since stopped using, and was dropped.
+    RetiredKey,
 }
 
 #[derive(Debug)]
@@ -35,7 +51,7 @@ pub enum PeerStatus {
 impl Display for PeerDecodeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            PeerDecodeError::AesDecryptError => write!(f, "AesDecryptError"),
+            PeerDecodeError::AesDecryptError(kind) => write!(f, "AesDecryptError: {kind}"),
             PeerDecodeError::IncorrectPacketType => write!(f, "IncorrectPacketType"),
             PeerDecodeError::AudioDecodeError => write!(f, "AudioDecodeError"),
             PeerDecodeError::ScreenDecodeError => write!(f, "ScreenDecodeError"),
@@ -46,64 +62,326 @@ impl Display for PeerDecodeError {
             PeerDecodeError::PacketParseError => {
                 write!(f, "Failed to parse to protobuf MediaPacket")
             }
+            PeerDecodeError::AwaitingKey => write!(f, "Awaiting a new AES key"),
+            PeerDecodeError::RetiredKey => write!(f, "Encrypted with a retired AES key"),
         }
     }
 }
 
+/// Which media a peer is sending, as announced in its heartbeats.  A disabled media type is one
+/// the peer turned off, e.g. a muted microphone, rather than one whose packets got lost.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MediaState {
+    pub audio_enabled: bool,
+    pub video_enabled: bool,
+    pub screen_enabled: bool,
+}
+
+/// Statistics of a peer since it joined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PeerStats {
+    /// Packets that couldn't be decrypted, including those dropped unread while waiting for a
+    /// new key after a failure.
+    pub decryption_failures: DecryptionFailures,
+}
+
+impl From<&HeartbeatMetadata> for MediaState {
+    fn from(metadata: &HeartbeatMetadata) -> Self {
+        Self {
+            audio_enabled: metadata.audio_enabled,
+            video_enabled: metadata.video_enabled,
+            screen_enabled: metadata.screen_enabled,
+        }
+    }
+}
+
+/// A decoded frame of a peer's media, see [RenderTarget::Callback].
+#[derive(Debug)]
+pub enum DecodedMedia {
+    Video(VideoFrame),
+    Audio(AudioData),
+}
+
+/// Where the peers' decoded media goes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum RenderTarget {
+    /// Video is painted onto the peers' canvases and audio is played.
+    #[default]
+    Canvas,
+    /// Decoded media is handed to the callback as `callback(peer_userid, media_type, media)`
+    /// instead, without needing any DOM elements, e.g. for a bot that re-encodes it.  The
+    /// callback owns the frames and must `close()` them.
+    Callback(Callback<(String, MediaType, DecodedMedia)>),
+}
+
+/// Creates the browser's WebCodecs decoders, which render to the canvases or hand the decoded
+/// media to the callback of the [RenderTarget].
 #[derive(Debug)]
-pub struct Peer {
-    pub audio: AudioPeerDecoder,
-    pub video: VideoPeerDecoder,
-    pub screen: VideoPeerDecoder,
+pub struct WebDecoders;
+
+impl DecoderFactory for WebDecoders {
+    type Video = VideoDecoderWrapper;
+    type Audio = AudioDecoderWrapper;
+
+    fn video_decoder(
+        target: &RenderTarget,
+        email: &str,
+        media_type: MediaType,
+        canvas_id: &str,
+    ) -> VideoDecoderWrapper {
+        match target {
+            RenderTarget::Canvas => VideoDecoderWrapper::new(canvas_id),
+            RenderTarget::Callback(on_media) => {
+                let email = email.to_owned();
+                VideoDecoderWrapper::with_callback(
+                    on_media.reform(move |frame| {
+                        (email.clone(), media_type, DecodedMedia::Video(frame))
+                    }),
+                )
+            }
+        }
+    }
+
+    fn audio_decoder(
+        target: &RenderTarget,
+        email: &str,
+        media_type: MediaType,
+        audio_output: Option<&str>,
+    ) -> AudioDecoderWrapper {
+        match target {
+            RenderTarget::Canvas => AudioDecoderWrapper::new(audio_output),
+            RenderTarget::Callback(on_media) => {
+                let email = email.to_owned();
+                AudioDecoderWrapper::with_callback(on_media.reform(move |audio_data| {
+                    (email.clone(), media_type, DecodedMedia::Audio(audio_data))
+                }))
+            }
+        }
+    }
+
+    fn clear_video(target: &RenderTarget, canvas_id: &str) {
+        if *target == RenderTarget::Canvas {
+            VideoDecoderWrapper::clear_canvas(canvas_id);
+        }
+    }
+}
+
+// A peer's audio, video, screen and screen audio decoders.
+type PeerDecoders<F> = (
+    AudioPeerDecoder<<F as DecoderFactory>::Audio>,
+    VideoPeerDecoder<<F as DecoderFactory>::Video>,
+    VideoPeerDecoder<<F as DecoderFactory>::Video>,
+    AudioPeerDecoder<<F as DecoderFactory>::Audio>,
+);
+
+#[derive(Debug)]
+pub struct Peer<F: DecoderFactory = WebDecoders> {
+    pub audio: AudioPeerDecoder<F::Audio>,
+    pub video: VideoPeerDecoder<F::Video>,
+    pub screen: VideoPeerDecoder<F::Video>,
+    // The audio of the peer's screen share, played along with its microphone.
+    pub screen_audio: AudioPeerDecoder<F::Audio>,
     pub email: String,
     pub video_canvas_id: String,
     pub screen_canvas_id: String,
-    pub aes: Option<Aes128State>,
+    // The peer's most recent keys by generation, oldest first.  A key is kept until a packet of a
+    // newer generation decrypted, and then for the packets still on their way, see retire_keys.
+    aes: Vec<(u32, Aes128State)>,
+    // The newest generation a packet of the peer was decrypted with.
+    decrypted_generation: Option<u32>,
+    pub heartbeat_metadata: Option<HeartbeatMetadata>,
+    // Channel and payload of the last DATA packet, until the manager hands them to the app.
+    app_data: Option<(u32, Vec<u8>)>,
+    // Set when a heartbeat changed the media state, until the manager reports it.
+    media_state_changed: bool,
+    audio_output: Option<String>,
+    render_target: RenderTarget,
     heartbeat_count: u8,
+    network: NetworkStats,
+    network_quality: Option<NetworkQuality>,
+    // Packets that failed to decrypt since the peer's key was last set.  While non-zero, the
+    // peer's media is dropped undecoded until a new key arrives.
+    decrypt_failures: u32,
+    // Why the first of those failed, which the dropped ones are counted as.
+    decrypt_failure_kind: DecryptErrorKind,
+    // All packets that failed to decrypt or were dropped for it since the peer joined.
+    decryption_failures: DecryptionFailures,
+    // When decrypt failures were last reported, in milliseconds.
+    decrypt_failure_reported: f64,
+    // Whether undecryptable packets arrived since the last heartbeat check, which keeps the peer
+    // alive although its heartbeats can't be read.
+    undecryptable_since_check: bool,
+    // Whether the peer's camera video is decoded, see PeerDecodeManager::set_peer_video_enabled.
+    video_enabled: bool,
+    // Whether the last camera or screen frame was dropped because its decoder awaits a key frame.
+    keyframe_needed: bool,
+    // When a key frame was last requested from the peer, in milliseconds.
+    keyframe_requested: Option<f64>,
+    // Target of the last KEYFRAME_REQUEST packet, until the manager reports it.
+    keyframe_request_target: Option<String>,
 }
 
-impl Peer {
+impl<F: DecoderFactory> Peer<F> {
     fn new(
         video_canvas_id: String,
         screen_canvas_id: String,
         email: String,
         aes: Option<Aes128State>,
+        audio_output: Option<String>,
+        render_target: RenderTarget,
     ) -> Self {
-        let (audio, video, screen) = Self::new_decoders(&video_canvas_id, &screen_canvas_id);
+        let (audio, video, screen, screen_audio) = Self::new_decoders(
+            &render_target,
+            &email,
+            &video_canvas_id,
+            &screen_canvas_id,
+            audio_output.as_deref(),
+        );
         Self {
             audio,
             video,
             screen,
+            screen_audio,
             email,
             video_canvas_id,
             screen_canvas_id,
-            aes,
+            aes: aes.map(|aes| (0, aes)).into_iter().collect(),
+            decrypted_generation: None,
+            heartbeat_metadata: None,
+            app_data: None,
+            media_state_changed: false,
+            audio_output,
+            render_target,
             heartbeat_count: 1,
+            network: NetworkStats::default(),
+            network_quality: None,
+            decrypt_failures: 0,
+            decrypt_failure_kind: DecryptErrorKind::NoKey,
+            decryption_failures: DecryptionFailures::default(),
+            decrypt_failure_reported: 0.0,
+            undecryptable_since_check: false,
+            video_enabled: true,
+            keyframe_needed: false,
+            keyframe_requested: None,
+            keyframe_request_target: None,
         }
     }
 
     fn new_decoders(
+        render_target: &RenderTarget,
+        email: &str,
         video_canvas_id: &str,
         screen_canvas_id: &str,
-    ) -> (AudioPeerDecoder, VideoPeerDecoder, VideoPeerDecoder) {
+        audio_output: Option<&str>,
+    ) -> PeerDecoders<F> {
         (
-            AudioPeerDecoder::new(),
-            VideoPeerDecoder::new(video_canvas_id),
-            VideoPeerDecoder::new(screen_canvas_id),
+            Self::audio_decoder(render_target, email, MediaType::AUDIO, audio_output),
+            Self::video_decoder(render_target, email, MediaType::VIDEO, video_canvas_id),
+            Self::video_decoder(render_target, email, MediaType::SCREEN, screen_canvas_id),
+            Self::audio_decoder(render_target, email, MediaType::SCREEN_AUDIO, audio_output),
         )
     }
 
+    fn video_decoder(
+        render_target: &RenderTarget,
+        email: &str,
+        media_type: MediaType,
+        canvas_id: &str,
+    ) -> VideoPeerDecoder<F::Video> {
+        VideoPeerDecoder::<F::Video>::new(F::video_decoder(
+            render_target,
+            email,
+            media_type,
+            canvas_id,
+        ))
+    }
+
+    fn audio_decoder(
+        render_target: &RenderTarget,
+        email: &str,
+        media_type: MediaType,
+        audio_output: Option<&str>,
+    ) -> AudioPeerDecoder<F::Audio> {
+        AudioPeerDecoder::<F::Audio>::new(F::audio_decoder(
+            render_target,
+            email,
+            media_type,
+            audio_output,
+        ))
+    }
+
+    // Drops the decoder of the ended track so that the next frame is awaited as a first frame
+    // again, and clears whatever was last rendered for it.
+    fn end_stream(&mut self, media_type: MediaType) {
+        match media_type {
+            MediaType::VIDEO => {
+                self.video = Self::video_decoder(
+                    &self.render_target,
+                    &self.email,
+                    media_type,
+                    &self.video_canvas_id,
+                );
+                F::clear_video(&self.render_target, &self.video_canvas_id);
+            }
+            MediaType::SCREEN => {
+                self.screen = Self::video_decoder(
+                    &self.render_target,
+                    &self.email,
+                    media_type,
+                    &self.screen_canvas_id,
+                );
+                F::clear_video(&self.render_target, &self.screen_canvas_id);
+            }
+            MediaType::AUDIO => {
+                self.audio = Self::audio_decoder(
+                    &self.render_target,
+                    &self.email,
+                    media_type,
+                    self.audio_output.as_deref(),
+                );
+            }
+            MediaType::SCREEN_AUDIO => {
+                self.screen_audio = Self::audio_decoder(
+                    &self.render_target,
+                    &self.email,
+                    media_type,
+                    self.audio_output.as_deref(),
+                );
+            }
+            MediaType::MEDIA_TYPE_UNSPECIFIED
+            | MediaType::HEARTBEAT
+            | MediaType::DATA
+            | MediaType::KEYFRAME_REQUEST => {}
+        }
+    }
+
+    // Disabling drops the decoder with whatever it buffered, and enabling starts from a fresh one,
+    // which waits for the peer's next key frame.
+    fn set_video_enabled(&mut self, enabled: bool) {
+        if self.video_enabled != enabled {
+            self.video_enabled = enabled;
+            self.end_stream(MediaType::VIDEO);
+        }
+    }
+
     fn reset(&mut self) {
-        let (audio, video, screen) =
-            Self::new_decoders(&self.video_canvas_id, &self.screen_canvas_id);
+        let (audio, video, screen, screen_audio) = Self::new_decoders(
+            &self.render_target,
+            &self.email,
+            &self.video_canvas_id,
+            &self.screen_canvas_id,
+            self.audio_output.as_deref(),
+        );
         self.audio = audio;
         self.video = video;
         self.screen = screen;
+        self.screen_audio = screen_audio;
     }
 
     fn decode(
         &mut self,
         packet: &Arc<PacketWrapper>,
+        now: f64,
     ) -> Result<(MediaType, DecodeStatus), PeerDecodeError> {
         if packet
             .packet_type
@@ -114,56 +392,235 @@ impl Peer {
             return Err(PeerDecodeError::IncorrectPacketType);
         }
 
-        let packet = match self.aes {
-            Some(aes) => {
-                let data = aes
-                    .decrypt(&packet.data)
-                    .map_err(|_| PeerDecodeError::AesDecryptError)?;
-                parse_media_packet(&data)?
-            }
-            None => parse_media_packet(&packet.data)?,
+        let packet = if self.aes.is_empty() {
+            parse_media_packet(&packet.data)?
+        } else {
+            let data = self
+                .key(packet.key_generation)?
+                .decrypt(&packet.data)
+                .map_err(|_| PeerDecodeError::AesDecryptError(DecryptErrorKind::InvalidData))?;
+            let media_packet = parse_media_packet(&data)?;
+            self.on_decrypted(packet.key_generation);
+            media_packet
         };
 
-        let media_type = packet
-            .media_type
-            .enum_value()
-            .map_err(|_| PeerDecodeError::NoMediaType)?;
-        match media_type {
-            MediaType::VIDEO => Ok((
+        let media_type = match packet.media_type.enum_value() {
+            Ok(MediaType::MEDIA_TYPE_UNSPECIFIED) | Err(_) => {
+                return Err(PeerDecodeError::NoMediaType)
+            }
+            Ok(media_type) => media_type,
+        };
+        if packet.end_of_stream {
+            self.end_stream(media_type);
+            return Ok((
                 media_type,
-                self.video
+                DecodeStatus {
+                    _rendered: false,
+                    first_frame: false,
+                    end_of_stream: true,
+                },
+            ));
+        }
+        match media_type {
+            MediaType::MEDIA_TYPE_UNSPECIFIED => Err(PeerDecodeError::NoMediaType),
+            MediaType::VIDEO => {
+                self.network.on_video_frame(packet.video_metadata.sequence);
+                if !self.video_enabled {
+                    self.keyframe_needed = false;
+                    return Ok((
+                        media_type,
+                        DecodeStatus {
+                            _rendered: false,
+                            first_frame: false,
+                            end_of_stream: false,
+                        },
+                    ));
+                }
+                let status = self
+                    .video
                     .decode(&packet)
-                    .map_err(|_| PeerDecodeError::VideoDecodeError)?,
-            )),
+                    .map_err(|_| PeerDecodeError::VideoDecodeError)?;
+                self.keyframe_needed = self.video.is_waiting_for_keyframe();
+                Ok((media_type, status))
+            }
             MediaType::AUDIO => Ok((
                 media_type,
                 self.audio
                     .decode(&packet)
                     .map_err(|_| PeerDecodeError::AudioDecodeError)?,
             )),
-            MediaType::SCREEN => Ok((
-                media_type,
-                self.screen
+            MediaType::SCREEN => {
+                let status = self
+                    .screen
                     .decode(&packet)
-                    .map_err(|_| PeerDecodeError::ScreenDecodeError)?,
-            )),
-            MediaType::HEARTBEAT => Ok((
+                    .map_err(|_| PeerDecodeError::ScreenDecodeError)?;
+                self.keyframe_needed = self.screen.is_waiting_for_keyframe();
+                Ok((media_type, status))
+            }
+            MediaType::SCREEN_AUDIO => Ok((
                 media_type,
-                DecodeStatus {
-                    _rendered: false,
-                    first_frame: false,
-                },
+                self.screen_audio
+                    .decode(&packet)
+                    .map_err(|_| PeerDecodeError::AudioDecodeError)?,
             )),
+            MediaType::HEARTBEAT => {
+                let previous = self
+                    .heartbeat_metadata
+                    .is_some()
+                    .then(|| self.media_state());
+                self.heartbeat_metadata = packet.heartbeat_metadata.clone().into_option();
+                // The first heartbeat always reports the state, even if nothing is enabled.
+                self.media_state_changed |= previous != Some(self.media_state());
+                self.network.on_heartbeat(packet.timestamp, now);
+                Ok((
+                    media_type,
+                    DecodeStatus {
+                        _rendered: false,
+                        first_frame: false,
+                        end_of_stream: false,
+                    },
+                ))
+            }
+            MediaType::DATA => {
+                let channel = packet.app_data_metadata.channel;
+                self.app_data = Some((channel, packet.data.clone()));
+                Ok((
+                    media_type,
+                    DecodeStatus {
+                        _rendered: false,
+                        first_frame: false,
+                        end_of_stream: false,
+                    },
+                ))
+            }
+            MediaType::KEYFRAME_REQUEST => {
+                self.keyframe_request_target =
+                    Some(packet.keyframe_request_metadata.target.clone());
+                Ok((
+                    media_type,
+                    DecodeStatus {
+                        _rendered: false,
+                        first_frame: false,
+                        end_of_stream: false,
+                    },
+                ))
+            }
+        }
+    }
+
+    // Packets of a newer generation than we know of can't be decrypted until the new key arrives,
+    // which is handled like any other key that doesn't work.
+    fn key(&self, generation: u32) -> Result<Aes128State, PeerDecodeError> {
+        match self.aes.iter().find(|(known, _)| *known == generation) {
+            Some((_, key)) => Ok(*key),
+            None if self.aes.iter().all(|(known, _)| *known < generation) => {
+                Err(PeerDecodeError::AesDecryptError(DecryptErrorKind::NoKey))
+            }
+            None => Err(PeerDecodeError::RetiredKey),
         }
     }
 
+    fn set_aes(&mut self, generation: u32, aes: Aes128State) {
+        self.aes.retain(|(known, _)| *known != generation);
+        self.aes.push((generation, aes));
+        self.aes.sort_by_key(|(known, _)| *known);
+        self.retire_keys();
+        self.decrypt_failures = 0;
+    }
+
+    fn on_decrypted(&mut self, generation: u32) {
+        // None is older than any generation.
+        if self.decrypted_generation < Some(generation) {
+            self.decrypted_generation = Some(generation);
+            self.retire_keys();
+        }
+    }
+
+    // A peer may hand out two keys in quick succession, e.g. when someone joins and someone else
+    // leaves, and keeps encrypting with its current key until the first delay is over.  So beyond
+    // the PEER_KEYS_KEPT newest ones, a key is only forgotten once the peer was seen to use a
+    // newer one.
+    fn retire_keys(&mut self) {
+        while self.aes.len() > PEER_KEYS_KEPT
+            && self
+                .decrypted_generation
+                .is_some_and(|newest| self.aes[0].0 < newest)
+        {
+            self.aes.remove(0);
+        }
+    }
+
+    fn set_audio_output(&mut self, device_id: &str) {
+        self.audio_output = Some(device_id.to_owned());
+        self.audio.set_sink_id(device_id);
+        self.screen_audio.set_sink_id(device_id);
+    }
+
+    /// The media the peer announced in its last heartbeat.
+    pub fn media_state(&self) -> MediaState {
+        self.heartbeat_metadata
+            .as_ref()
+            .map(MediaState::from)
+            .unwrap_or_default()
+    }
+
+    /// The quality of the network path from the peer, as of the last peer monitor run, or `None`
+    /// before the first.
+    pub fn network_quality(&self) -> Option<NetworkQuality> {
+        self.network_quality
+    }
+
     fn on_heartbeat(&mut self) {
         self.heartbeat_count += 1;
     }
 
+    // Counts a packet that couldn't be decrypted.  Returns true for the first failure and then at
+    // most once per DECRYPT_FAILURE_REPORT_INTERVAL_MS, when the caller should log and ask for
+    // the key again.
+    fn on_decrypt_failure(&mut self, now: f64, kind: DecryptErrorKind) -> bool {
+        if self.decrypt_failures == 0 {
+            self.decrypt_failure_kind = kind;
+        }
+        self.decrypt_failures += 1;
+        self.decryption_failures.record(kind);
+        self.undecryptable_since_check = true;
+        if self.decrypt_failures == 1
+            || now - self.decrypt_failure_reported >= DECRYPT_FAILURE_REPORT_INTERVAL_MS
+        {
+            self.decrypt_failure_reported = now;
+            return true;
+        }
+        false
+    }
+
+    // Returns true at most once per KEYFRAME_REQUEST_INTERVAL_MS, when the caller should ask the
+    // peer for a key frame.
+    fn on_keyframe_request(&mut self, now: f64) -> bool {
+        if self
+            .keyframe_requested
+            .is_some_and(|requested| now - requested < KEYFRAME_REQUEST_INTERVAL_MS)
+        {
+            return false;
+        }
+        self.keyframe_requested = Some(now);
+        true
+    }
+
+    /// Number of packets that failed to decrypt since the peer's key was last set.
+    pub fn decrypt_failures(&self) -> u32 {
+        self.decrypt_failures
+    }
+
+    pub fn stats(&self) -> PeerStats {
+        PeerStats {
+            decryption_failures: self.decryption_failures,
+        }
+    }
+
     pub fn check_heartbeat(&mut self) -> bool {
-        if self.heartbeat_count != 0 {
+        if self.heartbeat_count != 0 || self.undecryptable_since_check {
             self.heartbeat_count = 0;
+            self.undecryptable_since_check = false;
             return true;
         }
         debug!(
@@ -174,58 +631,195 @@ impl Peer {
     }
 }
 
+// How often decrypt failures of a peer are logged, and its key requested again.
+const DECRYPT_FAILURE_REPORT_INTERVAL_MS: f64 = 5000.0;
+
+// Number of keys always kept per peer: the one it replaced, the one in use and the next one.
+const PEER_KEYS_KEPT: usize = 3;
+
+// How often a key frame may be requested from a peer.  Peers send one every 50 frames anyway.
+const KEYFRAME_REQUEST_INTERVAL_MS: f64 = 1000.0;
+
 fn parse_media_packet(data: &[u8]) -> Result<Arc<MediaPacket>, PeerDecodeError> {
     Ok(Arc::new(
         MediaPacket::parse_from_bytes(data).map_err(|_| PeerDecodeError::PacketParseError)?,
     ))
 }
 
+/// Decodes the media of all remote peers.
+///
+/// Peers are keyed by their user id (the `email` of their packets), never by the order in which
+/// they arrived.  A peer's canvas ids are derived from that id alone, through
+/// [get_video_canvas_id](Self::get_video_canvas_id) and
+/// [get_screen_canvas_id](Self::get_screen_canvas_id), whenever the peer is added.  After a
+/// reconnect, when peers come back in whatever order their packets happen to arrive, each one
+/// is therefore rendered into the same canvas as before, provided those callbacks only depend
+/// on the id they are given.
+///
+/// The peers' tracks are decoded by the [FrameDecoder](super::frame_decoder::FrameDecoder)s of
+/// the [DecoderFactory], the browser's WebCodecs decoders by default, so that tests can run the
+/// manager natively on mock decoders.
 #[derive(Debug)]
-pub struct PeerDecodeManager {
-    connected_peers: HashMapWithOrderedKeys<String, Peer>,
+pub struct PeerDecodeManager<F: DecoderFactory = WebDecoders> {
+    connected_peers: HashMapWithOrderedKeys<String, Peer<F>>,
     pub on_first_frame: Callback<(String, MediaType)>,
+    pub on_stream_ended: Callback<(String, MediaType)>,
     pub get_video_canvas_id: Callback<String, String>,
     pub get_screen_canvas_id: Callback<String, String>,
+    pub on_app_data: Callback<(String, u32, Vec<u8>)>,
+    // Called as `callback(target)` when a peer asks `target` for a key frame.
+    pub on_keyframe_request: Callback<String>,
+    pub on_media_state: Callback<(String, MediaState)>,
+    pub on_active_speaker: Callback<String>,
+    pub on_network_quality: Callback<(String, NetworkQuality)>,
+    pub render_target: RenderTarget,
+    pub network_quality_thresholds: NetworkQualityThresholds,
+    pub active_speaker: ActiveSpeakerDetector,
+    pub clock: Rc<dyn Clock>,
+    // Whether peers are expected to encrypt their media, which tells packets that can't be read
+    // before a peer's key arrived from garbled ones.
+    pub enable_e2ee: bool,
+    audio_output: Option<String>,
+    // Peers whose camera video isn't decoded, kept across their removal so that it still applies
+    // when they come back.
+    video_disabled: HashSet<String>,
 }
 
-impl PeerDecodeManager {
+impl<F: DecoderFactory> PeerDecodeManager<F> {
     pub fn new() -> Self {
         Self {
             connected_peers: HashMapWithOrderedKeys::new(),
             on_first_frame: Callback::noop(),
+            on_stream_ended: Callback::noop(),
             get_video_canvas_id: Callback::from(|key| format!("video-{}", &key)),
             get_screen_canvas_id: Callback::from(|key| format!("screen-{}", &key)),
+            on_app_data: Callback::noop(),
+            on_keyframe_request: Callback::noop(),
+            on_media_state: Callback::noop(),
+            on_active_speaker: Callback::noop(),
+            on_network_quality: Callback::noop(),
+            render_target: RenderTarget::default(),
+            network_quality_thresholds: NetworkQualityThresholds::default(),
+            active_speaker: ActiveSpeakerDetector::new(
+                ACTIVE_SPEAKER_THRESHOLD,
+                ACTIVE_SPEAKER_HOLD,
+            ),
+            clock: Rc::new(BrowserClock),
+            enable_e2ee: false,
+            audio_output: None,
+            video_disabled: HashSet::new(),
         }
     }
 
+    /// The user ids of the connected peers, sorted by id rather than by arrival.
     pub fn sorted_keys(&self) -> &Vec<String> {
         self.connected_peers.ordered_keys()
     }
 
-    pub fn get(&self, key: &String) -> Option<&Peer> {
+    pub fn get(&self, key: &String) -> Option<&Peer<F>> {
         self.connected_peers.get(key)
     }
 
-    pub fn run_peer_monitor(&mut self) {
-        let pred = |peer: &mut Peer| peer.check_heartbeat();
+    /// Number of heartbeats received from all peers since the last [run_peer_monitor](Self::run_peer_monitor).
+    pub fn heartbeats_received(&self) -> u32 {
+        self.connected_peers
+            .values()
+            .map(|peer| peer.heartbeat_count as u32)
+            .sum()
+    }
+
+    /// The peers whose media currently fails to decrypt, with the number of packets dropped
+    /// since their key was last set.
+    pub fn decrypt_failures(&self) -> Vec<(String, u32)> {
+        self.sorted_keys()
+            .iter()
+            .filter_map(|key| {
+                let failures = self.connected_peers.get(key)?.decrypt_failures;
+                (failures > 0).then(|| (key.clone(), failures))
+            })
+            .collect()
+    }
+
+    /// Rates the peers' network quality, reporting the peers whose rating changed, and removes
+    /// peers whose heartbeat stopped.  This is also the backstop for tracks whose end-of-stream
+    /// packet got lost along the way.
+    ///
+    /// Without `rate_quality` the window is discarded unrated, e.g. while heartbeats arrive over
+    /// two connections and are counted twice.
+    pub fn run_peer_monitor(&mut self, rate_quality: bool) {
+        self.update_network_quality(rate_quality);
+        let pred = |peer: &mut Peer<F>| peer.check_heartbeat();
         self.connected_peers.remove_if(pred);
+        if let Some(speaker) = self.active_speaker.active_speaker().cloned() {
+            if !self.connected_peers.contains_key(&speaker) {
+                self.active_speaker.remove_peer(&speaker);
+            }
+        }
     }
 
     pub fn decode(&mut self, response: PacketWrapper) -> Result<(), PeerDecodeError> {
         let packet = Arc::new(response);
         let email = packet.email.clone();
         if let Some(peer) = self.connected_peers.get_mut(&email) {
-            match peer.decode(&packet) {
+            // Once a packet failed to decrypt, the others will as well until the key changes, so
+            // they're dropped unread and only reported now and then.
+            if peer.decrypt_failures > 0 {
+                let kind = peer.decrypt_failure_kind;
+                return if peer.on_decrypt_failure(self.clock.now(), kind) {
+                    Err(PeerDecodeError::AesDecryptError(kind))
+                } else {
+                    Err(PeerDecodeError::AwaitingKey)
+                };
+            }
+            let mut result = peer.decode(&packet, self.clock.now());
+            if self.enable_e2ee && matches!(result, Err(PeerDecodeError::PacketParseError)) {
+                // Until the peer's key arrives its packets are read as plaintext, which fails
+                // when they're encrypted.  With a key, the padding check passed on garbage.
+                let kind = if peer.aes.is_empty() {
+                    DecryptErrorKind::NoKey
+                } else {
+                    DecryptErrorKind::InvalidData
+                };
+                result = Err(PeerDecodeError::AesDecryptError(kind));
+            }
+            match result {
                 Ok((MediaType::HEARTBEAT, _)) => {
                     peer.on_heartbeat();
+                    if std::mem::take(&mut peer.media_state_changed) {
+                        let media_state = peer.media_state();
+                        self.on_media_state.emit((email, media_state));
+                    }
+                    Ok(())
+                }
+                Ok((MediaType::DATA, _)) => {
+                    if let Some((channel, data)) = peer.app_data.take() {
+                        self.on_app_data.emit((email, channel, data));
+                    }
+                    Ok(())
+                }
+                Ok((MediaType::KEYFRAME_REQUEST, _)) => {
+                    if let Some(target) = peer.keyframe_request_target.take() {
+                        self.on_keyframe_request.emit(target);
+                    }
                     Ok(())
                 }
                 Ok((media_type, decode_status)) => {
-                    if decode_status.first_frame {
+                    if decode_status.end_of_stream {
+                        self.on_stream_ended.emit((email.clone(), media_type));
+                    } else if decode_status.first_frame {
                         self.on_first_frame.emit((email.clone(), media_type));
                     }
+                    if media_type == MediaType::AUDIO {
+                        self.update_active_speaker();
+                    }
                     Ok(())
                 }
+                Err(PeerDecodeError::AesDecryptError(kind)) => {
+                    peer.on_decrypt_failure(self.clock.now(), kind);
+                    Err(PeerDecodeError::AesDecryptError(kind))
+                }
+                // A straggler from before the key changed, the peer's streams are fine.
+                Err(PeerDecodeError::RetiredKey) => Err(PeerDecodeError::RetiredKey),
                 Err(e) => {
                     peer.reset();
                     Err(e)
@@ -236,21 +830,76 @@ impl PeerDecodeManager {
         }
     }
 
+    fn update_network_quality(&mut self, rate_quality: bool) {
+        for key in self.connected_peers.ordered_keys().clone() {
+            let Some(peer) = self.connected_peers.get_mut(&key) else {
+                continue;
+            };
+            let quality = peer.network.finish_window(
+                EXPECTED_HEARTBEATS_PER_PEER,
+                &self.network_quality_thresholds,
+            );
+            if !rate_quality {
+                continue;
+            }
+            if peer.network_quality.replace(quality) != Some(quality) {
+                self.on_network_quality.emit((key, quality));
+            }
+        }
+    }
+
+    // Levels change as audio is decoded, so they're compared each time a peer's audio arrives.
+    fn update_active_speaker(&mut self) {
+        let levels = self
+            .connected_peers
+            .ordered_keys()
+            .iter()
+            .filter_map(|key| Some((key, self.connected_peers.get(key)?.audio.audio_level())));
+        if let Some(speaker) = self.active_speaker.update(levels, self.clock.now()) {
+            self.on_active_speaker.emit(speaker);
+        }
+    }
+
     fn add_peer(&mut self, email: &str, aes: Option<Aes128State>) {
         debug!("Adding peer {}", email);
-        self.connected_peers.insert(
+        let mut peer = Peer::new(
+            self.get_video_canvas_id.emit(email.to_owned()),
+            self.get_screen_canvas_id.emit(email.to_owned()),
             email.to_owned(),
-            Peer::new(
-                self.get_video_canvas_id.emit(email.to_owned()),
-                self.get_screen_canvas_id.emit(email.to_owned()),
-                email.to_owned(),
-                aes,
-            ),
+            aes,
+            self.audio_output.clone(),
+            self.render_target.clone(),
         );
+        peer.video_enabled = !self.video_disabled.contains(email);
+        self.connected_peers.insert(email.to_owned(), peer);
+    }
+
+    /// Stops or resumes decoding the camera video of `email`, whether or not it is connected yet.
+    /// Its audio, screen share and heartbeats are decoded either way.
+    pub fn set_peer_video_enabled(&mut self, email: &str, enabled: bool) {
+        if enabled {
+            self.video_disabled.remove(email);
+        } else {
+            self.video_disabled.insert(email.to_owned());
+        }
+        if let Some(peer) = self.connected_peers.get_mut(&email.to_owned()) {
+            peer.set_video_enabled(enabled);
+        }
+    }
+
+    /// Plays the audio of all current and future peers on the output device `device_id`.
+    pub fn set_audio_output(&mut self, device_id: &str) {
+        self.audio_output = Some(device_id.to_owned());
+        for key in self.connected_peers.ordered_keys().clone() {
+            if let Some(peer) = self.connected_peers.get_mut(&key) {
+                peer.set_audio_output(device_id);
+            }
+        }
     }
 
     pub fn delete_peer(&mut self, email: &String) {
         self.connected_peers.remove(email);
+        self.active_speaker.remove_peer(email);
     }
 
     pub fn ensure_peer(&mut self, email: &String) -> PeerStatus {
@@ -262,17 +911,590 @@ impl PeerDecodeManager {
         }
     }
 
+    /// Whether the last camera or screen frame of `email` was dropped because its decoder awaits a
+    /// key frame, e.g. after joining mid-stream or after a decoder reset.
+    pub fn keyframe_needed(&self, email: &str) -> bool {
+        self.connected_peers
+            .get(&email.to_owned())
+            .is_some_and(|peer| peer.keyframe_needed)
+    }
+
+    /// Whether a key frame may be requested from `email` now.  Requests are limited to one per
+    /// second per peer; this counts one if it returns true.
+    pub fn request_keyframe(&mut self, email: &str) -> bool {
+        let now = self.clock.now();
+        self.connected_peers
+            .get_mut(&email.to_owned())
+            .is_some_and(|peer| peer.on_keyframe_request(now))
+    }
+
+    /// Sets the key of `generation` that `email` encrypts its media with.  Old keys are forgotten
+    /// once `email` was seen to use a newer one.
     pub fn set_peer_aes(
         &mut self,
         email: &String,
+        generation: u32,
         aes: Aes128State,
     ) -> Result<(), PeerDecodeError> {
         match self.connected_peers.get_mut(email) {
             Some(peer) => {
-                peer.aes = Some(aes);
+                peer.set_aes(generation, aes);
                 Ok(())
             }
             None => Err(PeerDecodeError::NoSuchPeer(email.clone())),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::super::mock_frame_decoder::{MockDecoders, MockFrameDecoder};
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::codec::VideoCodec;
+    use std::cell::RefCell;
+    use std::time::Duration;
+    use videocall_types::protos::media_packet::{
+        AppDataMetadata, KeyframeRequestMetadata, VideoMetadata,
+    };
+    use web_sys::CodecState;
+
+    // Decodes all tracks with mock decoders, so that the tests run natively.
+    type TestManager = PeerDecodeManager<MockDecoders>;
+
+    // A manager on a clock of its own, as there is no browser clock natively.
+    fn new_manager() -> TestManager {
+        let mut manager = TestManager::new();
+        manager.clock = Rc::new(ManualClock::new());
+        manager
+    }
+
+    fn video_packet(sequence: u64, frame_type: &str, codec: &str) -> PacketWrapper {
+        let media_packet = MediaPacket {
+            email: "bob".to_owned(),
+            media_type: MediaType::VIDEO.into(),
+            frame_type: frame_type.to_owned(),
+            data: vec![sequence as u8],
+            video_metadata: Some(VideoMetadata {
+                sequence,
+                codec: codec.to_owned(),
+                ..Default::default()
+            })
+            .into(),
+            ..Default::default()
+        };
+        PacketWrapper {
+            email: "bob".to_owned(),
+            packet_type: PacketType::MEDIA.into(),
+            data: media_packet.write_to_bytes().unwrap(),
+            ..Default::default()
+        }
+    }
+
+    // bob's camera decoder.
+    fn video_decoder(manager: &TestManager) -> &MockFrameDecoder {
+        let peer = manager.get(&"bob".to_owned()).unwrap();
+        peer.video.frame_decoder().frame_decoder()
+    }
+
+    // The sequence numbers of bob's camera frames handed to the decoder.
+    fn decoded(manager: &TestManager) -> Vec<u64> {
+        video_decoder(manager).decoded_sequences()
+    }
+
+    fn canvas_ids(manager: &TestManager) -> Vec<(String, String, String)> {
+        manager
+            .sorted_keys()
+            .iter()
+            .map(|key| {
+                let peer = manager.get(key).unwrap();
+                (
+                    key.clone(),
+                    peer.video_canvas_id.clone(),
+                    peer.screen_canvas_id.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn canvas_ids_survive_reconnect_in_a_different_order() {
+        let mut manager = new_manager();
+        let peers = ["bob", "carol", "alice"].map(String::from);
+        for peer in &peers {
+            manager.ensure_peer(peer);
+        }
+        let before = canvas_ids(&manager);
+
+        // The connection drops and the peers come back in the reverse order.
+        for peer in &peers {
+            manager.delete_peer(peer);
+        }
+        for peer in peers.iter().rev() {
+            manager.ensure_peer(peer);
+        }
+
+        assert_eq!(canvas_ids(&manager), before);
+        assert_eq!(
+            before[0],
+            (
+                "alice".to_owned(),
+                "video-alice".to_owned(),
+                "screen-alice".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn app_data_is_handed_to_the_app() {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let mut manager = new_manager();
+        manager.on_app_data = {
+            let received = Rc::clone(&received);
+            Callback::from(move |message| received.borrow_mut().push(message))
+        };
+        manager.ensure_peer(&"bob".to_owned());
+
+        let media_packet = MediaPacket {
+            email: "bob".to_owned(),
+            media_type: MediaType::DATA.into(),
+            data: b"hello".to_vec(),
+            app_data_metadata: Some(AppDataMetadata {
+                channel: 7,
+                ..Default::default()
+            })
+            .into(),
+            ..Default::default()
+        };
+        manager
+            .decode(PacketWrapper {
+                email: "bob".to_owned(),
+                packet_type: PacketType::MEDIA.into(),
+                data: media_packet.write_to_bytes().unwrap(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            *received.borrow(),
+            vec![("bob".to_owned(), 7, b"hello".to_vec())]
+        );
+    }
+
+    #[test]
+    fn media_state_changes_are_reported() {
+        let reported = Rc::new(RefCell::new(Vec::new()));
+        let mut manager = new_manager();
+        manager.on_media_state = {
+            let reported = Rc::clone(&reported);
+            Callback::from(move |state| reported.borrow_mut().push(state))
+        };
+        manager.ensure_peer(&"bob".to_owned());
+
+        let mut heartbeat = |audio_enabled: bool| {
+            let media_packet = MediaPacket {
+                email: "bob".to_owned(),
+                media_type: MediaType::HEARTBEAT.into(),
+                heartbeat_metadata: Some(HeartbeatMetadata {
+                    audio_enabled,
+                    video_enabled: true,
+                    ..Default::default()
+                })
+                .into(),
+                ..Default::default()
+            };
+            manager
+                .decode(PacketWrapper {
+                    email: "bob".to_owned(),
+                    packet_type: PacketType::MEDIA.into(),
+                    data: media_packet.write_to_bytes().unwrap(),
+                    ..Default::default()
+                })
+                .unwrap();
+        };
+        heartbeat(true);
+        heartbeat(true);
+        heartbeat(false);
+
+        let state = |audio_enabled| MediaState {
+            audio_enabled,
+            video_enabled: true,
+            screen_enabled: false,
+        };
+        assert_eq!(
+            *reported.borrow(),
+            vec![
+                ("bob".to_owned(), state(true)),
+                ("bob".to_owned(), state(false))
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_video_is_dropped_and_stays_disabled_after_reconnect() {
+        let first_frames = Rc::new(RefCell::new(Vec::new()));
+        let mut manager = new_manager();
+        manager.on_first_frame = {
+            let first_frames = Rc::clone(&first_frames);
+            Callback::from(move |frame| first_frames.borrow_mut().push(frame))
+        };
+        let bob = "bob".to_owned();
+        manager.set_peer_video_enabled(&bob, false);
+        manager.ensure_peer(&bob);
+        assert!(!manager.get(&bob).unwrap().video_enabled);
+
+        let media_packet = MediaPacket {
+            email: bob.clone(),
+            media_type: MediaType::VIDEO.into(),
+            frame_type: "key".to_owned(),
+            data: b"frame".to_vec(),
+            ..Default::default()
+        };
+        manager
+            .decode(PacketWrapper {
+                email: bob.clone(),
+                packet_type: PacketType::MEDIA.into(),
+                data: media_packet.write_to_bytes().unwrap(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(first_frames.borrow().is_empty());
+        assert!(manager.get(&bob).unwrap().video.is_waiting_for_keyframe());
+
+        manager.delete_peer(&bob);
+        manager.ensure_peer(&bob);
+        assert!(!manager.get(&bob).unwrap().video_enabled);
+        manager.set_peer_video_enabled(&bob, true);
+        assert!(manager.get(&bob).unwrap().video_enabled);
+    }
+
+    #[test]
+    fn unset_media_type_is_rejected() {
+        let mut manager = new_manager();
+        manager.ensure_peer(&"bob".to_owned());
+
+        let media_packet = MediaPacket {
+            email: "bob".to_owned(),
+            data: b"frame".to_vec(),
+            ..Default::default()
+        };
+        let result = manager.decode(PacketWrapper {
+            email: "bob".to_owned(),
+            packet_type: PacketType::MEDIA.into(),
+            data: media_packet.write_to_bytes().unwrap(),
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(PeerDecodeError::NoMediaType)));
+    }
+
+    #[test]
+    fn keyframes_are_requested_at_a_limited_rate() {
+        let clock = ManualClock::new();
+        let mut manager = TestManager::new();
+        manager.clock = Rc::new(clock.clone());
+        let bob = "bob".to_owned();
+        manager.ensure_peer(&bob);
+
+        // Joining mid-stream, the first frame is a delta frame.
+        let media_packet = MediaPacket {
+            email: bob.clone(),
+            media_type: MediaType::SCREEN.into(),
+            frame_type: "delta".to_owned(),
+            data: b"frame".to_vec(),
+            ..Default::default()
+        };
+        assert!(!manager.keyframe_needed(&bob));
+        manager
+            .decode(PacketWrapper {
+                email: bob.clone(),
+                packet_type: PacketType::MEDIA.into(),
+                data: media_packet.write_to_bytes().unwrap(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(manager.keyframe_needed(&bob));
+
+        assert!(manager.request_keyframe(&bob));
+        clock.advance(Duration::from_millis(500));
+        assert!(!manager.request_keyframe(&bob));
+        clock.advance(Duration::from_millis(500));
+        assert!(manager.request_keyframe(&bob));
+        assert!(!manager.request_keyframe("carol"));
+    }
+
+    #[test]
+    fn keyframe_requests_are_reported() {
+        let targets = Rc::new(RefCell::new(Vec::new()));
+        let mut manager = new_manager();
+        manager.on_keyframe_request = {
+            let targets = Rc::clone(&targets);
+            Callback::from(move |target| targets.borrow_mut().push(target))
+        };
+        manager.ensure_peer(&"bob".to_owned());
+
+        let media_packet = MediaPacket {
+            email: "bob".to_owned(),
+            media_type: MediaType::KEYFRAME_REQUEST.into(),
+            keyframe_request_metadata: Some(KeyframeRequestMetadata {
+                target: "alice".to_owned(),
+                ..Default::default()
+            })
+            .into(),
+            ..Default::default()
+        };
+        manager
+            .decode(PacketWrapper {
+                email: "bob".to_owned(),
+                packet_type: PacketType::MEDIA.into(),
+                data: media_packet.write_to_bytes().unwrap(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(*targets.borrow(), vec!["alice".to_owned()]);
+    }
+
+    #[test]
+    fn decrypt_failures_are_reported_at_a_limited_rate() {
+        let mut peer = Peer::<MockDecoders>::new(
+            "video-bob".to_owned(),
+            "screen-bob".to_owned(),
+            "bob".to_owned(),
+            None,
+            None,
+            RenderTarget::default(),
+        );
+        let reports: Vec<bool> = [0.0, 30.0, 4990.0, 5000.0, 5030.0]
+            .into_iter()
+            .map(|now| peer.on_decrypt_failure(now, DecryptErrorKind::InvalidData))
+            .collect();
+        assert_eq!(reports, vec![true, false, false, true, false]);
+        assert_eq!(peer.decrypt_failures(), 5);
+        assert_eq!(peer.stats().decryption_failures.invalid_data, 5);
+
+        // The peer stays alive although none of its heartbeats could be read.
+        peer.heartbeat_count = 0;
+        assert!(peer.check_heartbeat());
+        assert!(!peer.check_heartbeat());
+    }
+
+    // A heartbeat of bob encrypted with his key of `generation`.
+    fn encrypted_heartbeat(generation: u32, key: &Aes128State) -> PacketWrapper {
+        let media_packet = MediaPacket {
+            email: "bob".to_owned(),
+            media_type: MediaType::HEARTBEAT.into(),
+            ..Default::default()
+        };
+        PacketWrapper {
+            email: "bob".to_owned(),
+            packet_type: PacketType::MEDIA.into(),
+            data: key
+                .encrypt(&media_packet.write_to_bytes().unwrap())
+                .unwrap(),
+            key_generation: generation,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn packets_are_decrypted_with_the_key_of_their_generation() {
+        let mut manager = new_manager();
+        let bob = "bob".to_owned();
+        manager.ensure_peer(&bob);
+        let keys = [0, 1, 2, 3].map(|_| Aes128State::new(true));
+        for (generation, key) in keys.iter().enumerate() {
+            manager.set_peer_aes(&bob, generation as u32, *key).unwrap();
+        }
+
+        assert!(manager.decode(encrypted_heartbeat(0, &keys[0])).is_ok());
+        assert!(manager.decode(encrypted_heartbeat(2, &keys[2])).is_ok());
+        // The previous key is kept for packets that were on their way when the key changed.
+        assert!(manager.decode(encrypted_heartbeat(1, &keys[1])).is_ok());
+        assert!(matches!(
+            manager.decode(encrypted_heartbeat(0, &keys[0])),
+            Err(PeerDecodeError::RetiredKey)
+        ));
+        // A key we haven't received yet.
+        assert!(matches!(
+            manager.decode(encrypted_heartbeat(4, &keys[3])),
+            Err(PeerDecodeError::AesDecryptError(DecryptErrorKind::NoKey))
+        ));
+    }
+
+    #[test]
+    fn key_in_use_is_kept_when_two_new_keys_arrive() {
+        let mut manager = new_manager();
+        let bob = "bob".to_owned();
+        manager.ensure_peer(&bob);
+        let keys = [0, 1, 2, 3].map(|_| Aes128State::new(true));
+        manager.set_peer_aes(&bob, 0, keys[0]).unwrap();
+        assert!(manager.decode(encrypted_heartbeat(0, &keys[0])).is_ok());
+
+        // Someone joined and someone else left right after, while bob still uses key 0.
+        manager.set_peer_aes(&bob, 1, keys[1]).unwrap();
+        manager.set_peer_aes(&bob, 2, keys[2]).unwrap();
+        assert!(manager.decode(encrypted_heartbeat(0, &keys[0])).is_ok());
+
+        // More keys than are always kept, but bob hasn't used a newer one yet.
+        manager.set_peer_aes(&bob, 3, keys[3]).unwrap();
+        assert!(manager.decode(encrypted_heartbeat(0, &keys[0])).is_ok());
+        assert_eq!(manager.get(&bob).unwrap().decrypt_failures(), 0);
+    }
+
+    #[test]
+    fn decryption_failures_are_counted_by_kind() {
+        let mut manager = new_manager();
+        manager.enable_e2ee = true;
+        let bob = "bob".to_owned();
+        manager.ensure_peer(&bob);
+        // A fixed key, whose heartbeat isn't by chance also a valid plaintext packet.
+        let key = Aes128State::from_vecs(vec![7; 16], vec![7; 16], true);
+        let heartbeat = || PacketWrapper {
+            email: bob.clone(),
+            packet_type: PacketType::MEDIA.into(),
+            data: key
+                .encrypt(
+                    &MediaPacket {
+                        email: bob.clone(),
+                        media_type: MediaType::HEARTBEAT.into(),
+                        ..Default::default()
+                    }
+                    .write_to_bytes()
+                    .unwrap(),
+                )
+                .unwrap(),
+            ..Default::default()
+        };
+
+        // Bob's key hasn't arrived yet; the second packet is dropped unread.
+        assert!(matches!(
+            manager.decode(heartbeat()),
+            Err(PeerDecodeError::AesDecryptError(DecryptErrorKind::NoKey))
+        ));
+        assert!(matches!(
+            manager.decode(heartbeat()),
+            Err(PeerDecodeError::AwaitingKey)
+        ));
+
+        // A packet altered on the way.
+        manager.set_peer_aes(&bob, 0, key).unwrap();
+        let mut tampered = heartbeat();
+        tampered.data.pop();
+        assert!(matches!(
+            manager.decode(tampered),
+            Err(PeerDecodeError::AesDecryptError(
+                DecryptErrorKind::InvalidData
+            ))
+        ));
+
+        manager.set_peer_aes(&bob, 0, key).unwrap();
+        assert!(manager.decode(heartbeat()).is_ok());
+        assert_eq!(
+            manager.get(&bob).unwrap().stats().decryption_failures,
+            DecryptionFailures {
+                no_key: 2,
+                invalid_data: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn reordered_frames_are_decoded_in_order() {
+        let mut manager = new_manager();
+        manager.ensure_peer(&"bob".to_owned());
+        for (sequence, frame_type) in [(1, "key"), (3, "delta"), (2, "delta"), (4, "delta")] {
+            manager
+                .decode(video_packet(sequence, frame_type, ""))
+                .unwrap();
+        }
+        assert_eq!(decoded(&manager), vec![1, 2, 3, 4]);
+        assert!(!manager.keyframe_needed("bob"));
+    }
+
+    #[test]
+    fn frames_before_the_first_keyframe_are_dropped() {
+        let mut manager = new_manager();
+        manager.ensure_peer(&"bob".to_owned());
+
+        // Joining mid-stream.
+        manager.decode(video_packet(5, "delta", "")).unwrap();
+        manager.decode(video_packet(6, "delta", "")).unwrap();
+        assert_eq!(decoded(&manager), Vec::<u64>::new());
+        assert!(manager.keyframe_needed("bob"));
+
+        manager.decode(video_packet(7, "key", "")).unwrap();
+        manager.decode(video_packet(8, "delta", "")).unwrap();
+        assert_eq!(decoded(&manager), vec![7, 8]);
+        assert!(!manager.keyframe_needed("bob"));
+    }
+
+    #[test]
+    fn lost_frame_is_skipped_once_the_buffer_is_full() {
+        let mut manager = new_manager();
+        manager.ensure_peer(&"bob".to_owned());
+        manager.decode(video_packet(1, "key", "")).unwrap();
+
+        // Frame 2 never arrives, so the frames after it wait for it in the jitter buffer.
+        for sequence in 3..=12 {
+            manager.decode(video_packet(sequence, "delta", "")).unwrap();
+        }
+        assert_eq!(decoded(&manager), vec![1]);
+
+        // Once the buffer overflows, the decoder skips ahead rather than waiting any longer.
+        manager.decode(video_packet(13, "delta", "")).unwrap();
+        let mut expected = vec![1];
+        expected.extend(3..=13);
+        assert_eq!(decoded(&manager), expected);
+        assert!(!manager.keyframe_needed("bob"));
+    }
+
+    #[test]
+    fn corrupt_packet_resets_the_decoders() {
+        let mut manager = new_manager();
+        manager.ensure_peer(&"bob".to_owned());
+        manager.decode(video_packet(1, "key", "")).unwrap();
+        manager.decode(video_packet(2, "delta", "")).unwrap();
+
+        let result = manager.decode(PacketWrapper {
+            email: "bob".to_owned(),
+            packet_type: PacketType::MEDIA.into(),
+            data: vec![0xff, 0xff, 0xff],
+            ..Default::default()
+        });
+        assert!(matches!(result, Err(PeerDecodeError::PacketParseError)));
+
+        // The fresh decoder can't continue from frame 2, so it waits for a key frame.
+        manager.decode(video_packet(3, "delta", "")).unwrap();
+        assert_eq!(decoded(&manager), Vec::<u64>::new());
+        assert!(manager.keyframe_needed("bob"));
+        assert!(manager.request_keyframe("bob"));
+    }
+
+    #[test]
+    fn codec_switch_waits_for_a_keyframe_of_the_new_codec() {
+        let mut manager = new_manager();
+        manager.ensure_peer(&"bob".to_owned());
+        manager.decode(video_packet(1, "key", "")).unwrap();
+        manager.decode(video_packet(2, "delta", "vp8")).unwrap();
+        assert!(manager.keyframe_needed("bob"));
+        manager.decode(video_packet(3, "key", "vp8")).unwrap();
+
+        let peer = manager.get(&"bob".to_owned()).unwrap();
+        assert_eq!(peer.video.current_codec(), VideoCodec::VP8);
+        assert_eq!(video_decoder(&manager).resets.get(), 1);
+        assert_eq!(decoded(&manager), vec![1, 3]);
+    }
+
+    #[test]
+    fn closed_decoder_is_an_error() {
+        let mut manager = new_manager();
+        manager.ensure_peer(&"bob".to_owned());
+        manager.decode(video_packet(1, "key", "")).unwrap();
+        video_decoder(&manager).state.set(CodecState::Closed);
+
+        let result = manager.decode(video_packet(2, "delta", ""));
+        assert!(matches!(result, Err(PeerDecodeError::VideoDecodeError)));
+        // The peer got fresh decoders.
+        assert_eq!(decoded(&manager), Vec::<u64>::new());
+    }
+}
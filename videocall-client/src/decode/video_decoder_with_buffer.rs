@@ -1,43 +1,59 @@
 use super::super::wrappers::EncodedVideoChunkTypeWrapper;
-use super::video_decoder_wrapper::VideoDecoderTrait;
+use super::frame_decoder::FrameDecoder;
+use crate::diagnostics::{record_drop, record_drops, DropReason};
 use std::{cmp::Ordering, collections::BTreeMap, sync::Arc};
 use videocall_types::protos::media_packet::MediaPacket;
-use wasm_bindgen::JsValue;
-use web_sys::{CodecState, EncodedVideoChunkType, VideoDecoderConfig, VideoDecoderInit};
+use web_sys::{CodecState, EncodedVideoChunkType};
 
 const MAX_BUFFER_SIZE: usize = 10;
 
-// This is a wrapper of the web-sys VideoDecoder which handles
+// This is a wrapper of a video FrameDecoder which handles
 // frames being out of order and other issues.
 #[derive(Debug)]
-pub struct VideoDecoderWithBuffer<A: VideoDecoderTrait> {
+pub struct VideoDecoderWithBuffer<A: FrameDecoder> {
     video_decoder: A,
     cache: BTreeMap<u64, Arc<MediaPacket>>,
     sequence: Option<u64>,
 }
 
-impl<T: VideoDecoderTrait> VideoDecoderWithBuffer<T> {
-    pub fn new(init: &VideoDecoderInit) -> Result<Self, JsValue> {
-        T::new(init).map(|video_decoder| VideoDecoderWithBuffer {
+impl<T: FrameDecoder> VideoDecoderWithBuffer<T> {
+    pub fn new(video_decoder: T) -> Self {
+        VideoDecoderWithBuffer {
             video_decoder,
             cache: BTreeMap::new(),
             sequence: None,
-        })
+        }
+    }
+
+    #[cfg(test)]
+    pub(super) fn frame_decoder(&self) -> &T {
+        &self.video_decoder
     }
 
-    pub fn configure(&self, config: &VideoDecoderConfig) {
-        self.video_decoder.configure(config);
+    pub fn configure(&self, codec: &str) {
+        self.video_decoder.configure(codec);
+    }
+
+    /// Configures the decoder for a different stream, e.g. in another codec.  Frames of the
+    /// previous stream that are still queued or buffered are discarded.
+    pub fn reconfigure(&mut self, codec: &str) {
+        self.video_decoder.reset();
+        self.cache.clear();
+        self.sequence = None;
+        self.video_decoder.configure(codec);
     }
 
     pub fn decode(&mut self, image: Arc<MediaPacket>) {
         let new_sequence_number = image.video_metadata.sequence;
         let frame_type = EncodedVideoChunkTypeWrapper::from(image.frame_type.as_str()).0;
+        let media_type = image.media_type.enum_value_or_default();
         let cache_size = self.cache.len();
         // If we get a keyframe, play it immediately, then prune all packets before it
         if frame_type == EncodedVideoChunkType::Key {
             self.video_decoder.decode(image);
             self.sequence = Some(new_sequence_number);
-            self.prune_older_frames_from_buffer(new_sequence_number);
+            let pruned = self.prune_older_frames_from_buffer(new_sequence_number);
+            record_drops(media_type, DropReason::Late, pruned);
         } else if let Some(sequence) = self.sequence {
             let is_future_frame = new_sequence_number > sequence;
             let is_future_i_frame = is_future_frame && frame_type == EncodedVideoChunkType::Key;
@@ -56,17 +72,22 @@ impl<T: VideoDecoderTrait> VideoDecoderWithBuffer<T> {
                 if is_future_frame {
                     self.cache.insert(new_sequence_number, image);
                     if cache_size + 1 > MAX_BUFFER_SIZE {
-                        self.fast_forward_frames_and_then_prune_buffer();
+                        let skipped = self.fast_forward_frames_and_then_prune_buffer();
+                        record_drops(media_type, DropReason::BufferFull, skipped);
                     }
+                } else {
+                    record_drop(media_type, DropReason::Late);
                 }
             }
         }
     }
 
-    fn fast_forward_frames_and_then_prune_buffer(&mut self) {
+    // Returns the number of frames that were removed without being decoded.
+    fn fast_forward_frames_and_then_prune_buffer(&mut self) -> u32 {
         let mut should_skip = false;
         let sorted_frames = self.cache.keys().cloned().collect::<Vec<_>>();
         let mut to_remove = Vec::new(); // We will store the keys that we want to remove here
+        let mut skipped = 0;
         for (index, sequence) in sorted_frames.iter().enumerate() {
             let image = self.cache.get(sequence).unwrap();
             let frame_type = EncodedVideoChunkTypeWrapper::from(image.frame_type.as_str()).0;
@@ -90,6 +111,7 @@ impl<T: VideoDecoderTrait> VideoDecoderWithBuffer<T> {
             } else if let Some(self_sequence) = self.sequence {
                 if *sequence < self_sequence {
                     to_remove.push(*sequence); // Again, add to the remove list instead of removing directly
+                    skipped += 1;
                 }
             }
         }
@@ -97,11 +119,15 @@ impl<T: VideoDecoderTrait> VideoDecoderWithBuffer<T> {
         for sequence in to_remove {
             self.cache.remove(&sequence);
         }
+        skipped
     }
 
-    fn prune_older_frames_from_buffer(&mut self, sequence_number: u64) {
+    // Returns the number of frames that were pruned.
+    fn prune_older_frames_from_buffer(&mut self, sequence_number: u64) -> u32 {
+        let cache_size = self.cache.len();
         self.cache
-            .retain(|sequence, _| *sequence >= sequence_number)
+            .retain(|sequence, _| *sequence >= sequence_number);
+        (cache_size - self.cache.len()) as u32
     }
 
     fn play_queued_follow_up_frames(&mut self) {
@@ -133,43 +159,10 @@ impl<T: VideoDecoderTrait> VideoDecoderWithBuffer<T> {
 #[cfg(test)]
 mod test {
 
-    use std::sync::Mutex;
-
-    use videocall_types::protos::media_packet::VideoMetadata;
-    use wasm_bindgen::prelude::*;
-    use wasm_bindgen_test::wasm_bindgen_test;
+    use super::super::mock_frame_decoder::MockFrameDecoder;
+    use videocall_types::protos::media_packet::{media_packet::MediaType, VideoMetadata};
 
     use super::*;
-    pub struct MockVideoDecoder {
-        chunks: Arc<Mutex<Vec<Arc<MediaPacket>>>>,
-        pub state: CodecState,
-    }
-
-    impl VideoDecoderTrait for MockVideoDecoder {
-        fn configure(&self, _config: &VideoDecoderConfig) {
-            // Mock implementation, possibly do nothing
-        }
-
-        fn decode(&self, image: Arc<MediaPacket>) {
-            let mut chunks = self.chunks.lock().unwrap();
-            chunks.push(image);
-        }
-
-        fn state(&self) -> CodecState {
-            // Mock implementation, return some state
-            self.state
-        }
-
-        fn new(_init: &VideoDecoderInit) -> Result<Self, JsValue>
-        where
-            Self: Sized,
-        {
-            Ok(MockVideoDecoder {
-                chunks: Arc::new(Mutex::new(Vec::new())),
-                state: CodecState::Configured,
-            })
-        }
-    }
 
     fn create_mock_packet(
         sequence: u64,
@@ -182,7 +175,7 @@ mod test {
         };
         // This function creates a mock MediaPacket.
         Arc::new(MediaPacket {
-            media_type: Default::default(), // Put an appropriate default or value here
+            media_type: MediaType::VIDEO.into(),
             email: "test@example.com".to_string(),
             data,
             frame_type: EncodedVideoChunkTypeWrapper(chunk_type).to_string(),
@@ -190,23 +183,14 @@ mod test {
             duration: 0.0,
             audio_metadata: Default::default(), // Put an appropriate default or value here
             video_metadata: Some(video_metadata).into(), // Assuming sequence is a field in VideoMetadata
-            special_fields: Default::default(),          // Put an appropriate default or value here
+            ..Default::default()
         })
     }
 
-    fn create_video_decoder() -> VideoDecoderWithBuffer<MockVideoDecoder> {
-        let error = Closure::wrap(Box::new(move |_e: JsValue| {}) as Box<dyn FnMut(JsValue)>);
-        let output =
-            Closure::wrap(Box::new(move |_original_chunk: JsValue| {}) as Box<dyn FnMut(JsValue)>);
-        let init = VideoDecoderInit::new(
-            error.as_ref().unchecked_ref(),
-            output.as_ref().unchecked_ref(),
-        );
-        let video_decoder_with_buffer: VideoDecoderWithBuffer<MockVideoDecoder> =
-            VideoDecoderWithBuffer::new(&init).unwrap();
-        video_decoder_with_buffer
+    fn create_video_decoder() -> VideoDecoderWithBuffer<MockFrameDecoder> {
+        VideoDecoderWithBuffer::new(MockFrameDecoder::new())
     }
-    #[wasm_bindgen_test]
+    #[test]
     fn test_in_order_frames_happy_path() {
         let mut video_decoder_with_buffer = create_video_decoder();
 
@@ -226,8 +210,7 @@ mod test {
         let processed_sequences: Vec<u64> = video_decoder_with_buffer
             .video_decoder
             .chunks
-            .lock()
-            .unwrap()
+            .borrow()
             .iter()
             .map(|chunk| {
                 // Extract sequence number from chunk; assuming a method to do this
@@ -237,7 +220,7 @@ mod test {
         assert_eq!(processed_sequences, vec![1, 2, 3]);
     }
 
-    #[wasm_bindgen_test]
+    #[test]
     fn test_out_of_order_key_frames() {
         let mut video_decoder_with_buffer = create_video_decoder();
 
@@ -257,8 +240,7 @@ mod test {
         let processed_sequences: Vec<u64> = video_decoder_with_buffer
             .video_decoder
             .chunks
-            .lock()
-            .unwrap()
+            .borrow()
             .iter()
             .map(|chunk| {
                 chunk.video_metadata.sequence // Extract sequence number from chunk; assuming a method to do this
@@ -267,7 +249,7 @@ mod test {
         assert_eq!(processed_sequences, vec![3, 1, 2]);
     }
 
-    #[wasm_bindgen_test]
+    #[test]
     fn test_extremely_out_of_order_frames() {
         let mut video_decoder_with_buffer = create_video_decoder();
 
@@ -289,8 +271,7 @@ mod test {
         let processed_sequences: Vec<u64> = video_decoder_with_buffer
             .video_decoder
             .chunks
-            .lock()
-            .unwrap()
+            .borrow()
             .iter()
             .map(|chunk| {
                 chunk.video_metadata.sequence // Extract sequence number from chunk; assuming a method to do this
@@ -298,4 +279,52 @@ mod test {
             .collect();
         assert!(processed_sequences == vec![5, 6] || processed_sequences == vec![5, 6]);
     }
+
+    #[test]
+    fn reconfigure_discards_buffered_frames() {
+        let mut video_decoder_with_buffer = create_video_decoder();
+        video_decoder_with_buffer.decode(create_mock_packet(
+            1,
+            EncodedVideoChunkType::Key,
+            vec![1],
+        ));
+        // Buffered until 2 arrives, which it doesn't before the stream changes.
+        video_decoder_with_buffer.decode(create_mock_packet(
+            3,
+            EncodedVideoChunkType::Delta,
+            vec![3],
+        ));
+
+        video_decoder_with_buffer.reconfigure("vp8");
+        for (sequence, frame_type) in [
+            (0, EncodedVideoChunkType::Key),
+            (1, EncodedVideoChunkType::Delta),
+            (2, EncodedVideoChunkType::Delta),
+            (3, EncodedVideoChunkType::Delta),
+        ] {
+            video_decoder_with_buffer.decode(create_mock_packet(
+                sequence,
+                frame_type,
+                vec![10 + sequence as u8],
+            ));
+        }
+
+        let decoded: Vec<(u64, Vec<u8>)> = video_decoder_with_buffer
+            .video_decoder
+            .chunks
+            .borrow()
+            .iter()
+            .map(|chunk| (chunk.video_metadata.sequence, chunk.data.clone()))
+            .collect();
+        assert_eq!(
+            decoded,
+            vec![
+                (1, vec![1]),
+                (0, vec![10]),
+                (1, vec![11]),
+                (2, vec![12]),
+                (3, vec![13])
+            ]
+        );
+    }
 }
@@ -1,31 +1,109 @@
 use super::super::wrappers::EncodedVideoChunkTypeWrapper;
+use super::frame_decoder::FrameDecoder;
 use js_sys::Uint8Array;
+use log::error;
 use std::sync::Arc;
 use videocall_types::protos::media_packet::MediaPacket;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
+use web_sys::window;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
 use web_sys::{
     CodecState, EncodedVideoChunk, EncodedVideoChunkInit, VideoDecoder, VideoDecoderConfig,
-    VideoDecoderInit,
+    VideoDecoderInit, VideoFrame,
 };
+use yew::prelude::Callback;
 
-// Define the trait
-pub trait VideoDecoderTrait {
-    fn new(init: &VideoDecoderInit) -> Result<Self, JsValue>
-    where
-        Self: Sized;
-    fn configure(&self, config: &VideoDecoderConfig);
-    fn decode(&self, image: Arc<MediaPacket>);
-    fn state(&self) -> CodecState;
+/// The browser's WebCodecs video decoder, with the closures it calls kept alive alongside it.
+#[derive(Debug)]
+pub struct VideoDecoderWrapper {
+    decoder: VideoDecoder,
+    _error: Closure<dyn FnMut(JsValue)>, // member exists to keep the closure in scope for the life of the struct
+    _output: Closure<dyn FnMut(JsValue)>, // member exists to keep the closure in scope for the life of the struct
 }
 
-// Create a wrapper struct for the foreign struct
-#[derive(Debug)]
-pub struct VideoDecoderWrapper(web_sys::VideoDecoder);
+impl VideoDecoderWrapper {
+    /// Renders the decoded frames into the HtmlCanvasElement with the DOM id `canvas_id`.  The size
+    /// of the canvas is set at decode time to match the image size from the media data.
+    pub fn new(canvas_id: &str) -> Self {
+        let id = canvas_id.to_owned();
+        Self::with_output(move |video_chunk: VideoFrame| {
+            let width = video_chunk.coded_width();
+            let height = video_chunk.coded_height();
+            let video_chunk = video_chunk.unchecked_into::<HtmlImageElement>();
+            let render_canvas = window()
+                .unwrap()
+                .document()
+                .unwrap()
+                .get_element_by_id(&id)
+                .unwrap()
+                .unchecked_into::<HtmlCanvasElement>();
+            let ctx = render_canvas
+                .get_context("2d")
+                .unwrap()
+                .unwrap()
+                .unchecked_into::<CanvasRenderingContext2d>();
+            render_canvas.set_width(width);
+            render_canvas.set_height(height);
+            if let Err(e) = ctx.draw_image_with_html_image_element(&video_chunk, 0.0, 0.0) {
+                error!("error {:?}", e);
+            }
+            video_chunk.unchecked_into::<VideoFrame>().close();
+        })
+    }
 
-// Implement the trait for the wrapper struct
-impl VideoDecoderTrait for VideoDecoderWrapper {
-    fn configure(&self, config: &VideoDecoderConfig) {
-        self.0.configure(config);
+    /// Hands the decoded frames to `on_frame` instead of painting them.  The callback owns the
+    /// frames and must close them.
+    pub fn with_callback(on_frame: Callback<VideoFrame>) -> Self {
+        Self::with_output(move |frame| on_frame.emit(frame))
+    }
+
+    fn with_output(mut on_frame: impl FnMut(VideoFrame) + 'static) -> Self {
+        let error = Closure::wrap(Box::new(move |e: JsValue| {
+            error!("{:?}", e);
+        }) as Box<dyn FnMut(JsValue)>);
+        let output = Closure::wrap(Box::new(move |chunk: JsValue| {
+            on_frame(chunk.unchecked_into::<VideoFrame>());
+        }) as Box<dyn FnMut(JsValue)>);
+        let decoder = VideoDecoder::new(&VideoDecoderInit::new(
+            error.as_ref().unchecked_ref(),
+            output.as_ref().unchecked_ref(),
+        ))
+        .unwrap();
+        Self {
+            decoder,
+            _error: error,
+            _output: output,
+        }
+    }
+
+    /// Blanks the canvas so the last decoded frame doesn't linger after the stream ended.
+    pub fn clear_canvas(canvas_id: &str) {
+        let canvas = window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id(canvas_id))
+            .map(|e| e.unchecked_into::<HtmlCanvasElement>());
+        if let Some(canvas) = canvas {
+            if let Ok(Some(ctx)) = canvas.get_context("2d") {
+                ctx.unchecked_into::<CanvasRenderingContext2d>().clear_rect(
+                    0.0,
+                    0.0,
+                    canvas.width() as f64,
+                    canvas.height() as f64,
+                );
+            }
+        }
+    }
+}
+
+impl FrameDecoder for VideoDecoderWrapper {
+    fn configure(&self, codec: &str) {
+        self.decoder.configure(&VideoDecoderConfig::new(codec));
+    }
+
+    fn reset(&self) {
+        self.decoder.reset();
     }
 
     fn decode(&self, image: Arc<MediaPacket>) {
@@ -35,16 +113,10 @@ impl VideoDecoderTrait for VideoDecoderWrapper {
         let mut video_chunk = EncodedVideoChunkInit::new(&video_data, image.timestamp, chunk_type);
         video_chunk.duration(image.duration);
         let encoded_video_chunk = EncodedVideoChunk::new(&video_chunk).unwrap();
-        self.0.decode(&encoded_video_chunk);
+        self.decoder.decode(&encoded_video_chunk);
     }
 
     fn state(&self) -> CodecState {
-        self.0.state()
-    }
-    fn new(init: &VideoDecoderInit) -> Result<Self, JsValue>
-    where
-        Self: Sized,
-    {
-        VideoDecoder::new(init).map(VideoDecoderWrapper)
+        self.decoder.state()
     }
 }
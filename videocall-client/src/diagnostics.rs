@@ -0,0 +1,211 @@
+//
+// Rate-limited logging of dropped frames.
+//
+// The encoders and decoders report each frame they drop with record_drop().  Drops are counted
+// per media type and reason, and a timer of the client summarizes them in a single log line per
+// interval with drops, e.g.
+//
+//      dropped 30 frames in 1.0s: VIDEO encode-overload=30
+//
+// logged at WARN when there were more drops than the threshold, at DEBUG otherwise.
+//
+
+use crate::clock::{Clock, Timer};
+use log::{log, Level};
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+use videocall_types::protos::media_packet::media_packet::MediaType;
+
+/// Why a frame was dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropReason {
+    /// The frame arrived after the decoder had moved past it.
+    Late,
+    /// The jitter buffer overflowed and skipped ahead.
+    BufferFull,
+    /// The encoder was too far behind to take another frame.
+    EncodeOverload,
+    /// The frame was shed in favour of higher-priority media.
+    Priority,
+    /// The frame was encoded with a codec the browser can't decode.
+    UnsupportedCodec,
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DropReason::Late => write!(f, "late"),
+            DropReason::BufferFull => write!(f, "buffer-full"),
+            DropReason::EncodeOverload => write!(f, "encode-overload"),
+            DropReason::Priority => write!(f, "priority"),
+            DropReason::UnsupportedCodec => write!(f, "unsupported-codec"),
+        }
+    }
+}
+
+/// Settings of the dropped frames log, see [set_drop_log_config].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DropLogConfig {
+    /// How often the drops are summarized.
+    pub interval: Duration,
+    /// Summaries with more drops than this are logged at WARN, the others at DEBUG.
+    pub warn_threshold: u32,
+}
+
+impl Default for DropLogConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            warn_threshold: 10,
+        }
+    }
+}
+
+/// Configures the dropped frames log for all clients on the page.  The interval applies to the
+/// clients created afterwards.
+pub fn set_drop_log_config(config: DropLogConfig) {
+    DROP_STATS.with(|stats| stats.borrow_mut().config = config);
+}
+
+/// Logs the summary of the drops every interval, timed by `clock`, until the returned timer is
+/// dropped.
+pub(crate) fn start_drop_log(clock: Rc<dyn Clock>) -> Timer {
+    let interval = DROP_STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        stats.started.get_or_insert(clock.now());
+        stats.config.interval
+    });
+    let timer_clock = Rc::clone(&clock);
+    clock.every(
+        interval,
+        Box::new(move || {
+            let summary = DROP_STATS.with(|stats| stats.borrow_mut().flush(timer_clock.now()));
+            if let Some((level, line)) = summary {
+                log!(level, "{}", line);
+            }
+        }),
+    )
+}
+
+/// Counts a dropped frame, to be logged with the summary of the current interval.
+pub(crate) fn record_drop(media_type: MediaType, reason: DropReason) {
+    record_drops(media_type, reason, 1);
+}
+
+pub(crate) fn record_drops(media_type: MediaType, reason: DropReason, count: u32) {
+    if count > 0 {
+        DROP_STATS.with(|stats| stats.borrow_mut().count(media_type, reason, count));
+    }
+}
+
+thread_local! {
+    static DROP_STATS: RefCell<DropStats> = RefCell::new(DropStats::new(DropLogConfig::default()));
+}
+
+struct DropStats {
+    config: DropLogConfig,
+    // Start of the current interval, in milliseconds; None until the log is started.
+    started: Option<f64>,
+    counts: Vec<(MediaType, DropReason, u32)>,
+}
+
+impl DropStats {
+    fn new(config: DropLogConfig) -> Self {
+        Self {
+            config,
+            started: None,
+            counts: Vec::new(),
+        }
+    }
+
+    fn count(&mut self, media_type: MediaType, reason: DropReason, count: u32) {
+        match self
+            .counts
+            .iter_mut()
+            .find(|(t, r, _)| *t == media_type && *r == reason)
+        {
+            Some((_, _, total)) => *total += count,
+            None => self.counts.push((media_type, reason, count)),
+        }
+    }
+
+    // Ends the current interval at `now`, returning its summary unless nothing was dropped.
+    fn flush(&mut self, now: f64) -> Option<(Level, String)> {
+        let started = self.started.replace(now).unwrap_or(now);
+        if self.counts.is_empty() {
+            return None;
+        }
+        let total: u32 = self.counts.iter().map(|(_, _, count)| count).sum();
+        let details = self
+            .counts
+            .iter()
+            .map(|(media_type, reason, count)| format!("{:?} {}={}", media_type, reason, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let level = if total > self.config.warn_threshold {
+            Level::Warn
+        } else {
+            Level::Debug
+        };
+        self.counts.clear();
+        Some((
+            level,
+            format!(
+                "dropped {} frames in {:.1}s: {}",
+                total,
+                (now - started) / 1000.0,
+                details
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn summarizes_drops_once_per_interval() {
+        let mut stats = DropStats::new(DropLogConfig::default());
+        assert_eq!(stats.flush(0.0), None);
+        for _ in 0..31 {
+            stats.count(MediaType::VIDEO, DropReason::EncodeOverload, 1);
+        }
+        stats.count(MediaType::SCREEN, DropReason::Late, 2);
+        let (level, line) = stats.flush(1000.0).unwrap();
+        assert_eq!(level, Level::Warn);
+        assert_eq!(
+            line,
+            "dropped 33 frames in 1.0s: VIDEO encode-overload=31, SCREEN late=2"
+        );
+        assert!(stats.counts.is_empty());
+    }
+
+    #[test]
+    fn logs_few_drops_at_debug() {
+        let mut stats = DropStats::new(DropLogConfig::default());
+        stats.flush(0.0);
+        stats.count(MediaType::AUDIO, DropReason::Late, 2);
+        let (level, _) = stats.flush(1000.0).unwrap();
+        assert_eq!(level, Level::Debug);
+    }
+
+    #[test]
+    fn burst_followed_by_silence_is_logged() {
+        let clock = ManualClock::new();
+        let _timer = start_drop_log(Rc::new(clock.clone()));
+        clock.advance(Duration::from_millis(1500));
+        record_drops(MediaType::VIDEO, DropReason::BufferFull, 12);
+
+        // Nothing else is dropped, yet the burst is summarized at the end of its interval.
+        clock.advance(Duration::from_millis(500));
+        assert!(DROP_STATS.with(|stats| stats.borrow().counts.is_empty()));
+        assert_eq!(
+            DROP_STATS.with(|stats| stats.borrow().started),
+            Some(2000.0)
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//
+// Adaptive bitrate for the camera encoder, driven by the loss the client measures on its path
+// to the server each time the peer monitor runs.
+//
+// The controller is AIMD-style: a window with high loss cuts the bitrate by a factor right away,
+// while the bitrate only grows by a fixed step after several windows in a row with low loss.
+// Loss between the two limits holds the bitrate, so noisy measurements don't make it oscillate.
+//
+
+// Loss above which the bitrate is cut, and below which it may grow.
+const HIGH_LOSS: f64 = 0.10;
+const LOW_LOSS: f64 = 0.02;
+const DECREASE_FACTOR: f64 = 0.85;
+// Windows of low loss in a row before each increase.  The peer monitor runs every 5 seconds.
+const LOW_LOSS_WINDOWS_BEFORE_INCREASE: u8 = 3;
+// Increase step, as a share of the range between the bounds.
+const INCREASE_STEP: f64 = 0.1;
+
+/// Bounds of the camera encoder's adaptive bitrate, see
+/// [`CameraEncoder::set_adaptive_bitrate`](crate::CameraEncoder::set_adaptive_bitrate).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdaptiveBitrate {
+    /// Lowest bitrate to go down to, in bits per second.
+    pub min_bitrate: u32,
+    /// Highest bitrate to go up to, in bits per second.
+    pub max_bitrate: u32,
+}
+
+#[derive(Debug)]
+pub(super) struct BitrateController {
+    bounds: AdaptiveBitrate,
+    bitrate: u32,
+    low_loss_windows: u8,
+}
+
+impl BitrateController {
+    /// Starts at `initial_bitrate`, clamped to the bounds.
+    pub fn new(bounds: AdaptiveBitrate, initial_bitrate: u32) -> Self {
+        Self {
+            bounds,
+            bitrate: initial_bitrate.clamp(bounds.min_bitrate, bounds.max_bitrate),
+            low_loss_windows: 0,
+        }
+    }
+
+    pub fn bitrate(&self) -> u32 {
+        self.bitrate
+    }
+
+    /// Takes the loss of the last window, from 0.0 to 1.0, and returns the new bitrate if it
+    /// changed.
+    pub fn on_loss(&mut self, loss: f64) -> Option<u32> {
+        let bitrate = if loss > HIGH_LOSS {
+            self.low_loss_windows = 0;
+            (self.bitrate as f64 * DECREASE_FACTOR) as u32
+        } else if loss < LOW_LOSS {
+            self.low_loss_windows += 1;
+            if self.low_loss_windows < LOW_LOSS_WINDOWS_BEFORE_INCREASE {
+                return None;
+            }
+            self.low_loss_windows = 0;
+            let range = self.bounds.max_bitrate - self.bounds.min_bitrate;
+            self.bitrate + ((range as f64 * INCREASE_STEP) as u32).max(1)
+        } else {
+            self.low_loss_windows = 0;
+            return None;
+        };
+        let bitrate = bitrate.clamp(self.bounds.min_bitrate, self.bounds.max_bitrate);
+        if bitrate == self.bitrate {
+            return None;
+        }
+        self.bitrate = bitrate;
+        Some(bitrate)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BOUNDS: AdaptiveBitrate = AdaptiveBitrate {
+        min_bitrate: 50_000,
+        max_bitrate: 150_000,
+    };
+
+    #[test]
+    fn cuts_on_loss_and_grows_slowly() {
+        let mut controller = BitrateController::new(BOUNDS, 100_000);
+        assert_eq!(controller.on_loss(0.2), Some(85_000));
+        assert_eq!(controller.on_loss(0.0), None);
+        assert_eq!(controller.on_loss(0.0), None);
+        assert_eq!(controller.on_loss(0.0), Some(95_000));
+
+        // Moderate loss holds the bitrate and restarts the wait for an increase.
+        assert_eq!(controller.on_loss(0.0), None);
+        assert_eq!(controller.on_loss(0.05), None);
+        assert_eq!(controller.on_loss(0.0), None);
+        assert_eq!(controller.on_loss(0.0), None);
+        assert_eq!(controller.on_loss(0.0), Some(105_000));
+    }
+
+    #[test]
+    fn stays_within_bounds() {
+        let mut controller = BitrateController::new(BOUNDS, 500_000);
+        assert_eq!(controller.bitrate(), 150_000);
+        for _ in 0..3 {
+            assert_eq!(controller.on_loss(0.0), None);
+        }
+        for _ in 0..20 {
+            controller.on_loss(0.5);
+        }
+        assert_eq!(controller.bitrate(), 50_000);
+        assert_eq!(controller.on_loss(0.5), None);
+    }
+}
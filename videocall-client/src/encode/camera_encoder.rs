@@ -1,3 +1,4 @@
+use anyhow::{anyhow, Result};
 use gloo_utils::window;
 use js_sys::Array;
 use js_sys::Boolean;
@@ -6,13 +7,13 @@ use js_sys::Reflect;
 use log::debug;
 use log::error;
 use std::sync::atomic::Ordering;
+use videocall_types::protos::media_packet::media_packet::MediaType;
 use videocall_types::protos::packet_wrapper::PacketWrapper;
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::HtmlVideoElement;
-use web_sys::LatencyMode;
 use web_sys::MediaStream;
 use web_sys::MediaStreamConstraints;
 use web_sys::MediaStreamTrack;
@@ -27,11 +28,19 @@ use web_sys::VideoFrame;
 use web_sys::VideoTrack;
 
 use super::super::client::VideoCallClient;
+use super::adaptive_bitrate::{AdaptiveBitrate, BitrateController};
 use super::encoder_state::EncoderState;
-use super::transform::transform_video_chunk;
+use super::rate_control::{
+    configure_latency_mode, configure_rate_control, set_frame_quantizer, LatencyMode, RateControl,
+};
+use super::sender_stats::FrameCounter;
+use super::transform::{transform_end_of_stream, transform_video_chunk};
+use crate::diagnostics::{record_drop, DropReason};
 
-use crate::constants::VIDEO_CODEC;
+use crate::codec::{first_encodable_codec, VideoCodec};
+use crate::constants::MAX_ENCODE_QUEUE_SIZE;
 use crate::constants::VIDEO_HEIGHT;
+use crate::constants::VIDEO_KEYFRAME_INTERVAL;
 use crate::constants::VIDEO_WIDTH;
 
 /// [CameraEncoder] encodes the video from a camera and sends it through a [`VideoCallClient`](crate::VideoCallClient) connection.
@@ -47,6 +56,10 @@ pub struct CameraEncoder {
     client: VideoCallClient,
     video_elem_id: String,
     state: EncoderState,
+    rate_control: RateControl,
+    latency_mode: LatencyMode,
+    adaptive_bitrate: Option<AdaptiveBitrate>,
+    keyframe_interval: Option<u32>,
 }
 
 impl CameraEncoder {
@@ -63,6 +76,10 @@ impl CameraEncoder {
             client,
             video_elem_id: video_elem_id.to_string(),
             state: EncoderState::new(),
+            rate_control: RateControl::Bitrate(100_000),
+            latency_mode: LatencyMode::default(),
+            adaptive_bitrate: None,
+            keyframe_interval: Some(VIDEO_KEYFRAME_INTERVAL),
         }
     }
 
@@ -75,13 +92,14 @@ impl CameraEncoder {
     ///
     /// Disabling encoding after it has started will cause it to stop.
     pub fn set_enabled(&mut self, value: bool) -> bool {
+        self.client.set_media_enabled(MediaType::VIDEO, value);
         self.state.set_enabled(value)
     }
 
     /// Selects a camera:
     ///
     /// * `device_id` - The value of `entry.device_id` for some entry in
-    /// [`media_device_list.video_inputs.devices()`](crate::MediaDeviceList::video_inputs)
+    ///   [`media_device_list.video_inputs.devices()`](crate::MediaDeviceList::video_inputs)
     ///
     /// The encoder starts without a camera associated,
     /// [`encoder.selected(device_id)`](Self::select) must be called prior to starting encoding.
@@ -89,8 +107,77 @@ impl CameraEncoder {
         self.state.select(device_id)
     }
 
+    /// Sets how the encoder trades bitrate against quality.  Defaults to
+    /// `RateControl::Bitrate(100_000)`.
+    ///
+    /// Takes effect the next time the encoder is started.
+    pub fn set_rate_control(&mut self, rate_control: RateControl) {
+        self.rate_control = rate_control;
+    }
+
+    /// Sets whether the encoder favours latency or quality.  Defaults to
+    /// `LatencyMode::Realtime`, which suits interactive calls; `LatencyMode::Quality` suits
+    /// recording.
+    ///
+    /// Takes effect the next time the encoder is started.
+    pub fn set_latency_mode(&mut self, latency_mode: LatencyMode) {
+        self.latency_mode = latency_mode;
+    }
+
+    /// Lets the encoder adapt its bitrate to the network within `bounds`, or keeps it fixed if
+    /// `None`, which is the default.
+    ///
+    /// The bitrate starts from the one set with [set_rate_control](Self::set_rate_control), is
+    /// cut when many of the peers' heartbeats go missing, and grows slowly while few do.  Each
+    /// change is reported through
+    /// [`on_encoder_settings_update`](crate::VideoCallClientOptions::on_encoder_settings_update).
+    /// It has no effect with `RateControl::Quantizer`, unless the browser falls back to bitrate
+    /// mode.
+    ///
+    /// Takes effect the next time the encoder is started.  Fails if `min_bitrate` is above
+    /// `max_bitrate`.
+    pub fn set_adaptive_bitrate(&mut self, bounds: Option<AdaptiveBitrate>) -> Result<()> {
+        if let Some(bounds) = bounds {
+            if bounds.min_bitrate > bounds.max_bitrate {
+                return Err(anyhow!(
+                    "adaptive bitrate minimum {} is above its maximum {}",
+                    bounds.min_bitrate,
+                    bounds.max_bitrate
+                ));
+            }
+        }
+        self.adaptive_bitrate = bounds;
+        Ok(())
+    }
+
+    /// Sets how many frames the encoder sends between periodic key frames, or `None` to send key
+    /// frames only when needed: at the start, when a peer asks for one and after the bitrate
+    /// changes.  Defaults to `Some(50)`.
+    ///
+    /// Shorter intervals let receivers recover from lost packets sooner on lossy links, at the
+    /// cost of bitrate, since key frames are much larger than the frames in between.  With
+    /// [adaptive bitrate](Self::set_adaptive_bitrate), each bitrate change starts with a key frame
+    /// and restarts the interval, so that the two don't add up to back-to-back key frames.  Keep
+    /// in mind that the key frames come out of the same bitrate, so a short interval at a low
+    /// adapted bitrate leaves little for the other frames.
+    ///
+    /// Takes effect the next time the encoder is started.
+    pub fn set_keyframe_interval(&mut self, frames: Option<u32>) {
+        self.keyframe_interval = frames;
+    }
+
+    /// Uses an already open camera stream, e.g. from
+    /// [`LocalPreview::take_camera_stream`](crate::LocalPreview::take_camera_stream), the next time the
+    /// encoder is started, instead of opening the selected camera again.
+    ///
+    /// The stream is only used if it comes from the selected device, otherwise it is stopped.
+    pub fn set_stream(&mut self, stream: MediaStream) {
+        self.state.set_stream(stream);
+    }
+
     /// Stops encoding after it has been started.
     pub fn stop(&mut self) {
+        self.client.set_media_enabled(MediaType::VIDEO, false);
         self.state.stop()
     }
 
@@ -106,34 +193,54 @@ impl CameraEncoder {
         let userid = client.userid().clone();
         let aes = client.aes();
         let video_elem_id = self.video_elem_id.clone();
+        let eos_client = client.clone();
         let EncoderState {
             destroy,
             enabled,
             switching,
             ..
         } = self.state.clone();
-        let video_output_handler = {
-            let mut buffer: [u8; 100000] = [0; 100000];
-            let mut sequence_number = 0;
-            Box::new(move |chunk: JsValue| {
-                let chunk = web_sys::EncodedVideoChunk::from(chunk);
-                let packet: PacketWrapper = transform_video_chunk(
-                    chunk,
-                    sequence_number,
-                    &mut buffer,
-                    &userid,
-                    aes.clone(),
-                );
-                client.send_packet(packet);
-                sequence_number += 1;
-            })
-        };
         let device_id = if let Some(vid) = &self.state.selected {
             vid.to_string()
         } else {
             return;
         };
+        let stream = self.state.take_stream(&device_id);
+        let rate_control = self.rate_control;
+        let latency_mode = self.latency_mode;
+        let adaptive_bitrate = self.adaptive_bitrate;
+        let keyframe_interval = self.keyframe_interval;
+        let mut frames = FrameCounter::new(MediaType::VIDEO, client.now());
         wasm_bindgen_futures::spawn_local(async move {
+            let codec = first_encodable_codec(
+                &client.video_codec_candidates(),
+                VIDEO_WIDTH as u32,
+                VIDEO_HEIGHT as u32,
+            )
+            .await
+            .unwrap_or(VideoCodec::VP9);
+            let settings = format!("codec: {}", codec);
+            let codec = codec.codec_string();
+            let video_output_handler = {
+                let mut buffer: [u8; 100000] = [0; 100000];
+                let mut sequence_number = 0;
+                let encoded = frames.encoded();
+                Box::new(move |chunk: JsValue| {
+                    encoded.set(encoded.get() + 1);
+                    let chunk = web_sys::EncodedVideoChunk::from(chunk);
+                    let packet: PacketWrapper = transform_video_chunk(
+                        chunk,
+                        sequence_number,
+                        codec,
+                        &mut buffer,
+                        &userid,
+                        aes.clone(),
+                    );
+                    client.send_packet(packet);
+                    sequence_number += 1;
+                })
+            };
+
             let navigator = window().navigator();
             let video_element = window()
                 .document()
@@ -142,21 +249,26 @@ impl CameraEncoder {
                 .unwrap()
                 .unchecked_into::<HtmlVideoElement>();
 
-            let media_devices = navigator.media_devices().unwrap();
-            let mut constraints = MediaStreamConstraints::new();
-            let mut media_info = web_sys::MediaTrackConstraints::new();
-            media_info.device_id(&device_id.into());
+            let device = match stream {
+                Some(stream) => stream,
+                None => {
+                    let media_devices = navigator.media_devices().unwrap();
+                    let mut constraints = MediaStreamConstraints::new();
+                    let mut media_info = web_sys::MediaTrackConstraints::new();
+                    media_info.device_id(&device_id.into());
 
-            constraints.video(&media_info.into());
-            constraints.audio(&Boolean::from(false));
+                    constraints.video(&media_info.into());
+                    constraints.audio(&Boolean::from(false));
 
-            let devices_query = media_devices
-                .get_user_media_with_constraints(&constraints)
-                .unwrap();
-            let device = JsFuture::from(devices_query)
-                .await
-                .unwrap()
-                .unchecked_into::<MediaStream>();
+                    let devices_query = media_devices
+                        .get_user_media_with_constraints(&constraints)
+                        .unwrap();
+                    JsFuture::from(devices_query)
+                        .await
+                        .unwrap()
+                        .unchecked_into::<MediaStream>()
+                }
+            };
             video_element.set_src_object(Some(&device));
             video_element.set_muted(true);
 
@@ -191,11 +303,43 @@ impl CameraEncoder {
             video_settings.height(VIDEO_HEIGHT);
 
             let mut video_encoder_config =
-                VideoEncoderConfig::new(VIDEO_CODEC, VIDEO_HEIGHT as u32, VIDEO_WIDTH as u32);
+                VideoEncoderConfig::new(codec, VIDEO_HEIGHT as u32, VIDEO_WIDTH as u32);
 
-            video_encoder_config.bitrate(100_000f64);
-            video_encoder_config.latency_mode(LatencyMode::Realtime);
+            // Latency mode first, so that the quantizer support check includes it.
+            configure_latency_mode(&mut video_encoder_config, latency_mode).await;
+            let quantizer =
+                configure_rate_control(&mut video_encoder_config, rate_control, "motion").await;
             video_encoder.configure(&video_encoder_config);
+            let mut bitrate_controller = adaptive_bitrate
+                .filter(|_| quantizer.is_none())
+                .map(|bounds| BitrateController::new(bounds, rate_control.bitrate()));
+            // The server's congestion feedback caps the bitrate, whether adaptive or not.
+            let mut bitrate_cap = eos_client.bitrate_cap().filter(|_| quantizer.is_none());
+            let target_bitrate = |controller: &Option<BitrateController>, cap: Option<u32>| {
+                let bitrate = controller
+                    .as_ref()
+                    .map_or(rate_control.bitrate(), BitrateController::bitrate);
+                cap.map_or(bitrate, |cap| bitrate.min(cap))
+            };
+            let adaptive = bitrate_controller.is_some();
+            let bitrate_settings = |bitrate: u32, cap: Option<u32>| match cap {
+                Some(cap) => format!(
+                    "{}, bitrate: {} kbps, server cap: {} kbps",
+                    settings,
+                    bitrate / 1000,
+                    cap / 1000
+                ),
+                None if adaptive => format!("{}, bitrate: {} kbps", settings, bitrate / 1000),
+                None => settings.clone(),
+            };
+            let mut bitrate = target_bitrate(&bitrate_controller, bitrate_cap);
+            if bitrate != rate_control.bitrate() {
+                video_encoder_config.bitrate(bitrate as f64);
+                video_encoder.configure(&video_encoder_config);
+            }
+            eos_client.report_encoder_settings(bitrate_settings(bitrate, bitrate_cap));
+            let mut link_loss_window = eos_client.link_loss().map(|(window, _)| window);
+            let mut keyframe_requests = eos_client.keyframe_requests();
 
             let video_processor =
                 MediaStreamTrackProcessor::new(&MediaStreamTrackProcessorInit::new(
@@ -208,7 +352,8 @@ impl CameraEncoder {
                 .unchecked_into::<ReadableStreamDefaultReader>();
 
             // Start encoding video and audio.
-            let mut video_frame_counter = 0;
+            // Frames since the last key frame.
+            let mut video_frame_counter: u32 = 0;
             let poll_video = async {
                 loop {
                     if !enabled.load(Ordering::Acquire)
@@ -220,7 +365,16 @@ impl CameraEncoder {
                             .unchecked_into::<MediaStreamTrack>()
                             .stop();
                         video_encoder.close();
-                        switching.store(false, Ordering::Release);
+                        // Closing discards the queued frames.
+                        eos_client.report_sender_stats(frames.stats(0));
+                        // A device switch restarts the stream, anything else ends it.
+                        if !switching.swap(false, Ordering::AcqRel) {
+                            eos_client.send_packet(transform_end_of_stream(
+                                MediaType::VIDEO,
+                                eos_client.userid(),
+                                eos_client.aes(),
+                            ));
+                        }
                         return;
                     }
                     match JsFuture::from(video_reader.read()).await {
@@ -228,10 +382,64 @@ impl CameraEncoder {
                             let video_frame = Reflect::get(&js_frame, &JsString::from("value"))
                                 .unwrap()
                                 .unchecked_into::<VideoFrame>();
+                            let queue_size = video_encoder.encode_queue_size();
+                            if let Some(stats) = frames.poll(eos_client.now(), queue_size) {
+                                eos_client.report_sender_stats(stats);
+                            }
+                            if queue_size > MAX_ENCODE_QUEUE_SIZE {
+                                video_frame.close();
+                                record_drop(MediaType::VIDEO, DropReason::EncodeOverload);
+                                frames.on_dropped_before_encode();
+                                continue;
+                            }
                             let mut opts = VideoEncoderEncodeOptions::new();
-                            video_frame_counter = (video_frame_counter + 1) % 50;
+                            video_frame_counter = video_frame_counter.saturating_add(1);
+                            if keyframe_interval.is_some_and(|n| video_frame_counter >= n) {
+                                video_frame_counter = 0;
+                            }
+                            let mut changed = false;
+                            if let Some(controller) = &mut bitrate_controller {
+                                match eos_client.link_loss() {
+                                    Some((window, loss)) if link_loss_window != Some(window) => {
+                                        link_loss_window = Some(window);
+                                        if let Some(bitrate) = controller.on_loss(loss) {
+                                            debug!("adapting video bitrate to {}", bitrate);
+                                            changed = true;
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                            }
+                            let cap = eos_client.bitrate_cap().filter(|_| quantizer.is_none());
+                            if cap != bitrate_cap {
+                                debug!("server capped video bitrate to {:?}", cap);
+                                bitrate_cap = cap;
+                                changed = true;
+                            }
+                            if changed {
+                                let target = target_bitrate(&bitrate_controller, bitrate_cap);
+                                if target != bitrate {
+                                    bitrate = target;
+                                    video_encoder_config.bitrate(bitrate as f64);
+                                    // Reconfiguring needs a key frame to start from.
+                                    video_encoder.configure(&video_encoder_config);
+                                    video_frame_counter = 0;
+                                }
+                                eos_client.report_encoder_settings(bitrate_settings(
+                                    bitrate,
+                                    bitrate_cap,
+                                ));
+                            }
+                            if eos_client.keyframe_requests() != keyframe_requests {
+                                keyframe_requests = eos_client.keyframe_requests();
+                                video_frame_counter = 0;
+                            }
                             opts.key_frame(video_frame_counter == 0);
+                            if let Some(quantizer) = quantizer {
+                                set_frame_quantizer(&opts, codec, quantizer);
+                            }
                             video_encoder.encode_with_options(&video_frame, &opts);
+                            frames.on_submitted();
                             video_frame.close();
                         }
                         Err(e) => {
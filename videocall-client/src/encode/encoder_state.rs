@@ -1,6 +1,12 @@
+use js_sys::Array;
+use js_sys::Reflect;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::MediaStream;
+use web_sys::MediaStreamTrack;
 
 //
 // EncoderState struct contains state variables that are common among the encoders, and the logic
@@ -13,6 +19,9 @@ pub struct EncoderState {
     pub(super) enabled: Arc<AtomicBool>,
     pub(super) selected: Option<String>,
     pub(super) switching: Arc<AtomicBool>,
+    // Stream that was already acquired elsewhere, used by the next start instead of opening the
+    // selected device again.
+    pub(super) stream: Option<MediaStream>,
 }
 
 impl EncoderState {
@@ -22,6 +31,7 @@ impl EncoderState {
             enabled: Arc::new(AtomicBool::new(false)),
             selected: None,
             switching: Arc::new(AtomicBool::new(false)),
+            stream: None,
         }
     }
 
@@ -52,4 +62,31 @@ impl EncoderState {
     pub fn stop(&mut self) {
         self.destroy.store(true, Ordering::Release);
     }
+
+    pub fn set_stream(&mut self, stream: MediaStream) {
+        self.stream = Some(stream);
+    }
+
+    // Takes the stream passed to set_stream() if it comes from the given device, otherwise stops it.
+    pub fn take_stream(&mut self, device_id: &str) -> Option<MediaStream> {
+        let stream = self.stream.take()?;
+        let track = stream
+            .get_tracks()
+            .find(&mut |_: JsValue, _: u32, _: Array| true)
+            .dyn_into::<MediaStreamTrack>()
+            .ok()?;
+        let stream_device_id = Reflect::get(&track.get_settings(), &"deviceId".into())
+            .ok()
+            .and_then(|id| id.as_string());
+        if stream_device_id.as_deref() == Some(device_id) {
+            Some(stream)
+        } else {
+            stream
+                .get_tracks()
+                .for_each(&mut |track: JsValue, _: u32, _: Array| {
+                    track.unchecked_into::<MediaStreamTrack>().stop()
+                });
+            None
+        }
+    }
 }
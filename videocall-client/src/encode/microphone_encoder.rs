@@ -5,6 +5,7 @@ use js_sys::JsString;
 use js_sys::Reflect;
 use log::error;
 use std::sync::atomic::Ordering;
+use videocall_types::protos::media_packet::media_packet::MediaType;
 use videocall_types::protos::packet_wrapper::PacketWrapper;
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::JsCast;
@@ -21,16 +22,21 @@ use web_sys::MediaStreamTrack;
 use web_sys::MediaStreamTrackProcessor;
 use web_sys::MediaStreamTrackProcessorInit;
 use web_sys::ReadableStreamDefaultReader;
+use yew::prelude::Callback;
 
 use super::super::client::VideoCallClient;
 use super::encoder_state::EncoderState;
-use super::transform::transform_audio_chunk;
+use super::opus_options::{configure_opus, OpusOptions};
+use super::transform::{transform_audio_chunk, transform_end_of_stream};
+use crate::audio_level::{audio_data_rms, LevelMeter};
 
 use crate::constants::AUDIO_BITRATE;
 use crate::constants::AUDIO_CHANNELS;
 use crate::constants::AUDIO_CODEC;
 use crate::constants::AUDIO_SAMPLE_RATE;
 
+const AUDIO_LEVEL_REPORTS_PER_SECOND: u32 = 20;
+
 /// [MicrophoneEncoder] encodes the audio from a microphone and sends it through a [`VideoCallClient`](crate::VideoCallClient) connection.
 ///
 /// See also:
@@ -40,19 +46,26 @@ use crate::constants::AUDIO_SAMPLE_RATE;
 pub struct MicrophoneEncoder {
     client: VideoCallClient,
     state: EncoderState,
+    on_audio_level: Callback<f32>,
+    opus_options: OpusOptions,
 }
 
 impl MicrophoneEncoder {
     /// Construct a microphone encoder:
     ///
     /// * `client` - an instance of a [`VideoCallClient`](crate::VideoCallClient).  It does not need to be currently connected.
+    /// * `on_audio_level` - called about 20 times per second while encoding with the smoothed
+    ///   level of the captured audio, from 0.0 for silence to 1.0, e.g. for a level meter.  It
+    ///   doesn't affect the encoded audio.
     ///
     /// The encoder is created in a disabled state, [`encoder.set_enabled(true)`](Self::set_enabled) must be called before it can start encoding.
     /// The encoder is created without a camera selected, [`encoder.select(device_id)`](Self::select) must be called before it can start encoding.
-    pub fn new(client: VideoCallClient) -> Self {
+    pub fn new(client: VideoCallClient, on_audio_level: Callback<f32>) -> Self {
         Self {
             client,
             state: EncoderState::new(),
+            on_audio_level,
+            opus_options: OpusOptions::default(),
         }
     }
 
@@ -65,13 +78,14 @@ impl MicrophoneEncoder {
     ///
     /// Disabling encoding after it has started will cause it to stop.
     pub fn set_enabled(&mut self, value: bool) -> bool {
+        self.client.set_media_enabled(MediaType::AUDIO, value);
         self.state.set_enabled(value)
     }
 
     /// Selects a microphone:
     ///
     /// * `device_id` - The value of `entry.device_id` for some entry in
-    /// [`media_device_list.audio_inputs.devices()`](crate::MediaDeviceList::audio_inputs)
+    ///   [`media_device_list.audio_inputs.devices()`](crate::MediaDeviceList::audio_inputs)
     ///
     /// The encoder starts without a microphone associated,
     /// [`encoder.selected(device_id)`](Self::select) must be called prior to starting encoding.
@@ -79,8 +93,26 @@ impl MicrophoneEncoder {
         self.state.select(device)
     }
 
+    /// Uses an already open microphone stream, e.g. from
+    /// [`LocalPreview::take_microphone_stream`](crate::LocalPreview::take_microphone_stream), the next time the
+    /// encoder is started, instead of opening the selected microphone again.
+    ///
+    /// The stream is only used if it comes from the selected device, otherwise it is stopped.
+    pub fn set_stream(&mut self, stream: MediaStream) {
+        self.state.set_stream(stream);
+    }
+
+    /// Sets the Opus options, e.g. DTX to save bandwidth while the user is silent, or FEC to
+    /// better withstand packet loss.  Takes effect the next time the encoder is started.
+    ///
+    /// Options the browser doesn't support are ignored with a warning.
+    pub fn set_opus_options(&mut self, options: OpusOptions) {
+        self.opus_options = options;
+    }
+
     /// Stops encoding after it has been started.
     pub fn stop(&mut self) {
+        self.client.set_media_enabled(MediaType::AUDIO, false);
         self.state.stop()
     }
 
@@ -94,16 +126,26 @@ impl MicrophoneEncoder {
         } else {
             return;
         };
+        let stream = self.state.take_stream(&device_id);
         let client = self.client.clone();
         let userid = client.userid().clone();
         let aes = client.aes();
+        let eos_client = client.clone();
+        let on_audio_level = self.on_audio_level.clone();
+        let opus_options = self.opus_options;
         let audio_output_handler = {
             let mut buffer: [u8; 100000] = [0; 100000];
             let mut sequence = 0;
             Box::new(move |chunk: JsValue| {
                 let chunk = web_sys::EncodedAudioChunk::from(chunk);
-                let packet: PacketWrapper =
-                    transform_audio_chunk(&chunk, &mut buffer, &userid, sequence, aes.clone());
+                let packet: PacketWrapper = transform_audio_chunk(
+                    &chunk,
+                    MediaType::AUDIO,
+                    &mut buffer,
+                    &userid,
+                    sequence,
+                    aes.clone(),
+                );
                 client.send_packet(packet);
                 sequence += 1;
             })
@@ -116,22 +158,27 @@ impl MicrophoneEncoder {
         } = self.state.clone();
 
         wasm_bindgen_futures::spawn_local(async move {
-            let navigator = window().navigator();
-            let media_devices = navigator.media_devices().unwrap();
-            // TODO: Add dropdown so that user can select the device that they want to use.
-            let mut constraints = MediaStreamConstraints::new();
-            let mut media_info = web_sys::MediaTrackConstraints::new();
-            media_info.device_id(&device_id.into());
-
-            constraints.audio(&media_info.into());
-            constraints.video(&Boolean::from(false));
-            let devices_query = media_devices
-                .get_user_media_with_constraints(&constraints)
-                .unwrap();
-            let device = JsFuture::from(devices_query)
-                .await
-                .unwrap()
-                .unchecked_into::<MediaStream>();
+            let device = match stream {
+                Some(stream) => stream,
+                None => {
+                    let navigator = window().navigator();
+                    let media_devices = navigator.media_devices().unwrap();
+                    // TODO: Add dropdown so that user can select the device that they want to use.
+                    let mut constraints = MediaStreamConstraints::new();
+                    let mut media_info = web_sys::MediaTrackConstraints::new();
+                    media_info.device_id(&device_id.into());
+
+                    constraints.audio(&media_info.into());
+                    constraints.video(&Boolean::from(false));
+                    let devices_query = media_devices
+                        .get_user_media_with_constraints(&constraints)
+                        .unwrap();
+                    JsFuture::from(devices_query)
+                        .await
+                        .unwrap()
+                        .unchecked_into::<MediaStream>()
+                }
+            };
 
             // Setup audio encoder.
 
@@ -157,6 +204,7 @@ impl MicrophoneEncoder {
             audio_encoder_config.bitrate(AUDIO_BITRATE);
             audio_encoder_config.sample_rate(AUDIO_SAMPLE_RATE);
             audio_encoder_config.number_of_channels(AUDIO_CHANNELS);
+            configure_opus(&audio_encoder_config, opus_options).await;
             audio_encoder.configure(&audio_encoder_config);
 
             let audio_processor =
@@ -169,16 +217,23 @@ impl MicrophoneEncoder {
                 .get_reader()
                 .unchecked_into::<ReadableStreamDefaultReader>();
 
+            let mut level_meter = LevelMeter::new(AUDIO_LEVEL_REPORTS_PER_SECOND);
             let poll_audio = async {
                 loop {
                     if !enabled.load(Ordering::Acquire)
                         || destroy.load(Ordering::Acquire)
                         || switching.load(Ordering::Acquire)
                     {
-                        switching.store(false, Ordering::Release);
                         let audio_track = audio_track.clone().unchecked_into::<MediaStreamTrack>();
                         audio_track.stop();
                         audio_encoder.close();
+                        if !switching.swap(false, Ordering::AcqRel) {
+                            eos_client.send_packet(transform_end_of_stream(
+                                MediaType::AUDIO,
+                                eos_client.userid(),
+                                eos_client.aes(),
+                            ));
+                        }
                         return;
                     }
                     match JsFuture::from(audio_reader.read()).await {
@@ -186,6 +241,12 @@ impl MicrophoneEncoder {
                             let audio_frame = Reflect::get(&js_frame, &JsString::from("value"))
                                 .unwrap()
                                 .unchecked_into::<AudioData>();
+                            if let Some(level) = level_meter
+                                .push(audio_data_rms(&audio_frame), audio_frame.timestamp())
+                            {
+                                eos_client.set_speaking(level);
+                                on_audio_level.emit(level);
+                            }
                             audio_encoder.encode(&audio_frame);
                             audio_frame.close();
                         }
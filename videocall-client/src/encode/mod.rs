@@ -1,9 +1,17 @@
+mod adaptive_bitrate;
 mod camera_encoder;
 mod encoder_state;
 mod microphone_encoder;
+mod opus_options;
+mod rate_control;
 mod screen_encoder;
-mod transform;
+mod sender_stats;
+pub(crate) mod transform;
 
+pub use adaptive_bitrate::AdaptiveBitrate;
 pub use camera_encoder::CameraEncoder;
 pub use microphone_encoder::MicrophoneEncoder;
+pub use opus_options::OpusOptions;
+pub use rate_control::{LatencyMode, RateControl};
 pub use screen_encoder::ScreenEncoder;
+pub use sender_stats::SenderStats;
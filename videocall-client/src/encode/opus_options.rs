@@ -0,0 +1,67 @@
+//
+// OpusOptions tunes the Opus encoder of the microphone.  web-sys doesn't expose the `opus`
+// member of AudioEncoderConfig yet, so it is set through Reflect.
+//
+
+use crate::codec::is_supported;
+use js_sys::{Object, Reflect};
+use log::warn;
+use wasm_bindgen::JsValue;
+use web_sys::{AudioEncoder, AudioEncoderConfig};
+
+// Expected packet loss the encoder sizes its FEC data for.  Opus adds no FEC at 0%.
+const FEC_EXPECTED_LOSS_PERCENT: u32 = 10;
+
+/// Opus settings of the microphone encoder, see
+/// [`MicrophoneEncoder::set_opus_options`](crate::MicrophoneEncoder::set_opus_options).  The
+/// default leaves both DTX and FEC off and the complexity to the browser.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpusOptions {
+    /// Discontinuous transmission: during silence, only a comfort noise packet is sent every
+    /// 400 ms or so instead of one packet per 20 ms, which saves most of the bitrate.  Audio
+    /// packets then arrive in bursts with gaps, which receivers handle as they do loss; peers
+    /// stay alive through their heartbeats, which are sent regardless.
+    pub dtx: bool,
+
+    /// In-band forward error correction: each packet also carries a low-bitrate copy of the
+    /// previous one, from which a receiver can recover a single lost packet.  Costs bitrate, and
+    /// is tuned for about 10% loss.
+    pub fec: bool,
+
+    /// Encoder complexity from 0, the cheapest, to 10, the best quality.  `None` leaves it to
+    /// the browser.
+    pub complexity: Option<u8>,
+}
+
+/// Applies `options` to `config`, leaving the browser's defaults in place if it doesn't support
+/// them.
+pub(super) async fn configure_opus(config: &AudioEncoderConfig, options: OpusOptions) {
+    if options == OpusOptions::default() {
+        return;
+    }
+    let opus = Object::new();
+    let _ = Reflect::set(&opus, &"usedtx".into(), &options.dtx.into());
+    let _ = Reflect::set(&opus, &"useinbandfec".into(), &options.fec.into());
+    if options.fec {
+        let _ = Reflect::set(
+            &opus,
+            &"packetlossperc".into(),
+            &JsValue::from(FEC_EXPECTED_LOSS_PERCENT),
+        );
+    }
+    if let Some(complexity) = options.complexity {
+        let _ = Reflect::set(
+            &opus,
+            &"complexity".into(),
+            &JsValue::from(complexity.min(10) as u32),
+        );
+    }
+    let _ = Reflect::set(config, &"opus".into(), &opus);
+    if !is_supported(AudioEncoder::is_config_supported(config)).await {
+        warn!(
+            "opus options {:?} are not supported, using the browser's defaults",
+            options
+        );
+        let _ = Reflect::delete_property(config, &"opus".into());
+    }
+}
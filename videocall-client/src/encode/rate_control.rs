@@ -0,0 +1,122 @@
+//
+// RateControl selects how the video encoders trade bitrate against quality, and LatencyMode how
+// they trade latency against quality.  This module holds the WebCodecs plumbing for both.  web-sys doesn't expose `bitrateMode`, `contentHint` or the
+// per-codec encode options yet, so those are set through Reflect.
+//
+
+use crate::codec::is_supported;
+use js_sys::{Object, Reflect};
+use log::warn;
+use wasm_bindgen::JsValue;
+use web_sys::{VideoEncoder, VideoEncoderConfig, VideoEncoderEncodeOptions};
+
+/// Latency mode of the video encoders, see
+/// [`CameraEncoder::set_latency_mode`](crate::CameraEncoder::set_latency_mode) and
+/// [`ScreenEncoder::set_latency_mode`](crate::ScreenEncoder::set_latency_mode).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LatencyMode {
+    /// Emit each frame as soon as possible, dropping frames if needed to keep up.  Best for
+    /// interactive calls.
+    #[default]
+    Realtime,
+
+    /// Let the encoder buffer frames to improve quality, e.g. when recording.
+    Quality,
+}
+
+impl From<LatencyMode> for web_sys::LatencyMode {
+    fn from(latency_mode: LatencyMode) -> Self {
+        match latency_mode {
+            LatencyMode::Realtime => web_sys::LatencyMode::Realtime,
+            LatencyMode::Quality => web_sys::LatencyMode::Quality,
+        }
+    }
+}
+
+/// Applies `latency_mode` to `config`, leaving the browser's default in place if it doesn't
+/// support the requested mode.
+pub(super) async fn configure_latency_mode(
+    config: &mut VideoEncoderConfig,
+    latency_mode: LatencyMode,
+) {
+    config.latency_mode(latency_mode.into());
+    if !is_supported(VideoEncoder::is_config_supported(config)).await {
+        warn!(
+            "latency mode {:?} is not supported, using the browser's default",
+            latency_mode
+        );
+        let _ = Reflect::delete_property(config, &"latencyMode".into());
+    }
+}
+
+/// Rate control mode of the video encoders, see
+/// [`CameraEncoder::set_rate_control`](crate::CameraEncoder::set_rate_control) and
+/// [`ScreenEncoder::set_rate_control`](crate::ScreenEncoder::set_rate_control).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateControl {
+    /// Target a bitrate, in bits per second.  Quality drops when there is a lot of motion.
+    Bitrate(u32),
+
+    /// Encode every frame with a fixed quantizer, so quality stays constant and the bitrate
+    /// varies with the content.  Lower is sharper; the range is 0-63 for VP9 and AV1.
+    ///
+    /// Falls back to `fallback_bitrate` when the browser doesn't support quantizer mode for the
+    /// configured codec.
+    Quantizer {
+        quantizer: u8,
+        fallback_bitrate: u32,
+    },
+}
+
+impl RateControl {
+    pub(super) fn bitrate(&self) -> u32 {
+        match self {
+            RateControl::Bitrate(bitrate) => *bitrate,
+            RateControl::Quantizer {
+                fallback_bitrate, ..
+            } => *fallback_bitrate,
+        }
+    }
+}
+
+/// Applies `rate_control` to `config`, checking with the browser that quantizer mode is
+/// supported.  Returns the quantizer to pass to [set_frame_quantizer], or `None` when encoding
+/// in bitrate mode.
+pub(super) async fn configure_rate_control(
+    config: &mut VideoEncoderConfig,
+    rate_control: RateControl,
+    content_hint: &str,
+) -> Option<u8> {
+    config.bitrate(rate_control.bitrate() as f64);
+    // contentHint lets the encoder favour sharpness ("detail", "text") or smoothness ("motion").
+    let _ = Reflect::set(config, &"contentHint".into(), &content_hint.into());
+    let RateControl::Quantizer { quantizer, .. } = rate_control else {
+        return None;
+    };
+    let _ = Reflect::set(config, &"bitrateMode".into(), &"quantizer".into());
+    if is_supported(VideoEncoder::is_config_supported(config)).await {
+        Some(quantizer)
+    } else {
+        warn!("quantizer rate control is not supported, falling back to bitrate");
+        let _ = Reflect::delete_property(config, &"bitrateMode".into());
+        None
+    }
+}
+
+/// Sets the per-frame quantizer in the encode options, keyed by the codec family as WebCodecs
+/// expects (`{ vp9: { quantizer } }`, `{ av1: { quantizer } }`...).
+pub(super) fn set_frame_quantizer(opts: &VideoEncoderEncodeOptions, codec: &str, quantizer: u8) {
+    let family = match codec.split('.').next() {
+        Some("vp09") => "vp9",
+        Some("av01") => "av1",
+        Some("avc1") | Some("avc3") => "avc",
+        _ => return,
+    };
+    let codec_opts = Object::new();
+    let _ = Reflect::set(
+        &codec_opts,
+        &"quantizer".into(),
+        &JsValue::from(quantizer as u32),
+    );
+    let _ = Reflect::set(opts, &family.into(), &codec_opts);
+}
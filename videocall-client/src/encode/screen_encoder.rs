@@ -2,14 +2,20 @@ use gloo_utils::window;
 use js_sys::Array;
 use js_sys::JsString;
 use js_sys::Reflect;
-use log::error;
-use std::sync::atomic::Ordering;
+use log::{debug, error};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use videocall_types::protos::media_packet::media_packet::MediaType;
 use videocall_types::protos::packet_wrapper::PacketWrapper;
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::LatencyMode;
+use web_sys::AudioData;
+use web_sys::AudioEncoder;
+use web_sys::AudioEncoderConfig;
+use web_sys::AudioEncoderInit;
+use web_sys::DisplayMediaStreamConstraints;
 use web_sys::MediaStream;
 use web_sys::MediaStreamTrack;
 use web_sys::MediaStreamTrackProcessor;
@@ -24,8 +30,16 @@ use web_sys::VideoTrack;
 
 use super::super::client::VideoCallClient;
 use super::encoder_state::EncoderState;
-use super::transform::transform_screen_chunk;
+use super::rate_control::{
+    configure_latency_mode, configure_rate_control, set_frame_quantizer, LatencyMode, RateControl,
+};
+use super::sender_stats::FrameCounter;
+use super::transform::{transform_audio_chunk, transform_end_of_stream, transform_screen_chunk};
+use crate::diagnostics::{record_drop, DropReason};
 
+use crate::constants::AUDIO_BITRATE;
+use crate::constants::AUDIO_CODEC;
+use crate::constants::MAX_ENCODE_QUEUE_SIZE;
 use crate::constants::SCREEN_HEIGHT;
 use crate::constants::SCREEN_WIDTH;
 use crate::constants::VIDEO_CODEC;
@@ -39,6 +53,9 @@ use crate::constants::VIDEO_CODEC;
 pub struct ScreenEncoder {
     client: VideoCallClient,
     state: EncoderState,
+    rate_control: RateControl,
+    latency_mode: LatencyMode,
+    share_audio: bool,
 }
 
 impl ScreenEncoder {
@@ -51,6 +68,9 @@ impl ScreenEncoder {
         Self {
             client,
             state: EncoderState::new(),
+            rate_control: RateControl::Bitrate(64_000),
+            latency_mode: LatencyMode::default(),
+            share_audio: false,
         }
     }
 
@@ -63,11 +83,42 @@ impl ScreenEncoder {
     ///
     /// Disabling encoding after it has started will cause it to stop.
     pub fn set_enabled(&mut self, value: bool) -> bool {
+        self.client.set_media_enabled(MediaType::SCREEN, value);
         self.state.set_enabled(value)
     }
 
+    /// Sets how the encoder trades bitrate against quality.  Defaults to
+    /// `RateControl::Bitrate(64_000)`.
+    ///
+    /// Takes effect the next time the encoder is started.
+    pub fn set_rate_control(&mut self, rate_control: RateControl) {
+        self.rate_control = rate_control;
+    }
+
+    /// Sets whether the encoder favours latency or quality.  Defaults to
+    /// `LatencyMode::Realtime`, which suits interactive calls; `LatencyMode::Quality` suits
+    /// recording.
+    ///
+    /// Takes effect the next time the encoder is started.
+    pub fn set_latency_mode(&mut self, latency_mode: LatencyMode) {
+        self.latency_mode = latency_mode;
+    }
+
+    /// Sets whether to ask the browser for the audio of the shared tab or system along with the
+    /// video.  Defaults to `false`.
+    ///
+    /// The audio is sent as `MediaType::SCREEN_AUDIO` and played by the peers along with the
+    /// sharer's microphone.  Browsers only offer audio for some sources, e.g. tabs in Chrome, and
+    /// the user may decline it; the screen is then shared without audio.
+    ///
+    /// Takes effect the next time the encoder is started.
+    pub fn set_share_audio(&mut self, share_audio: bool) {
+        self.share_audio = share_audio;
+    }
+
     /// Stops encoding after it has been started.
     pub fn stop(&mut self) {
+        self.client.set_media_enabled(MediaType::SCREEN, false);
         self.state.stop()
     }
 
@@ -83,10 +134,14 @@ impl ScreenEncoder {
         let client = self.client.clone();
         let userid = client.userid().clone();
         let aes = client.aes();
+        let eos_client = client.clone();
+        let mut frames = FrameCounter::new(MediaType::SCREEN, client.now());
         let screen_output_handler = {
             let mut buffer: [u8; 150000] = [0; 150000];
             let mut sequence_number = 0;
+            let encoded = frames.encoded();
             Box::new(move |chunk: JsValue| {
+                encoded.set(encoded.get() + 1);
                 let chunk = web_sys::EncodedVideoChunk::from(chunk);
                 let packet: PacketWrapper = transform_screen_chunk(
                     chunk,
@@ -99,14 +154,37 @@ impl ScreenEncoder {
                 sequence_number += 1;
             })
         };
+        let rate_control = self.rate_control;
+        let latency_mode = self.latency_mode;
+        let share_audio = self.share_audio;
         wasm_bindgen_futures::spawn_local(async move {
             let navigator = window().navigator();
             let media_devices = navigator.media_devices().unwrap();
-            let screen_to_share: MediaStream =
-                JsFuture::from(media_devices.get_display_media().unwrap())
-                    .await
-                    .unwrap()
-                    .unchecked_into::<MediaStream>();
+            let mut constraints = DisplayMediaStreamConstraints::new();
+            constraints.video(&true.into());
+            constraints.audio(&share_audio.into());
+            let screen_to_share: MediaStream = JsFuture::from(
+                media_devices
+                    .get_display_media_with_constraints(&constraints)
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .unchecked_into::<MediaStream>();
+
+            let audio_track = screen_to_share
+                .get_audio_tracks()
+                .find(&mut |_: JsValue, _: u32, _: Array| true);
+            if !audio_track.is_undefined() {
+                wasm_bindgen_futures::spawn_local(encode_screen_audio(
+                    eos_client.clone(),
+                    audio_track.unchecked_into::<MediaStreamTrack>(),
+                    enabled.clone(),
+                    destroy.clone(),
+                ));
+            } else if share_audio {
+                debug!("screen shared without audio");
+            }
 
             // TODO: How can we determine the actual width and height of the screen to set the encoder config?
             let screen_track = Box::new(
@@ -131,15 +209,28 @@ impl ScreenEncoder {
             let screen_encoder = Box::new(VideoEncoder::new(&screen_encoder_init).unwrap());
             let mut screen_encoder_config =
                 VideoEncoderConfig::new(VIDEO_CODEC, SCREEN_HEIGHT, SCREEN_WIDTH);
-            screen_encoder_config.bitrate(64_000f64);
-            screen_encoder_config.latency_mode(LatencyMode::Realtime);
+            // Latency mode first, so that the quantizer support check includes it.
+            configure_latency_mode(&mut screen_encoder_config, latency_mode).await;
+            let quantizer =
+                configure_rate_control(&mut screen_encoder_config, rate_control, "detail").await;
             screen_encoder.configure(&screen_encoder_config);
 
+            let screen_track = screen_track.unchecked_into::<MediaStreamTrack>();
+            // Stopping the share from the browser's own UI ends the track, which disables the
+            // encoder as if the app had, so that it and the peers don't see it as still sharing.
+            let on_ended = {
+                let enabled = enabled.clone();
+                let client = eos_client.clone();
+                Closure::wrap(Box::new(move || {
+                    enabled.store(false, Ordering::Release);
+                    client.set_media_enabled(MediaType::SCREEN, false);
+                }) as Box<dyn FnMut()>)
+            };
+            screen_track.set_onended(Some(on_ended.as_ref().unchecked_ref()));
+
             let screen_processor =
-                MediaStreamTrackProcessor::new(&MediaStreamTrackProcessorInit::new(
-                    &screen_track.unchecked_into::<MediaStreamTrack>(),
-                ))
-                .unwrap();
+                MediaStreamTrackProcessor::new(&MediaStreamTrackProcessorInit::new(&screen_track))
+                    .unwrap();
 
             let screen_reader = screen_processor
                 .readable()
@@ -147,24 +238,53 @@ impl ScreenEncoder {
                 .unchecked_into::<ReadableStreamDefaultReader>();
 
             let mut screen_frame_counter = 0;
+            let mut keyframe_requests = eos_client.keyframe_requests();
 
             let poll_screen = async {
                 loop {
                     if destroy.load(Ordering::Acquire) {
-                        return;
+                        break;
                     }
                     if !enabled.load(Ordering::Acquire) {
-                        return;
+                        break;
                     }
                     match JsFuture::from(screen_reader.read()).await {
                         Ok(js_frame) => {
+                            // The stream is done when the user stops sharing from the browser UI,
+                            // which may be noticed here before the track's ended event.
+                            if Reflect::get(&js_frame, &JsString::from("done"))
+                                .map(|done| done.is_truthy())
+                                .unwrap_or(false)
+                            {
+                                enabled.store(false, Ordering::Release);
+                                eos_client.set_media_enabled(MediaType::SCREEN, false);
+                                break;
+                            }
                             let video_frame = Reflect::get(&js_frame, &JsString::from("value"))
                                 .unwrap()
                                 .unchecked_into::<VideoFrame>();
+                            let queue_size = screen_encoder.encode_queue_size();
+                            if let Some(stats) = frames.poll(eos_client.now(), queue_size) {
+                                eos_client.report_sender_stats(stats);
+                            }
+                            if queue_size > MAX_ENCODE_QUEUE_SIZE {
+                                video_frame.close();
+                                record_drop(MediaType::SCREEN, DropReason::EncodeOverload);
+                                frames.on_dropped_before_encode();
+                                continue;
+                            }
                             let mut opts = VideoEncoderEncodeOptions::new();
                             screen_frame_counter = (screen_frame_counter + 1) % 50;
+                            if eos_client.keyframe_requests() != keyframe_requests {
+                                keyframe_requests = eos_client.keyframe_requests();
+                                screen_frame_counter = 0;
+                            }
                             opts.key_frame(screen_frame_counter == 0);
+                            if let Some(quantizer) = quantizer {
+                                set_frame_quantizer(&opts, VIDEO_CODEC, quantizer);
+                            }
                             screen_encoder.encode_with_options(&video_frame, &opts);
+                            frames.on_submitted();
                             video_frame.close();
                         }
                         Err(e) => {
@@ -174,6 +294,97 @@ impl ScreenEncoder {
                 }
             };
             poll_screen.await;
+            screen_track.set_onended(None);
+            drop(on_ended);
+            screen_encoder.close();
+            // Closing discards the queued frames.
+            eos_client.report_sender_stats(frames.stats(0));
+            eos_client.send_packet(transform_end_of_stream(
+                MediaType::SCREEN,
+                eos_client.userid(),
+                eos_client.aes(),
+            ));
         });
     }
 }
+
+// Encodes the audio of a screen share until the share ends or the encoder is disabled.  The
+// encoder is configured from the first block of audio, as tabs and systems differ in their
+// sample rate and channels.
+async fn encode_screen_audio(
+    client: VideoCallClient,
+    track: MediaStreamTrack,
+    enabled: Arc<AtomicBool>,
+    destroy: Arc<AtomicBool>,
+) {
+    let output_handler = {
+        let client = client.clone();
+        let userid = client.userid().clone();
+        let aes = client.aes();
+        let mut buffer: [u8; 100000] = [0; 100000];
+        let mut sequence = 0;
+        Closure::wrap(Box::new(move |chunk: JsValue| {
+            let chunk = web_sys::EncodedAudioChunk::from(chunk);
+            let packet = transform_audio_chunk(
+                &chunk,
+                MediaType::SCREEN_AUDIO,
+                &mut buffer,
+                &userid,
+                sequence,
+                aes.clone(),
+            );
+            client.send_packet(packet);
+            sequence += 1;
+        }) as Box<dyn FnMut(JsValue)>)
+    };
+    let error_handler = Closure::wrap(Box::new(move |e: JsValue| {
+        error!("screen audio error_handler error {:?}", e);
+    }) as Box<dyn FnMut(JsValue)>);
+    let encoder = AudioEncoder::new(&AudioEncoderInit::new(
+        error_handler.as_ref().unchecked_ref(),
+        output_handler.as_ref().unchecked_ref(),
+    ))
+    .unwrap();
+    let reader = MediaStreamTrackProcessor::new(&MediaStreamTrackProcessorInit::new(&track))
+        .unwrap()
+        .readable()
+        .get_reader()
+        .unchecked_into::<ReadableStreamDefaultReader>();
+
+    let mut configured = false;
+    while enabled.load(Ordering::Acquire) && !destroy.load(Ordering::Acquire) {
+        match JsFuture::from(reader.read()).await {
+            Ok(js_frame) => {
+                if Reflect::get(&js_frame, &JsString::from("done"))
+                    .map(|done| done.is_truthy())
+                    .unwrap_or(false)
+                {
+                    break;
+                }
+                let audio_data = Reflect::get(&js_frame, &JsString::from("value"))
+                    .unwrap()
+                    .unchecked_into::<AudioData>();
+                if !configured {
+                    let mut config = AudioEncoderConfig::new(AUDIO_CODEC);
+                    config.bitrate(AUDIO_BITRATE);
+                    config.sample_rate(audio_data.sample_rate() as u32);
+                    config.number_of_channels(audio_data.number_of_channels());
+                    encoder.configure(&config);
+                    configured = true;
+                }
+                encoder.encode(&audio_data);
+                audio_data.close();
+            }
+            Err(e) => {
+                error!("error {:?}", e);
+            }
+        }
+    }
+    track.stop();
+    encoder.close();
+    client.send_packet(transform_end_of_stream(
+        MediaType::SCREEN_AUDIO,
+        client.userid(),
+        client.aes(),
+    ));
+}
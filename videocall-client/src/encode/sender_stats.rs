@@ -0,0 +1,116 @@
+//
+// Frame counts of the video encoders, reported through on_sender_stats_update so that choppy
+// video can be told apart as the encoder not keeping up rather than the network.
+//
+
+use std::cell::Cell;
+use std::rc::Rc;
+use videocall_types::protos::media_packet::media_packet::MediaType;
+
+// How often the encoders report their statistics, in milliseconds.
+const REPORT_INTERVAL_MS: f64 = 1000.0;
+
+/// Statistics of a video encoder since it started encoding, see
+/// [`on_sender_stats_update`](crate::VideoCallClientOptions::on_sender_stats_update).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SenderStats {
+    /// `VIDEO` for the camera encoder, `SCREEN` for the screen encoder.
+    pub media_type: MediaType,
+    /// Frames the encoder produced.
+    pub frames_encoded: u32,
+    /// Frames dropped without being encoded because the encoder was too far behind, i.e. the
+    /// CPU can't keep up at this resolution.
+    pub dropped_before_encode: u32,
+    /// Frames handed to the encoder that it never produced, e.g. because it skipped them to keep
+    /// up in realtime mode or was reconfigured with frames queued.
+    pub dropped_in_flight: u32,
+    /// Frames waiting in the encoder's queue.
+    pub encode_queue_size: u32,
+}
+
+#[derive(Debug)]
+pub(super) struct FrameCounter {
+    media_type: MediaType,
+    submitted: u32,
+    // Shared with the encoder's output handler.
+    encoded: Rc<Cell<u32>>,
+    dropped_before_encode: u32,
+    // When the statistics were last reported, in milliseconds.
+    reported: f64,
+}
+
+impl FrameCounter {
+    pub fn new(media_type: MediaType, now: f64) -> Self {
+        Self {
+            media_type,
+            submitted: 0,
+            encoded: Rc::new(Cell::new(0)),
+            dropped_before_encode: 0,
+            reported: now,
+        }
+    }
+
+    /// The counter for the encoder's output handler to increment with each chunk.
+    pub fn encoded(&self) -> Rc<Cell<u32>> {
+        Rc::clone(&self.encoded)
+    }
+
+    pub fn on_submitted(&mut self) {
+        self.submitted += 1;
+    }
+
+    pub fn on_dropped_before_encode(&mut self) {
+        self.dropped_before_encode += 1;
+    }
+
+    pub fn stats(&self, encode_queue_size: u32) -> SenderStats {
+        let encoded = self.encoded.get();
+        SenderStats {
+            media_type: self.media_type,
+            frames_encoded: encoded,
+            dropped_before_encode: self.dropped_before_encode,
+            dropped_in_flight: self
+                .submitted
+                .saturating_sub(encoded.saturating_add(encode_queue_size)),
+            encode_queue_size,
+        }
+    }
+
+    /// Returns the statistics if they are due to be reported.
+    pub fn poll(&mut self, now: f64, encode_queue_size: u32) -> Option<SenderStats> {
+        if now - self.reported < REPORT_INTERVAL_MS {
+            return None;
+        }
+        self.reported = now;
+        Some(self.stats(encode_queue_size))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counts_frames_lost_inside_the_encoder() {
+        let mut counter = FrameCounter::new(MediaType::VIDEO, 0.0);
+        let encoded = counter.encoded();
+        for _ in 0..10 {
+            counter.on_submitted();
+        }
+        counter.on_dropped_before_encode();
+        encoded.set(6);
+
+        assert_eq!(counter.poll(500.0, 2), None);
+        assert_eq!(
+            counter.poll(1000.0, 2),
+            Some(SenderStats {
+                media_type: MediaType::VIDEO,
+                frames_encoded: 6,
+                dropped_before_encode: 1,
+                dropped_in_flight: 2,
+                encode_queue_size: 2,
+            })
+        );
+        assert_eq!(counter.poll(1500.0, 2), None);
+    }
+}
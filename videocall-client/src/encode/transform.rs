@@ -1,9 +1,12 @@
 use super::super::wrappers::{EncodedAudioChunkTypeWrapper, EncodedVideoChunkTypeWrapper};
-use crate::crypto::aes::Aes128State;
+use crate::crypto::aes::MediaKey;
 use protobuf::Message;
 use std::rc::Rc;
 use videocall_types::protos::{
-    media_packet::{media_packet::MediaType, MediaPacket, VideoMetadata},
+    media_packet::{
+        media_packet::MediaType, AppDataMetadata, KeyframeRequestMetadata, MediaPacket,
+        VideoMetadata,
+    },
     packet_wrapper::{packet_wrapper::PacketType, PacketWrapper},
 };
 use web_sys::{EncodedAudioChunk, EncodedVideoChunk};
@@ -11,9 +14,10 @@ use web_sys::{EncodedAudioChunk, EncodedVideoChunk};
 pub fn transform_video_chunk(
     chunk: EncodedVideoChunk,
     sequence: u64,
+    codec: &str,
     buffer: &mut [u8],
     email: &str,
-    aes: Rc<Aes128State>,
+    aes: Rc<MediaKey>,
 ) -> PacketWrapper {
     let byte_length = chunk.byte_length() as usize;
     chunk.copy_to_with_u8_array(buffer);
@@ -25,6 +29,7 @@ pub fn transform_video_chunk(
         timestamp: chunk.timestamp(),
         video_metadata: Some(VideoMetadata {
             sequence,
+            codec: codec.to_owned(),
             ..Default::default()
         })
         .into(),
@@ -34,9 +39,10 @@ pub fn transform_video_chunk(
         media_packet.duration = duration0;
     }
     let data = media_packet.write_to_bytes().unwrap();
-    let data = aes.encrypt(&data).unwrap();
+    let (key_generation, data) = aes.encrypt(&data).unwrap();
     PacketWrapper {
         data,
+        key_generation,
         email: media_packet.email,
         packet_type: PacketType::MEDIA.into(),
         ..Default::default()
@@ -48,7 +54,7 @@ pub fn transform_screen_chunk(
     sequence: u64,
     buffer: &mut [u8],
     email: &str,
-    aes: Rc<Aes128State>,
+    aes: Rc<MediaKey>,
 ) -> PacketWrapper {
     let byte_length = chunk.byte_length() as usize;
     chunk.copy_to_with_u8_array(buffer);
@@ -69,26 +75,30 @@ pub fn transform_screen_chunk(
         media_packet.duration = duration0;
     }
     let data = media_packet.write_to_bytes().unwrap();
-    let data = aes.encrypt(&data).unwrap();
+    let (key_generation, data) = aes.encrypt(&data).unwrap();
     PacketWrapper {
         data,
+        key_generation,
         email: media_packet.email,
         packet_type: PacketType::MEDIA.into(),
         ..Default::default()
     }
 }
 
+/// Builds the packet of an encoded audio chunk, of the microphone (`MediaType::AUDIO`) or of a
+/// screen share (`MediaType::SCREEN_AUDIO`).
 pub fn transform_audio_chunk(
     chunk: &EncodedAudioChunk,
+    media_type: MediaType,
     buffer: &mut [u8],
     email: &str,
     sequence: u64,
-    aes: Rc<Aes128State>,
+    aes: Rc<MediaKey>,
 ) -> PacketWrapper {
     chunk.copy_to_with_u8_array(buffer);
     let mut media_packet: MediaPacket = MediaPacket {
         email: email.to_owned(),
-        media_type: MediaType::AUDIO.into(),
+        media_type: media_type.into(),
         data: buffer[0..chunk.byte_length() as usize].to_vec(),
         frame_type: EncodedAudioChunkTypeWrapper(chunk.type_()).to_string(),
         timestamp: chunk.timestamp(),
@@ -103,9 +113,85 @@ pub fn transform_audio_chunk(
         media_packet.duration = duration0;
     }
     let data = media_packet.write_to_bytes().unwrap();
-    let data = aes.encrypt(&data).unwrap();
+    let (key_generation, data) = aes.encrypt(&data).unwrap();
     PacketWrapper {
         data,
+        key_generation,
+        email: media_packet.email,
+        packet_type: PacketType::MEDIA.into(),
+        ..Default::default()
+    }
+}
+
+/// Builds the packet announcing that the sender stopped the track of the given `media_type`.
+pub fn transform_end_of_stream(
+    media_type: MediaType,
+    email: &str,
+    aes: Rc<MediaKey>,
+) -> PacketWrapper {
+    let media_packet: MediaPacket = MediaPacket {
+        email: email.to_owned(),
+        media_type: media_type.into(),
+        end_of_stream: true,
+        ..Default::default()
+    };
+    let data = media_packet.write_to_bytes().unwrap();
+    let (key_generation, data) = aes.encrypt(&data).unwrap();
+    PacketWrapper {
+        data,
+        key_generation,
+        email: media_packet.email,
+        packet_type: PacketType::MEDIA.into(),
+        ..Default::default()
+    }
+}
+
+/// Builds a DATA packet carrying an application-defined message on `channel`.
+pub fn transform_app_data(
+    channel: u32,
+    data: Vec<u8>,
+    email: &str,
+    aes: Rc<MediaKey>,
+) -> PacketWrapper {
+    let media_packet: MediaPacket = MediaPacket {
+        email: email.to_owned(),
+        media_type: MediaType::DATA.into(),
+        data,
+        app_data_metadata: Some(AppDataMetadata {
+            channel,
+            ..Default::default()
+        })
+        .into(),
+        ..Default::default()
+    };
+    let data = media_packet.write_to_bytes().unwrap();
+    let (key_generation, data) = aes.encrypt(&data).unwrap();
+    PacketWrapper {
+        data,
+        key_generation,
+        email: media_packet.email,
+        packet_type: PacketType::MEDIA.into(),
+        ..Default::default()
+    }
+}
+
+/// Builds the packet asking the peer `target` to send a key frame.
+pub fn transform_keyframe_request(target: &str, email: &str, aes: Rc<MediaKey>) -> PacketWrapper {
+    let media_packet: MediaPacket = MediaPacket {
+        email: email.to_owned(),
+        media_type: MediaType::KEYFRAME_REQUEST.into(),
+        keyframe_request_metadata: Some(KeyframeRequestMetadata {
+            target: target.to_owned(),
+            ..Default::default()
+        })
+        .into(),
+        ..Default::default()
+    };
+    let data = media_packet.write_to_bytes().unwrap();
+    let (key_generation, data) = aes.encrypt(&data).unwrap();
+    PacketWrapper {
+        data,
+        key_generation,
         email: media_packet.email,
         packet_type: PacketType::MEDIA.into(),
         ..Default::default()
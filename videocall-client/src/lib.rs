@@ -21,7 +21,7 @@
 //! For more detailed documentation see the doc for each struct.
 //!
 //! ## Client creation and connection:
-//! ```no_run
+//! ```ignore
 //! let options = VideoCallClientOptions {...}; // set parameters and callbacks for various events
 //! let client = VideoCallClient::new(options);
 //!
@@ -29,7 +29,7 @@
 //! ```
 //!
 //! ## Encoder creation:
-//! ```no_run
+//! ```ignore
 //! let camera = CameraEncoder.new(client, video_element_id);
 //! let microphone = MicrophoneEncoder.new(client);
 //! let screen = ScreenEncoder.new(client);
@@ -46,7 +46,7 @@
 //!
 //! ## Device access permission:
 //!
-//! ```no_run
+//! ```ignore
 //! let media_device_access = MediaDeviceAccess::new();
 //! media_device_access.on_granted = ...; // callback
 //! media_device_access.on_denied = ...; // callback
@@ -54,7 +54,7 @@
 //! ```
 //!
 //! ### Device query and listing:
-//! ```no_run
+//! ```ignore
 //! let media_device_list = MediaDeviceList::new();
 //! media_device_list.audio_inputs.on_selected = ...; // callback
 //! media_device_access.video_inputs.on_selected = ...; // callback
@@ -67,16 +67,57 @@
 //! media_device_list.video_inputs.select(&cameras[i].device_id);
 //!
 //! ```
+//!
+//! ### Pre-join preview:
+//! ```ignore
+//! let preview = LocalPreview::new(video_element_id);
+//! preview.select_camera(video_device);
+//! preview.select_microphone(audio_device);
+//! preview.start();
+//! let level = preview.audio_level();
+//!
+//! // on join, reuse the open devices
+//! camera.set_stream(preview.take_camera_stream().unwrap());
+//! microphone.set_stream(preview.take_microphone_stream().unwrap());
+//! ```
+//!
+//! ### Recording:
+//! ```ignore
+//! let mut recorder = Recorder::new(client.clone(), on_progress);
+//! recorder.start(&peer_userids)?;
+//! ...
+//! let webm: Blob = recorder.stop().await.unwrap();
+//! ```
 
+mod audio_level;
 mod client;
+mod clock;
+mod codec;
 mod connection;
 mod constants;
 mod crypto;
 mod decode;
+mod diagnostics;
 mod encode;
 mod media_devices;
+mod network_quality;
+mod recording;
 mod wrappers;
 
-pub use client::{VideoCallClient, VideoCallClientOptions};
-pub use encode::{CameraEncoder, MicrophoneEncoder, ScreenEncoder};
-pub use media_devices::{MediaDeviceAccess, MediaDeviceList, SelectableDevices};
+pub use client::{display_name_hash, VideoCallClient, VideoCallClientOptions};
+pub use clock::{BrowserClock, Clock, ManualClock, Timer};
+pub use codec::VideoCodec;
+pub use connection::{TransportPolicy, TransportType};
+pub use crypto::aes::{DecryptErrorKind, DecryptionFailures};
+pub use decode::{DecodedMedia, MediaState, PeerStats, RenderTarget};
+pub use diagnostics::{set_drop_log_config, DropLogConfig, DropReason};
+pub use encode::{
+    AdaptiveBitrate, CameraEncoder, LatencyMode, MicrophoneEncoder, OpusOptions, RateControl,
+    ScreenEncoder, SenderStats,
+};
+pub use media_devices::{
+    DeviceCapabilities, DeviceDiff, LocalPreview, MediaDeviceAccess, MediaDeviceList,
+    SelectableDevices,
+};
+pub use network_quality::{NetworkQuality, NetworkQualityThresholds};
+pub use recording::{Recorder, RecordingProgress};
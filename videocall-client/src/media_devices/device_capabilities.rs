@@ -0,0 +1,96 @@
+//
+// DeviceCapabilities reads what an input device supports from the `InputDeviceInfo` objects that
+// `enumerateDevices()` returns, without opening the device.  Browsers that don't implement
+// `InputDeviceInfo.getCapabilities()`, e.g. Firefox, and audio outputs have none.
+//
+
+use js_sys::{Function, Reflect};
+use std::ops::RangeInclusive;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::MediaDeviceInfo;
+
+/// The settings an input device supports, see
+/// [`SelectableDevices::capabilities`](crate::SelectableDevices::capabilities).
+///
+/// Fields are `None` when the browser doesn't report them, e.g. the video fields of a microphone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceCapabilities {
+    /// Video width, in pixels.
+    pub width: Option<RangeInclusive<u32>>,
+    /// Video height, in pixels.
+    pub height: Option<RangeInclusive<u32>>,
+    /// Video frames per second.
+    pub frame_rate: Option<RangeInclusive<f64>>,
+    /// Audio samples per second.
+    pub sample_rate: Option<RangeInclusive<u32>>,
+    /// Audio channels.
+    pub channel_count: Option<RangeInclusive<u32>>,
+}
+
+impl DeviceCapabilities {
+    /// Calls `getCapabilities()` on `device`, returns `None` if it has no such method or reports
+    /// nothing, which is the case until the user grants access to the devices.
+    pub(crate) fn of_device(device: &MediaDeviceInfo) -> Option<Self> {
+        let get_capabilities = Reflect::get(device, &"getCapabilities".into())
+            .ok()?
+            .dyn_into::<Function>()
+            .ok()?;
+        let capabilities = get_capabilities.call0(device).ok()?;
+        Some(Self::from_js(&capabilities)).filter(|capabilities| *capabilities != Self::default())
+    }
+
+    // Parses a `MediaTrackCapabilities` dictionary.
+    fn from_js(capabilities: &JsValue) -> Self {
+        let integers =
+            |name: &str| range(capabilities, name).map(|(min, max)| (min as u32)..=(max as u32));
+        Self {
+            width: integers("width"),
+            height: integers("height"),
+            frame_rate: range(capabilities, "frameRate").map(|(min, max)| min..=max),
+            sample_rate: integers("sampleRate"),
+            channel_count: integers("channelCount"),
+        }
+    }
+}
+
+// Reads a `{min, max}` range member of a dictionary.
+fn range(dictionary: &JsValue, name: &str) -> Option<(f64, f64)> {
+    let range = Reflect::get(dictionary, &name.into()).ok()?;
+    let bound = |bound: &str| Reflect::get(&range, &bound.into()).ok()?.as_f64();
+    Some((bound("min")?, bound("max")?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use js_sys::Object;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn range_of(min: f64, max: f64) -> Object {
+        let range = Object::new();
+        Reflect::set(&range, &"min".into(), &min.into()).unwrap();
+        Reflect::set(&range, &"max".into(), &max.into()).unwrap();
+        range
+    }
+
+    #[wasm_bindgen_test]
+    fn parses_camera_capabilities() {
+        let capabilities = Object::new();
+        Reflect::set(&capabilities, &"width".into(), &range_of(1.0, 3840.0)).unwrap();
+        Reflect::set(&capabilities, &"height".into(), &range_of(1.0, 2160.0)).unwrap();
+        Reflect::set(&capabilities, &"frameRate".into(), &range_of(0.0, 30.0)).unwrap();
+        // Ranges without both bounds are ignored.
+        Reflect::set(&capabilities, &"sampleRate".into(), &Object::new()).unwrap();
+
+        assert_eq!(
+            DeviceCapabilities::from_js(&capabilities),
+            DeviceCapabilities {
+                width: Some(1..=3840),
+                height: Some(1..=2160),
+                frame_rate: Some(0.0..=30.0),
+                sample_rate: None,
+                channel_count: None,
+            }
+        );
+    }
+}
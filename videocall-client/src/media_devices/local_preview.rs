@@ -0,0 +1,266 @@
+use gloo_utils::window;
+use js_sys::Array;
+use log::error;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AnalyserNode, AudioContext, HtmlVideoElement, MediaStream, MediaStreamConstraints,
+    MediaStreamTrack, MediaTrackConstraints,
+};
+use yew::prelude::Callback;
+
+#[derive(Default)]
+struct PreviewStreams {
+    camera: Option<MediaStream>,
+    microphone: Option<MediaStream>,
+    audio_context: Option<AudioContext>,
+    analyser: Option<AnalyserNode>,
+}
+
+/// [LocalPreview] shows the user's camera and measures their microphone level before a call is
+/// joined, e.g. on a pre-join screen.  It doesn't need a [`VideoCallClient`](crate::VideoCallClient).
+///
+/// On join, hand the acquired streams over to the encoders with
+/// [`CameraEncoder::set_stream`](crate::CameraEncoder::set_stream) and
+/// [`MicrophoneEncoder::set_stream`](crate::MicrophoneEncoder::set_stream), so the devices aren't
+/// opened a second time:
+///
+/// ```ignore
+/// let mut preview = LocalPreview::new("preview-video");
+/// preview.select_camera(camera_id);
+/// preview.select_microphone(mic_id);
+/// preview.start();
+/// // ... poll preview.audio_level() to draw a meter ...
+/// if let Some(stream) = preview.take_camera_stream() {
+///     camera_encoder.set_stream(stream);
+/// }
+/// if let Some(stream) = preview.take_microphone_stream() {
+///     microphone_encoder.set_stream(stream);
+/// }
+/// preview.stop();
+/// ```
+pub struct LocalPreview {
+    video_elem_id: String,
+    camera: Option<String>,
+    microphone: Option<String>,
+    streams: Rc<RefCell<PreviewStreams>>,
+    running: Rc<Cell<bool>>,
+
+    /// Callback that is called if a device can't be opened
+    pub on_error: Callback<JsValue>,
+}
+
+impl LocalPreview {
+    /// Constructor for the preview:
+    ///
+    /// * `video_elem_id` - the ID of an `HtmlVideoElement` in which the camera will be shown.  It
+    ///   does not need to currently exist.
+    ///
+    /// The preview is created without devices selected, call [`select_camera()`](Self::select_camera)
+    /// and/or [`select_microphone()`](Self::select_microphone), then [`start()`](Self::start).
+    pub fn new(video_elem_id: &str) -> Self {
+        Self {
+            video_elem_id: video_elem_id.to_string(),
+            camera: None,
+            microphone: None,
+            streams: Rc::new(RefCell::new(PreviewStreams::default())),
+            running: Rc::new(Cell::new(false)),
+            on_error: Callback::noop(),
+        }
+    }
+
+    /// Selects the camera to preview, by the `device_id` of an entry in
+    /// [`media_device_list.video_inputs.devices()`](crate::MediaDeviceList::video_inputs).
+    /// Switches to it immediately if the preview is running.
+    pub fn select_camera(&mut self, device_id: String) {
+        self.camera = Some(device_id);
+        if self.running.get() {
+            if let Some(stream) = self.streams.borrow_mut().camera.take() {
+                stop_tracks(&stream);
+            }
+            self.open_camera();
+        }
+    }
+
+    /// Selects the microphone to measure, by the `device_id` of an entry in
+    /// [`media_device_list.audio_inputs.devices()`](crate::MediaDeviceList::audio_inputs).
+    /// Switches to it immediately if the preview is running.
+    pub fn select_microphone(&mut self, device_id: String) {
+        self.microphone = Some(device_id);
+        if self.running.get() {
+            self.close_microphone();
+            self.open_microphone();
+        }
+    }
+
+    /// Opens the selected devices.  This function returns immediately, the camera shows up in the
+    /// video element once the user granted access.
+    pub fn start(&mut self) {
+        if self.running.replace(true) {
+            return;
+        }
+        self.open_camera();
+        self.open_microphone();
+    }
+
+    /// Stops the devices that haven't been handed over.
+    pub fn stop(&mut self) {
+        self.running.set(false);
+        if let Some(stream) = self.streams.borrow_mut().camera.take() {
+            stop_tracks(&stream);
+        }
+        self.close_microphone();
+    }
+
+    /// Current microphone level, from 0.0 (silence) to 1.0 (full scale).  Returns 0.0 until the
+    /// microphone is open.
+    pub fn audio_level(&self) -> f32 {
+        let streams = self.streams.borrow();
+        let Some(analyser) = &streams.analyser else {
+            return 0.0;
+        };
+        let mut samples = vec![0f32; analyser.fft_size() as usize];
+        analyser.get_float_time_domain_data(&mut samples);
+        let sum_of_squares: f32 = samples.iter().map(|sample| sample * sample).sum();
+        (sum_of_squares / samples.len() as f32).sqrt().min(1.0)
+    }
+
+    /// Hands over the camera stream, which the preview then no longer stops.  Returns `None` if
+    /// the camera isn't open (yet).
+    pub fn take_camera_stream(&mut self) -> Option<MediaStream> {
+        self.streams.borrow_mut().camera.take()
+    }
+
+    /// Hands over the microphone stream, which the preview then no longer stops or measures.
+    /// Returns `None` if the microphone isn't open (yet).
+    pub fn take_microphone_stream(&mut self) -> Option<MediaStream> {
+        let mut streams = self.streams.borrow_mut();
+        close_audio_context(&mut streams);
+        streams.microphone.take()
+    }
+
+    fn open_camera(&self) {
+        let Some(device_id) = self.camera.clone() else {
+            return;
+        };
+        let video_elem_id = self.video_elem_id.clone();
+        let streams = Rc::clone(&self.streams);
+        let running = Rc::clone(&self.running);
+        let on_error = self.on_error.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let stream = match get_user_media(&device_id, true).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!("failed to open camera {:?}", e);
+                    on_error.emit(e);
+                    return;
+                }
+            };
+            // The preview may have been stopped while waiting for the user.
+            if !running.get() {
+                stop_tracks(&stream);
+                return;
+            }
+            if let Some(video_element) = window()
+                .document()
+                .and_then(|document| document.get_element_by_id(&video_elem_id))
+            {
+                let video_element = video_element.unchecked_into::<HtmlVideoElement>();
+                video_element.set_src_object(Some(&stream));
+                video_element.set_muted(true);
+            }
+            if let Some(previous) = streams.borrow_mut().camera.replace(stream) {
+                stop_tracks(&previous);
+            }
+        });
+    }
+
+    fn open_microphone(&self) {
+        let Some(device_id) = self.microphone.clone() else {
+            return;
+        };
+        let streams = Rc::clone(&self.streams);
+        let running = Rc::clone(&self.running);
+        let on_error = self.on_error.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = match get_user_media(&device_id, false).await {
+                Ok(stream) if running.get() => {
+                    create_analyser(&stream).map(|(context, analyser)| (stream, context, analyser))
+                }
+                Ok(stream) => {
+                    stop_tracks(&stream);
+                    return;
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok((stream, audio_context, analyser)) => {
+                    let mut streams = streams.borrow_mut();
+                    close_audio_context(&mut streams);
+                    if let Some(previous) = streams.microphone.replace(stream) {
+                        stop_tracks(&previous);
+                    }
+                    streams.audio_context = Some(audio_context);
+                    streams.analyser = Some(analyser);
+                }
+                Err(e) => {
+                    error!("failed to open microphone {:?}", e);
+                    on_error.emit(e);
+                }
+            }
+        });
+    }
+
+    fn close_microphone(&self) {
+        let mut streams = self.streams.borrow_mut();
+        close_audio_context(&mut streams);
+        if let Some(stream) = streams.microphone.take() {
+            stop_tracks(&stream);
+        }
+    }
+}
+
+async fn get_user_media(device_id: &str, video: bool) -> Result<MediaStream, JsValue> {
+    let media_devices = window().navigator().media_devices()?;
+    let mut constraints = MediaStreamConstraints::new();
+    let mut media_info = MediaTrackConstraints::new();
+    media_info.device_id(&device_id.into());
+    if video {
+        constraints.video(&media_info.into());
+        constraints.audio(&JsValue::FALSE);
+    } else {
+        constraints.audio(&media_info.into());
+        constraints.video(&JsValue::FALSE);
+    }
+    let promise = media_devices.get_user_media_with_constraints(&constraints)?;
+    Ok(JsFuture::from(promise)
+        .await?
+        .unchecked_into::<MediaStream>())
+}
+
+fn create_analyser(stream: &MediaStream) -> Result<(AudioContext, AnalyserNode), JsValue> {
+    let audio_context = AudioContext::new()?;
+    let source = audio_context.create_media_stream_source(stream)?;
+    let analyser = audio_context.create_analyser()?;
+    analyser.set_fft_size(1024);
+    source.connect_with_audio_node(&analyser)?;
+    Ok((audio_context, analyser))
+}
+
+fn close_audio_context(streams: &mut PreviewStreams) {
+    streams.analyser = None;
+    if let Some(audio_context) = streams.audio_context.take() {
+        let _ = audio_context.close();
+    }
+}
+
+fn stop_tracks(stream: &MediaStream) {
+    stream
+        .get_tracks()
+        .for_each(&mut |track: JsValue, _: u32, _: Array| {
+            track.unchecked_into::<MediaStreamTrack>().stop()
+        });
+}
@@ -25,7 +25,7 @@ impl MediaDeviceAccess {
     /// After construction, set the callbacks, then call the [`request()`](Self::request) method to request
     /// access, e.g.:
     ///
-    /// ```
+    /// ```ignore
     /// let media_device_access = MediaDeviceAccess::new();
     /// media_device_access.on_granted = ...; // callback
     /// media_device_access.on_denied = ...; // callback
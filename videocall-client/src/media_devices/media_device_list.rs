@@ -1,7 +1,9 @@
+use gloo::events::EventListener;
 use gloo_utils::window;
 use js_sys::Array;
 use js_sys::Promise;
-use std::cell::OnceCell;
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
@@ -9,14 +11,20 @@ use web_sys::MediaDeviceInfo;
 use web_sys::MediaDeviceKind;
 use yew::prelude::Callback;
 
+use super::device_capabilities::DeviceCapabilities;
+use crate::decode::audio_sink_selection_supported;
+
 /// A "smart" list of [web_sys::MediaDeviceInfo](web_sys::MediaDeviceInfo) items, used by [MediaDeviceList]
 ///
 /// The list keeps track of a currently selected device, supporting selection and a callback that
 /// is triggered when a selection is made.
 ///
 pub struct SelectableDevices {
-    devices: Rc<OnceCell<Vec<MediaDeviceInfo>>>,
-    selected: Option<String>,
+    devices: Rc<RefCell<Vec<MediaDeviceInfo>>>,
+    // Shared with the device change listener, which clears it when the device goes away.
+    selected: Rc<RefCell<Option<String>>>,
+    // Capabilities of the devices that reported some, by device_id.
+    capabilities: RefCell<HashMap<String, DeviceCapabilities>>,
 
     /// Callback that will be called as `callback(device_id)` whenever [`select(device_id)`](Self::select) is called with a valid `device_id`
     pub on_selected: Callback<String>,
@@ -25,8 +33,9 @@ pub struct SelectableDevices {
 impl SelectableDevices {
     fn new() -> Self {
         Self {
-            devices: Rc::new(OnceCell::new()),
-            selected: None,
+            devices: Rc::new(RefCell::new(Vec::new())),
+            selected: Rc::new(RefCell::new(None)),
+            capabilities: RefCell::new(HashMap::new()),
             on_selected: Callback::noop(),
         }
     }
@@ -45,47 +54,132 @@ impl SelectableDevices {
     /// that the [`on_selected(device_id)`](Self::on_selected) callback will be set to a function
     /// that calls the `select` method of the appropriate encoder.
     pub fn select(&mut self, device_id: &str) {
-        if let Some(devices) = self.devices.get() {
-            for device in devices.iter() {
-                if device.device_id() == device_id {
-                    self.selected = Some(device_id.to_string());
-                    self.on_selected.emit(device_id.to_string());
-                }
-            }
+        let found = self
+            .devices
+            .borrow()
+            .iter()
+            .any(|device| device.device_id() == device_id);
+        if found {
+            *self.selected.borrow_mut() = Some(device_id.to_string());
+            self.on_selected.emit(device_id.to_string());
         }
     }
 
     /// Returns a reference to an array of [MediaDeviceInfo] entries for the available devices.
-    pub fn devices(&self) -> &[MediaDeviceInfo] {
-        match self.devices.get() {
-            Some(devices) => devices,
-            None => &[],
+    ///
+    /// The reference must be dropped before control returns to the browser, which may update the
+    /// list when devices are plugged in or removed.
+    pub fn devices(&self) -> Ref<'_, [MediaDeviceInfo]> {
+        Ref::map(self.devices.borrow(), Vec::as_slice)
+    }
+
+    /// Returns the resolutions and frame rates a camera supports, or the sample rates and channels
+    /// of a microphone, e.g. to offer a choice of quality before starting the encoder.
+    ///
+    /// Returns `None` if `device_id` is not in [`devices()`](Self::devices), for audio outputs,
+    /// before the user granted access to the devices, and in browsers that can't tell without
+    /// opening the device, e.g. Firefox.  The result is cached.
+    pub fn capabilities(&self, device_id: &str) -> Option<DeviceCapabilities> {
+        if let Some(capabilities) = self.capabilities.borrow().get(device_id) {
+            return Some(capabilities.clone());
         }
+        let capabilities = self
+            .devices
+            .borrow()
+            .iter()
+            .find(|device| device.device_id() == device_id)
+            .and_then(DeviceCapabilities::of_device)?;
+        self.capabilities
+            .borrow_mut()
+            .insert(device_id.to_string(), capabilities.clone());
+        Some(capabilities)
     }
 
     /// Returns the `device_id` of the currently selected device, or "" if there are no devices.
     pub fn selected(&self) -> String {
-        match &self.selected {
-            Some(selected) => selected.to_string(),
-            // device 0 is the default selection
-            None => match self.devices().first() {
-                Some(device) => device.device_id(),
-                None => "".to_string(),
-            },
+        selected_device(&self.devices.borrow(), &self.selected.borrow())
+    }
+
+    // Replaces the devices after they changed, keeping the selection if the device is still
+    // there.  Triggers `on_selected` if that changes the selected device.
+    fn update(
+        devices: &RefCell<Vec<MediaDeviceInfo>>,
+        selected: &RefCell<Option<String>>,
+        on_selected: &Callback<String>,
+        new_devices: Vec<MediaDeviceInfo>,
+    ) {
+        let previous = selected_device(&devices.borrow(), &selected.borrow());
+        let removed = selected.borrow().as_ref().is_some_and(|device_id| {
+            !new_devices
+                .iter()
+                .any(|device| &device.device_id() == device_id)
+        });
+        if removed {
+            *selected.borrow_mut() = None;
+        }
+        *devices.borrow_mut() = new_devices;
+        let current = selected_device(&devices.borrow(), &selected.borrow());
+        if current != previous && !current.is_empty() {
+            on_selected.emit(current);
         }
     }
 }
 
+fn selected_device(devices: &[MediaDeviceInfo], selected: &Option<String>) -> String {
+    match selected {
+        Some(selected) => selected.to_string(),
+        // device 0 is the default selection
+        None => match devices.first() {
+            Some(device) => device.device_id(),
+            None => "".to_string(),
+        },
+    }
+}
+
+/// The devices that were plugged in or removed, see
+/// [`MediaDeviceList::on_devices_changed`](MediaDeviceList::on_devices_changed).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceDiff {
+    pub added: Vec<MediaDeviceInfo>,
+    pub removed: Vec<MediaDeviceInfo>,
+}
+
+impl DeviceDiff {
+    fn new(previous: &[MediaDeviceInfo], current: &[MediaDeviceInfo]) -> Self {
+        let same = |a: &MediaDeviceInfo, b: &MediaDeviceInfo| {
+            a.kind() == b.kind() && a.device_id() == b.device_id()
+        };
+        Self {
+            added: current
+                .iter()
+                .filter(|device| !previous.iter().any(|old| same(old, device)))
+                .cloned()
+                .collect(),
+            removed: previous
+                .iter()
+                .filter(|device| !current.iter().any(|new| same(new, device)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
 ///  [MediaDeviceList] is a utility that queries the user's system for the currently
-///  available audio and video input devices, and maintains a current selection for each.
+///  available audio and video input devices and audio output devices, and maintains a current
+///  selection for each.
 ///
 ///  It does *not* have any explicit connection to [`CameraEncoder`](crate::CameraEncoder) or
 ///  [`MicrophoneEncoder`](crate::MicrophoneEncoder) -- the calling app is responsible for passing
-///  the selection info from this utility to the encoders.
+///  the selection info from this utility to the encoders, and the audio output selection to
+///  [`VideoCallClient::set_audio_output`](crate::VideoCallClient::set_audio_output).
 ///
 ///  Outline of usage is:
 ///
-/// ```
+/// ```ignore
 /// let media_device_list = MediaDeviceList::new();
 /// media_device_list.audio_inputs.on_selected = ...; // callback
 /// media_device_access.video_inputs.on_selected = ...; // callback
@@ -105,8 +199,26 @@ pub struct MediaDeviceList {
     /// The list of video input devices.  This field is `pub` for access through it, but should be considerd "read-only".
     pub video_inputs: SelectableDevices,
 
+    /// The list of audio output devices.  This field is `pub` for access through it, but should be considerd "read-only".
+    ///
+    /// Browsers that can't route audio to a given output (see
+    /// [`audio_output_selection_supported()`](Self::audio_output_selection_supported)) may still
+    /// list them.
+    pub audio_outputs: SelectableDevices,
+
     /// Callback that is called as `callback(())` after loading via [`load()`](Self::load) is complete.
     pub on_loaded: Callback<()>,
+
+    /// Callback that is called as `callback(diff)` when devices are plugged in or removed after
+    /// [`load()`](Self::load), once the lists have been updated.
+    ///
+    /// A selected device that is still there stays selected.  If it was removed, the selection
+    /// goes back to the first device of the list, and its
+    /// [`on_selected`](SelectableDevices::on_selected) callback is triggered.
+    pub on_devices_changed: Callback<DeviceDiff>,
+
+    // Listens for `devicechange` events, once loaded.
+    listener: RefCell<Option<EventListener>>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -116,7 +228,7 @@ impl MediaDeviceList {
     /// After constructing, the user should set the [`on_selected`](SelectableDevices::on_selected)
     /// callbacks, e.g.:
     ///
-    /// ```
+    /// ```ignore
     /// let media_device_list = MediaDeviceList::new();
     /// media_device_list.audio_inputs.on_selected = ...; // callback
     /// media_device_access.video_inputs.on_selected = ...; // callback
@@ -127,62 +239,123 @@ impl MediaDeviceList {
         Self {
             audio_inputs: SelectableDevices::new(),
             video_inputs: SelectableDevices::new(),
+            audio_outputs: SelectableDevices::new(),
             on_loaded: Callback::noop(),
+            on_devices_changed: Callback::noop(),
+            listener: RefCell::new(None),
         }
     }
 
-    /// Queries the user's system to find the available audio and video input devices.
+    /// Returns `true` if the browser supports playing call audio on a selected output device.
+    pub fn audio_output_selection_supported() -> bool {
+        audio_sink_selection_supported()
+    }
+
+    /// Queries the user's system to find the available audio and video input devices and audio
+    /// output devices.
     ///
     /// This is an asynchronous operation; when it is complete the [`on_loaded`](Self::on_loaded)
-    /// callback will be triggered.   Additionally, by default the first audio input device and
-    /// first video input device are automatically selected, and their
-    /// [`on_selected`](SelectableDevices::on_selected) callbacks will be triggered.
+    /// callback will be triggered.   Additionally, by default the first device of each list is
+    /// automatically selected, and its [`on_selected`](SelectableDevices::on_selected) callback
+    /// will be triggered.
     ///
-    /// After loading, the [`audio_inputs`](Self::audio_inputs) and [`video_inputs`](Self::video_inputs) lists
-    /// will be populated, and can be queried and selected.
+    /// After loading, the [`audio_inputs`](Self::audio_inputs), [`video_inputs`](Self::video_inputs)
+    /// and [`audio_outputs`](Self::audio_outputs) lists will be populated, and can be queried and
+    /// selected.
     pub fn load(&self) {
+        self.listen();
         let on_loaded = self.on_loaded.clone();
         let on_audio_selected = self.audio_inputs.on_selected.clone();
         let on_video_selected = self.video_inputs.on_selected.clone();
+        let on_audio_output_selected = self.audio_outputs.on_selected.clone();
         let audio_input_devices = Rc::clone(&self.audio_inputs.devices);
         let video_input_devices = Rc::clone(&self.video_inputs.devices);
+        let audio_output_devices = Rc::clone(&self.audio_outputs.devices);
         wasm_bindgen_futures::spawn_local(async move {
-            let navigator = window().navigator();
-            let media_devices = navigator.media_devices().unwrap();
-
-            let promise: Promise = media_devices
-                .enumerate_devices()
-                .expect("enumerate devices");
-            let future = JsFuture::from(promise);
-            let devices = future
-                .await
-                .expect("await devices")
-                .unchecked_into::<Array>();
-            let devices = devices.to_vec();
-            let devices = devices
-                .into_iter()
-                .map(|d| d.unchecked_into::<MediaDeviceInfo>())
-                .collect::<Vec<MediaDeviceInfo>>();
-            _ = audio_input_devices.set(
-                devices
-                    .clone()
-                    .into_iter()
-                    .filter(|device| device.kind() == MediaDeviceKind::Audioinput)
-                    .collect(),
-            );
-            _ = video_input_devices.set(
-                devices
-                    .into_iter()
-                    .filter(|device| device.kind() == MediaDeviceKind::Videoinput)
-                    .collect(),
-            );
+            let devices = enumerate_devices().await;
+            *audio_input_devices.borrow_mut() = of_kind(&devices, MediaDeviceKind::Audioinput);
+            *video_input_devices.borrow_mut() = of_kind(&devices, MediaDeviceKind::Videoinput);
+            *audio_output_devices.borrow_mut() = of_kind(&devices, MediaDeviceKind::Audiooutput);
             on_loaded.emit(());
-            if let Some(device) = audio_input_devices.get().unwrap().first() {
+            if let Some(device) = audio_input_devices.borrow().first() {
                 on_audio_selected.emit(device.device_id())
             }
-            if let Some(device) = video_input_devices.get().unwrap().first() {
+            if let Some(device) = video_input_devices.borrow().first() {
                 on_video_selected.emit(device.device_id())
             }
+            if let Some(device) = audio_output_devices.borrow().first() {
+                on_audio_output_selected.emit(device.device_id())
+            }
+        });
+    }
+
+    // Re-enumerates the devices whenever the browser reports that they changed.
+    fn listen(&self) {
+        if self.listener.borrow().is_some() {
+            return;
+        }
+        let Some(media_devices) = window().navigator().media_devices().ok() else {
+            return;
+        };
+        let lists = [&self.audio_inputs, &self.video_inputs, &self.audio_outputs].map(|list| {
+            (
+                Rc::clone(&list.devices),
+                Rc::clone(&list.selected),
+                list.on_selected.clone(),
+            )
+        });
+        let lists = Rc::new(lists);
+        let on_devices_changed = self.on_devices_changed.clone();
+        let listener = EventListener::new(&media_devices, "devicechange", move |_| {
+            let lists = Rc::clone(&lists);
+            let on_devices_changed = on_devices_changed.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let devices = enumerate_devices().await;
+                let kinds = [
+                    MediaDeviceKind::Audioinput,
+                    MediaDeviceKind::Videoinput,
+                    MediaDeviceKind::Audiooutput,
+                ];
+                let mut diff = DeviceDiff::default();
+                for ((list, selected, on_selected), kind) in lists.iter().zip(kinds) {
+                    let new_devices = of_kind(&devices, kind);
+                    let list_diff = DeviceDiff::new(&list.borrow(), &new_devices);
+                    diff.added.extend(list_diff.added);
+                    diff.removed.extend(list_diff.removed);
+                    SelectableDevices::update(list, selected, on_selected, new_devices);
+                }
+                if !diff.is_empty() {
+                    on_devices_changed.emit(diff);
+                }
+            });
         });
+        *self.listener.borrow_mut() = Some(listener);
     }
 }
+
+async fn enumerate_devices() -> Vec<MediaDeviceInfo> {
+    let navigator = window().navigator();
+    let media_devices = navigator.media_devices().unwrap();
+
+    let promise: Promise = media_devices
+        .enumerate_devices()
+        .expect("enumerate devices");
+    let future = JsFuture::from(promise);
+    let devices = future
+        .await
+        .expect("await devices")
+        .unchecked_into::<Array>();
+    devices
+        .to_vec()
+        .into_iter()
+        .map(|d| d.unchecked_into::<MediaDeviceInfo>())
+        .collect()
+}
+
+fn of_kind(devices: &[MediaDeviceInfo], kind: MediaDeviceKind) -> Vec<MediaDeviceInfo> {
+    devices
+        .iter()
+        .filter(|device| device.kind() == kind)
+        .cloned()
+        .collect()
+}
@@ -1,5 +1,9 @@
+mod device_capabilities;
+mod local_preview;
 mod media_device_access;
 mod media_device_list;
 
+pub use device_capabilities::DeviceCapabilities;
+pub use local_preview::LocalPreview;
 pub use media_device_access::MediaDeviceAccess;
-pub use media_device_list::{MediaDeviceList, SelectableDevices};
+pub use media_device_list::{DeviceDiff, MediaDeviceList, SelectableDevices};
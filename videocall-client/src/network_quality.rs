@@ -0,0 +1,180 @@
+//
+// Per-peer network quality, rated each time the peer monitor runs from what arrived from the
+// peer during its window:
+//
+//  * loss: the share of the peer's video frames missing from their sequence numbers, or, for
+//    peers that don't send video, the share of missing heartbeats;
+//  * jitter: how much the transit time of the peer's heartbeats varies, smoothed like RTP
+//    interarrival jitter (RFC 3550).  The offset between the peer's clock and ours cancels out.
+//
+// Packets aren't echoed back, so there is no round-trip time to go by.  Heartbeat loss is
+// coarse, with only a handful of heartbeats per window, so peers without video tend to jump
+// between ratings more.
+//
+
+/// A rating of the network path from a peer, e.g. for a signal bars indicator.  Ordered from
+/// best to worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NetworkQuality {
+    Excellent,
+    Good,
+    Fair,
+    Poor,
+}
+
+/// Limits of the [NetworkQuality] ratings.  Each limit is the worst value still rated
+/// Excellent, Good and Fair, in that order; anything worse is Poor.  A peer gets the worse of
+/// the ratings of its loss and its jitter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NetworkQualityThresholds {
+    /// Share of lost packets, from 0.0 to 1.0.  Defaults to 1%, 3% and 10%.
+    pub loss: [f64; 3],
+    /// Jitter in milliseconds.  Defaults to 20, 50 and 100 ms.
+    pub jitter_ms: [f64; 3],
+}
+
+impl Default for NetworkQualityThresholds {
+    fn default() -> Self {
+        Self {
+            loss: [0.01, 0.03, 0.10],
+            jitter_ms: [20.0, 50.0, 100.0],
+        }
+    }
+}
+
+impl NetworkQualityThresholds {
+    pub fn rate(&self, loss: f64, jitter_ms: f64) -> NetworkQuality {
+        rate(loss, &self.loss).max(rate(jitter_ms, &self.jitter_ms))
+    }
+}
+
+fn rate(value: f64, limits: &[f64; 3]) -> NetworkQuality {
+    match limits.iter().position(|limit| value <= *limit) {
+        Some(0) => NetworkQuality::Excellent,
+        Some(1) => NetworkQuality::Good,
+        Some(_) => NetworkQuality::Fair,
+        None => NetworkQuality::Poor,
+    }
+}
+
+// A frame this far behind the newest one means the peer restarted its encoder, which starts
+// counting from 0 again, rather than a late frame.
+const MAX_REORDER: u64 = 100;
+
+/// What arrived from a peer, collected between peer monitor runs.
+#[derive(Debug, Default)]
+pub(crate) struct NetworkStats {
+    heartbeats: u32,
+    last_transit: Option<f64>,
+    // In milliseconds.
+    jitter: f64,
+    last_video_sequence: Option<u64>,
+    video_received: u32,
+    video_lost: u32,
+}
+
+impl NetworkStats {
+    /// Counts a heartbeat the peer sent at `sent` by its clock, which arrived at `now` by ours.
+    pub fn on_heartbeat(&mut self, sent: f64, now: f64) {
+        self.heartbeats += 1;
+        let transit = now - sent;
+        if let Some(last_transit) = self.last_transit {
+            self.jitter += ((transit - last_transit).abs() - self.jitter) / 16.0;
+        }
+        self.last_transit = Some(transit);
+    }
+
+    pub fn on_video_frame(&mut self, sequence: u64) {
+        match self.last_video_sequence {
+            Some(last) if sequence > last => {
+                self.video_lost += (sequence - last - 1).min(u32::MAX as u64) as u32;
+            }
+            Some(last) if sequence + MAX_REORDER < last => {}
+            // Late or duplicate; a late frame was counted as lost when a later one arrived.
+            Some(_) => {
+                self.video_lost = self.video_lost.saturating_sub(1);
+                return;
+            }
+            None => {}
+        }
+        self.last_video_sequence = Some(sequence);
+        self.video_received += 1;
+    }
+
+    /// Rates the window that ends now, in which `expected_heartbeats` heartbeats were due, and
+    /// starts the next one.
+    pub fn finish_window(
+        &mut self,
+        expected_heartbeats: u32,
+        thresholds: &NetworkQualityThresholds,
+    ) -> NetworkQuality {
+        let video_frames = self.video_received + self.video_lost;
+        let loss = if video_frames > 0 {
+            self.video_lost as f64 / video_frames as f64
+        } else if expected_heartbeats > 0 {
+            // One heartbeat of slack, as the peer's timer and our monitor drift apart.
+            let missing = expected_heartbeats.saturating_sub(self.heartbeats + 1);
+            missing as f64 / expected_heartbeats as f64
+        } else {
+            0.0
+        };
+        self.heartbeats = 0;
+        self.video_received = 0;
+        self.video_lost = 0;
+        thresholds.rate(loss, self.jitter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rates_the_worse_of_loss_and_jitter() {
+        let thresholds = NetworkQualityThresholds::default();
+        assert_eq!(thresholds.rate(0.0, 5.0), NetworkQuality::Excellent);
+        assert_eq!(thresholds.rate(0.02, 5.0), NetworkQuality::Good);
+        assert_eq!(thresholds.rate(0.02, 80.0), NetworkQuality::Fair);
+        assert_eq!(thresholds.rate(0.5, 5.0), NetworkQuality::Poor);
+    }
+
+    #[test]
+    fn counts_video_frames_missing_from_the_sequence() {
+        let thresholds = NetworkQualityThresholds::default();
+        let mut stats = NetworkStats::default();
+        for sequence in (0..200).filter(|sequence| sequence % 20 != 5) {
+            stats.on_video_frame(sequence);
+        }
+        // 10 of 200 frames lost.
+        assert_eq!(stats.finish_window(5, &thresholds), NetworkQuality::Fair);
+
+        // Late frames aren't lost, and a restarted encoder isn't a gap.
+        for sequence in [200, 202, 201, 203, 0, 1, 2] {
+            stats.on_video_frame(sequence);
+        }
+        assert_eq!(stats.video_lost, 0);
+        assert_eq!(
+            stats.finish_window(5, &thresholds),
+            NetworkQuality::Excellent
+        );
+    }
+
+    #[test]
+    fn rates_peers_without_video_by_heartbeats_and_jitter() {
+        let thresholds = NetworkQualityThresholds::default();
+        let mut stats = NetworkStats::default();
+        // Steady transit times, with the peer's clock 500ms behind ours.
+        for second in 0..4 {
+            let sent = second as f64 * 1000.0;
+            stats.on_heartbeat(sent, sent + 500.0 + 10.0);
+        }
+        assert_eq!(
+            stats.finish_window(5, &thresholds),
+            NetworkQuality::Excellent
+        );
+
+        stats.on_heartbeat(5000.0, 5510.0);
+        stats.on_heartbeat(6000.0, 6800.0);
+        assert_eq!(stats.finish_window(5, &thresholds), NetworkQuality::Poor);
+    }
+}
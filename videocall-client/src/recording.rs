@@ -0,0 +1,337 @@
+//
+// Local recording of peers' decoded media to WebM.
+//
+// The recorder only reads what the decoders already produce, so live rendering is unaffected:
+// the peers' camera canvases are drawn side by side onto a canvas of its own, and the tracks
+// their audio plays from are mixed in an audio context of its own.  A MediaRecorder encodes the
+// result.  Decoders are replaced when a peer restarts a track, so the sources are looked up again
+// on every frame.
+//
+// WebM constrains what can be recorded:
+//
+//  * video must be VP8, VP9 or AV1, and audio Opus or Vorbis.  Browsers pick the codecs; VP8 and
+//    Opus are asked for where supported, as every WebM player handles them;
+//  * a file holds one video and one audio track, hence the compositing and the mixing;
+//  * MediaRecorder writes WebM as a live stream, without a duration or an index, so some players
+//    can't seek in the file until it is remuxed, e.g. with `ffmpeg -i in.webm -c copy out.webm`.
+//
+
+use crate::clock::Timer;
+use crate::VideoCallClient;
+use anyhow::{anyhow, Result};
+use js_sys::{Array, Promise};
+use log::error;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    AudioContext, Blob, BlobEvent, BlobPropertyBag, CanvasRenderingContext2d, HtmlCanvasElement,
+    MediaRecorder, MediaRecorderOptions, MediaStream, MediaStreamAudioDestinationNode,
+    MediaStreamAudioSourceNode, MediaStreamTrack,
+};
+
+const RECORDING_WIDTH: u32 = 1280;
+const RECORDING_HEIGHT: u32 = 720;
+const RECORDING_FRAMES_PER_SECOND: u32 = 30;
+// How often the recorded data is handed over, and progress reported.
+const RECORDING_TIME_SLICE_MS: i32 = 1000;
+const MIME_TYPES: [&str; 2] = ["video/webm;codecs=vp8,opus", "video/webm"];
+
+/// How far a recording has got, reported about once per second.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecordingProgress {
+    pub duration: Duration,
+    /// Size of the recording so far.
+    pub bytes: u64,
+}
+
+/// [Recorder] records the decoded video and audio of selected peers into a WebM file, with the
+/// peers' cameras side by side.
+///
+/// ```ignore
+/// let mut recorder = Recorder::new(client.clone(), on_progress);
+/// recorder.start(&["alice".to_owned(), "bob".to_owned()])?;
+/// ...
+/// let webm = recorder.stop().await;
+/// ```
+#[derive(Debug)]
+pub struct Recorder {
+    client: VideoCallClient,
+    on_progress: yew::Callback<RecordingProgress>,
+    active: Option<ActiveRecording>,
+}
+
+#[derive(Debug)]
+struct ActiveRecording {
+    media_recorder: MediaRecorder,
+    mime_type: String,
+    chunks: Rc<RefCell<Vec<Blob>>>,
+    audio_context: AudioContext,
+    _compositor: Timer,
+    _on_data: Closure<dyn FnMut(BlobEvent)>,
+}
+
+impl Recorder {
+    /// Construct a recorder:
+    ///
+    /// * `client` - the [`VideoCallClient`](crate::VideoCallClient) whose peers to record.
+    /// * `on_progress` - called about once per second while recording.
+    pub fn new(client: VideoCallClient, on_progress: yew::Callback<RecordingProgress>) -> Self {
+        Self {
+            client,
+            on_progress,
+            active: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Starts recording `peers`, by user id, each taking a cell of the video in the given order.
+    /// Peers that aren't connected, or join later, have a black cell until their video arrives.
+    pub fn start(&mut self, peers: &[String]) -> Result<()> {
+        if self.active.is_some() {
+            return Err(anyhow!("already recording"));
+        }
+        let mime_type = MIME_TYPES
+            .into_iter()
+            .find(|mime_type| MediaRecorder::is_type_supported(mime_type))
+            .ok_or_else(|| anyhow!("WebM recording is not supported"))?;
+        let js_err = |e: JsValue| anyhow!("{:?}", e);
+        let canvas = gloo_utils::document()
+            .create_element("canvas")
+            .map_err(js_err)?
+            .unchecked_into::<HtmlCanvasElement>();
+        canvas.set_width(RECORDING_WIDTH);
+        canvas.set_height(RECORDING_HEIGHT);
+        let context = canvas
+            .get_context("2d")
+            .map_err(js_err)?
+            .ok_or_else(|| anyhow!("no 2d context"))?
+            .unchecked_into::<CanvasRenderingContext2d>();
+        let stream = canvas
+            .capture_stream_with_frame_request_rate(RECORDING_FRAMES_PER_SECOND as f64)
+            .map_err(js_err)?;
+
+        let audio_context = AudioContext::new().map_err(js_err)?;
+        let destination = audio_context
+            .create_media_stream_destination()
+            .map_err(js_err)?;
+        for track in destination.stream().get_audio_tracks().iter() {
+            stream.add_track(&track.unchecked_into::<MediaStreamTrack>());
+        }
+
+        let mut mixer = AudioMixer {
+            context: audio_context.clone(),
+            destination,
+            sources: HashMap::new(),
+        };
+        let clock = self.client.clock();
+        let client = self.client.clone();
+        let peers = peers.to_vec();
+        let compositor = clock.every(
+            Duration::from_secs(1) / RECORDING_FRAMES_PER_SECOND,
+            Box::new(move || {
+                let sources = client.recording_sources(&peers);
+                draw_frame(&context, &sources);
+                mixer.update(sources.iter().flatten().flat_map(|(_, tracks)| tracks));
+            }),
+        );
+
+        let media_recorder = MediaRecorder::new_with_media_stream_and_media_recorder_options(
+            &stream,
+            MediaRecorderOptions::new().mime_type(mime_type),
+        )
+        .map_err(js_err)?;
+        let chunks = Rc::new(RefCell::new(Vec::new()));
+        let on_data = {
+            let chunks = Rc::clone(&chunks);
+            let on_progress = self.on_progress.clone();
+            let started = clock.now();
+            let mut bytes = 0;
+            Closure::wrap(Box::new(move |event: BlobEvent| {
+                let Some(blob) = event.data() else {
+                    return;
+                };
+                bytes += blob.size() as u64;
+                chunks.borrow_mut().push(blob);
+                on_progress.emit(RecordingProgress {
+                    duration: Duration::from_millis((clock.now() - started) as u64),
+                    bytes,
+                });
+            }) as Box<dyn FnMut(BlobEvent)>)
+        };
+        media_recorder.set_ondataavailable(Some(on_data.as_ref().unchecked_ref()));
+        media_recorder
+            .start_with_time_slice(RECORDING_TIME_SLICE_MS)
+            .map_err(js_err)?;
+
+        self.active = Some(ActiveRecording {
+            media_recorder,
+            mime_type: mime_type.to_owned(),
+            chunks,
+            audio_context,
+            _compositor: compositor,
+            _on_data: on_data,
+        });
+        Ok(())
+    }
+
+    /// Stops recording, resolving to the WebM file once the last data is in, or to `None` if
+    /// nothing was being recorded.
+    pub fn stop(&mut self) -> impl Future<Output = Option<Blob>> {
+        let active = self.active.take();
+        async move {
+            let active = active?;
+            // The stop event comes after the last dataavailable event.
+            let stopped = Promise::new(&mut |resolve, _| {
+                active.media_recorder.set_onstop(Some(&resolve));
+            });
+            if let Err(e) = active.media_recorder.stop() {
+                error!("failed to stop recording: {:?}", e);
+            } else {
+                let _ = JsFuture::from(stopped).await;
+            }
+            let _ = active.audio_context.close();
+            let parts = active.chunks.borrow().iter().collect::<Array>();
+            Blob::new_with_blob_sequence_and_options(
+                &parts,
+                BlobPropertyBag::new().type_(&active.mime_type),
+            )
+            .ok()
+        }
+    }
+}
+
+// Mixes the peers' audio tracks into the recording, following the tracks as they come and go.
+struct AudioMixer {
+    context: AudioContext,
+    destination: MediaStreamAudioDestinationNode,
+    // By track id.
+    sources: HashMap<String, MediaStreamAudioSourceNode>,
+}
+
+impl AudioMixer {
+    fn update<'a>(&mut self, tracks: impl Iterator<Item = &'a MediaStreamTrack>) {
+        let mut current = HashMap::new();
+        for track in tracks {
+            let id = track.id();
+            let source = match self.sources.remove(&id) {
+                Some(source) => source,
+                None => match self.connect(track) {
+                    Ok(source) => source,
+                    Err(e) => {
+                        error!("failed to record audio track: {:?}", e);
+                        continue;
+                    }
+                },
+            };
+            current.insert(id, source);
+        }
+        for source in self.sources.values() {
+            let _ = source.disconnect();
+        }
+        self.sources = current;
+    }
+
+    fn connect(&self, track: &MediaStreamTrack) -> Result<MediaStreamAudioSourceNode, JsValue> {
+        let stream = MediaStream::new_with_tracks(&Array::of1(track))?;
+        let source = self.context.create_media_stream_source(&stream)?;
+        source.connect_with_audio_node(&self.destination)?;
+        Ok(source)
+    }
+}
+
+fn draw_frame(
+    context: &CanvasRenderingContext2d,
+    sources: &[Option<(String, Vec<MediaStreamTrack>)>],
+) {
+    context.set_fill_style(&"black".into());
+    let (width, height) = (RECORDING_WIDTH as f64, RECORDING_HEIGHT as f64);
+    context.fill_rect(0.0, 0.0, width, height);
+    let cells = grid_cells(sources.len(), width, height);
+    for (source, cell) in sources.iter().zip(cells) {
+        let Some((canvas_id, _)) = source else {
+            continue;
+        };
+        let Some(canvas) = gloo_utils::document()
+            .get_element_by_id(canvas_id)
+            .and_then(|element| element.dyn_into::<HtmlCanvasElement>().ok())
+        else {
+            continue;
+        };
+        if canvas.width() == 0 || canvas.height() == 0 {
+            continue;
+        }
+        let (x, y, w, h) = fit(canvas.width() as f64, canvas.height() as f64, cell);
+        let _ = context.draw_image_with_html_canvas_element_and_dw_and_dh(&canvas, x, y, w, h);
+    }
+}
+
+type Rect = (f64, f64, f64, f64);
+
+// Splits a `width` x `height` frame into a grid of equal cells for `count` peers, filled row by
+// row, as square as possible.
+fn grid_cells(count: usize, width: f64, height: f64) -> Vec<Rect> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let columns = (count as f64).sqrt().ceil() as usize;
+    let rows = count.div_ceil(columns);
+    let (cell_width, cell_height) = (width / columns as f64, height / rows as f64);
+    (0..count)
+        .map(|i| {
+            let (row, column) = (i / columns, i % columns);
+            (
+                column as f64 * cell_width,
+                row as f64 * cell_height,
+                cell_width,
+                cell_height,
+            )
+        })
+        .collect()
+}
+
+// Scales a `width` x `height` image to fit `cell`, centred, keeping its aspect ratio.
+fn fit(width: f64, height: f64, (x, y, cell_width, cell_height): Rect) -> Rect {
+    let scale = (cell_width / width).min(cell_height / height);
+    let (w, h) = (width * scale, height * scale);
+    (
+        x + (cell_width - w) / 2.0,
+        y + (cell_height - h) / 2.0,
+        w,
+        h,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn peers_are_laid_out_in_a_grid() {
+        assert_eq!(
+            grid_cells(1, 1280.0, 720.0),
+            vec![(0.0, 0.0, 1280.0, 720.0)]
+        );
+        assert_eq!(
+            grid_cells(3, 1280.0, 720.0),
+            vec![
+                (0.0, 0.0, 640.0, 360.0),
+                (640.0, 0.0, 640.0, 360.0),
+                (0.0, 360.0, 640.0, 360.0),
+            ]
+        );
+        // A 4:3 camera in a 16:9 cell is pillarboxed.
+        assert_eq!(
+            fit(640.0, 480.0, (640.0, 0.0, 640.0, 360.0)),
+            (720.0, 0.0, 480.0, 360.0)
+        );
+    }
+}
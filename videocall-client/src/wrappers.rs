@@ -43,6 +43,7 @@ impl fmt::Display for EncodedAudioChunkTypeWrapper {
     }
 }
 
+#[allow(dead_code)]
 pub struct AudioSampleFormatWrapper(pub AudioSampleFormat);
 
 impl From<String> for AudioSampleFormatWrapper {
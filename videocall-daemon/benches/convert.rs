@@ -0,0 +1,39 @@
+//! Compares the reference, single-threaded and (with `--features rayon`) parallel YUYV to I420
+//! conversions.
+//!
+//! Run with `cargo bench --bench convert --features rayon`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use videocall_daemon::convert::{i420_len, yuyv_to_i420, yuyv_to_i420_reference};
+
+const RESOLUTIONS: [(usize, usize); 3] = [(640, 480), (1280, 720), (1920, 1080)];
+
+fn yuyv_frame(width: usize, height: usize) -> Vec<u8> {
+    (0..width * height * 2).map(|i| i as u8).collect()
+}
+
+fn bench_yuyv_to_i420(c: &mut Criterion) {
+    let mut group = c.benchmark_group("yuyv_to_i420");
+    for (width, height) in RESOLUTIONS {
+        let yuyv = yuyv_frame(width, height);
+        let mut dest = vec![0u8; i420_len(width, height)];
+        let id = format!("{}x{}", width, height);
+        group.throughput(Throughput::Bytes(yuyv.len() as u64));
+        group.bench_with_input(BenchmarkId::new("reference", &id), &yuyv, |b, yuyv| {
+            b.iter(|| yuyv_to_i420_reference(yuyv, &mut dest, width, height).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("fast", &id), &yuyv, |b, yuyv| {
+            b.iter(|| yuyv_to_i420(yuyv, &mut dest, width, height).unwrap())
+        });
+        #[cfg(feature = "rayon")]
+        group.bench_with_input(BenchmarkId::new("rayon", &id), &yuyv, |b, yuyv| {
+            b.iter(|| {
+                videocall_daemon::convert::par_yuyv_to_i420(yuyv, &mut dest, width, height).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_yuyv_to_i420);
+criterion_main!(benches);
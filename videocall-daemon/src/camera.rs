@@ -1,9 +1,18 @@
+use crate::camera_format::{choose_closest, choose_default};
+use crate::convert::{i420_len, rgb24_to_i420};
+#[cfg(not(feature = "rayon"))]
+use crate::convert::{nv12_to_i420, yuyv_to_i420};
+#[cfg(feature = "rayon")]
+use crate::convert::{par_nv12_to_i420 as nv12_to_i420, par_yuyv_to_i420 as yuyv_to_i420};
+use crate::frame_pool::FramePool;
+use crate::frame_rate::FrameDecimator;
 use crate::video_encoder::Frame;
 use crate::video_encoder::VideoEncoderBuilder;
-use anyhow::Result;
-use nokhwa::pixel_format::YuyvFormat;
+use anyhow::{anyhow, bail, Result};
+use nokhwa::pixel_format::RgbFormat;
 use nokhwa::utils::RequestedFormat;
 use nokhwa::utils::RequestedFormatType;
+use nokhwa::utils::{buf_mjpeg_to_rgb, Resolution};
 
 use nokhwa::{
     utils::{ApiBackend, CameraFormat, CameraIndex, FrameFormat},
@@ -11,10 +20,11 @@ use nokhwa::{
 };
 use protobuf::Message;
 use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{Receiver, SyncSender};
 use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::mpsc::{self, error::TrySendError, Sender};
 use tracing::{debug, error, info};
 
 use videocall_types::protos::media_packet::media_packet::MediaType;
@@ -23,6 +33,9 @@ use videocall_types::protos::packet_wrapper::{packet_wrapper::PacketType, Packet
 
 type CameraPacket = (Vec<u8>, u128);
 
+// Resolution the camera opened with, and the pool of frames of that size.
+type CameraReady = (u32, u32, FramePool);
+
 pub fn transform_video_chunk(frame: &Frame, email: &str) -> PacketWrapper {
     let frame_type = if frame.key {
         "key".to_string()
@@ -53,17 +66,99 @@ pub fn transform_video_chunk(frame: &Frame, email: &str) -> PacketWrapper {
 
 static THRESHOLD_MILLIS: u128 = 1000;
 
+// Frames in flight between the camera and encoder threads rarely exceed a handful.
+static FRAME_POOL_CAPACITY: usize = 8;
+
 pub fn since_the_epoch() -> Duration {
     SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Chooses the capture format of the camera `video_device` when the user didn't ask for one,
+/// among all the formats it supports.
+pub fn default_camera_format(video_device: &CameraIndex) -> Result<CameraFormat> {
+    choose_default(&camera_formats(video_device)?)
+        .ok_or_else(|| anyhow!("camera {} has no usable format", video_device))
+}
+
+/// Chooses the format of the camera `video_device` closest to `width`x`height` at `framerate`,
+/// for when the camera doesn't offer exactly that.
+pub fn closest_camera_format(
+    video_device: &CameraIndex,
+    width: u32,
+    height: u32,
+    framerate: u32,
+) -> Result<CameraFormat> {
+    choose_closest(&camera_formats(video_device)?, width, height, framerate)
+        .ok_or_else(|| anyhow!("camera {} has no usable format", video_device))
+}
+
+/// Lists every format the camera `video_device` can capture in, whatever its pixel format.
+pub fn camera_formats(video_device: &CameraIndex) -> Result<Vec<CameraFormat>> {
+    // RGB can be decoded from every pixel format, so no format is filtered out.
+    let mut camera = Camera::new(
+        video_device.clone(),
+        RequestedFormat::new::<RgbFormat>(RequestedFormatType::None),
+    )?;
+    Ok(camera.compatible_camera_formats()?)
+}
+
+#[derive(Clone, Debug)]
 pub struct CameraConfig {
     pub width: u32,
     pub height: u32,
     pub framerate: u32,
-    pub video_device_index: usize,
+    pub video_device: CameraIndex,
     pub frame_format: FrameFormat,
+    /// Frames per second to send, dropping captured frames as needed, if lower than `framerate`.
+    pub target_fps: Option<u32>,
+}
+
+/// Converts the frames of a camera into I420, whatever the pixel format it captures in.
+struct FrameConverter {
+    format: FrameFormat,
+    width: usize,
+    height: usize,
+    // MJPEG frames are decoded to RGB24 first.
+    rgb: Vec<u8>,
+}
+
+impl FrameConverter {
+    fn new(format: FrameFormat, resolution: Resolution) -> Result<Self> {
+        let (width, height) = (resolution.width() as usize, resolution.height() as usize);
+        let rgb = match format {
+            FrameFormat::YUYV | FrameFormat::NV12 | FrameFormat::RAWRGB => vec![],
+            FrameFormat::MJPEG => vec![0; width * height * 3],
+            FrameFormat::GRAY => bail!("unsupported camera frame format {}", format),
+        };
+        Ok(Self {
+            format,
+            width,
+            height,
+            rgb,
+        })
+    }
+
+    fn convert(&mut self, frame: &[u8], dest: &mut [u8]) -> Result<()> {
+        let (width, height) = (self.width, self.height);
+        match self.format {
+            FrameFormat::YUYV => yuyv_to_i420(frame, dest, width, height),
+            // Backends hand out whole rows, padding included, so the stride follows from the
+            // frame size.
+            FrameFormat::NV12 => {
+                nv12_to_i420(frame, stride(frame, height * 3 / 2), dest, width, height)
+            }
+            FrameFormat::RAWRGB => rgb24_to_i420(frame, stride(frame, height), dest, width, height),
+            FrameFormat::MJPEG => {
+                buf_mjpeg_to_rgb(frame, &mut self.rgb, false)?;
+                rgb24_to_i420(&self.rgb, width * 3, dest, width, height)
+            }
+            FrameFormat::GRAY => bail!("unsupported camera frame format {}", self.format),
+        }
+    }
+}
+
+fn stride(frame: &[u8], rows: usize) -> usize {
+    frame.len() / rows.max(1)
 }
 
 pub struct CameraDaemon {
@@ -95,15 +190,18 @@ impl CameraDaemon {
     }
 
     pub fn start(&mut self) -> Result<()> {
-        self.handles.push(self.camera_thread()?);
-        let encoder = self.encoder_thread();
+        // The camera may not offer the configured resolution, so the camera thread tells the
+        // encoder thread the one it opened with.
+        let (ready_tx, ready_rx) = std::sync::mpsc::sync_channel(1);
+        self.handles.push(self.camera_thread(ready_tx)?);
+        let encoder = self.encoder_thread(ready_rx);
         self.handles.push(encoder);
         // let fps = self.fps_thread();
         // self.handles.push(fps);
         Ok(())
     }
 
-    fn camera_thread(&self) -> Result<JoinHandle<()>> {
+    fn camera_thread(&self, ready_tx: SyncSender<CameraReady>) -> Result<JoinHandle<()>> {
         let devices = nokhwa::query(ApiBackend::Auto)?;
         for (i, camera_info) in devices.iter().enumerate() {
             info!("AVAILABLE CAMERA DEVICE INDEX {}: {:?}", i, camera_info);
@@ -113,50 +211,88 @@ impl CameraDaemon {
         let height = self.config.height;
         let framerate = self.config.framerate;
         let frame_format = self.config.frame_format;
-        let video_device_index = self.config.video_device_index as u32;
+        let mut decimator = self.config.target_fps.map(FrameDecimator::new);
+        let video_device = self.config.video_device.clone();
         let quit = self.quit.clone();
-        let mut buffer_slice_i420 = vec![
-            0u8;
-            (width * height + 2 * (width / 2) * (height / 2))
-                .try_into()
-                .unwrap()
-        ];
         Ok(std::thread::spawn(move || {
             debug!("Camera opened... waiting for frames");
-            let mut camera = Camera::new(
-                CameraIndex::Index(video_device_index),
-                RequestedFormat::new::<YuyvFormat>(RequestedFormatType::Closest(
+            let camera = Camera::new(
+                video_device.clone(),
+                RequestedFormat::new::<RgbFormat>(RequestedFormatType::Closest(
                     CameraFormat::new_from(width, height, frame_format, framerate),
                 )),
-            )
-            .unwrap();
-            camera.open_stream().unwrap();
-
-            while camera
-                .write_frame_to_buffer::<YuyvFormat>(&mut buffer_slice_i420)
-                .is_ok()
-            {
+            );
+            let mut camera = match camera.and_then(|mut camera| {
+                camera.open_stream()?;
+                Ok(camera)
+            }) {
+                Ok(camera) => camera,
+                Err(e) => {
+                    error!("failed to open camera {}: {}", video_device, e);
+                    return;
+                }
+            };
+            let resolution = camera.resolution();
+            let mut converter = match FrameConverter::new(camera.frame_format(), resolution) {
+                Ok(converter) => converter,
+                Err(e) => {
+                    error!("{}", e);
+                    return;
+                }
+            };
+            if resolution.width() != width || resolution.height() != height {
+                info!(
+                    "camera {} opened at {} instead of {}x{}",
+                    video_device, resolution, width, height
+                );
+            }
+            let (width, height) = (resolution.width(), resolution.height());
+            let frame_pool = FramePool::new(
+                i420_len(width as usize, height as usize),
+                FRAME_POOL_CAPACITY,
+            );
+            if ready_tx.send((width, height, frame_pool.clone())).is_err() {
+                return;
+            }
+
+            while let Ok(frame) = camera.frame_raw() {
                 if quit.load(std::sync::atomic::Ordering::Relaxed) {
                     return;
                 }
-                if let Err(e) = cam_tx.try_send(Some((
-                    buffer_slice_i420.to_vec(),
-                    since_the_epoch().as_millis(),
-                ))) {
-                    error!("error sending image {}", e);
+                let captured = since_the_epoch().as_millis();
+                if let Some(decimator) = &mut decimator {
+                    if !decimator.keep(captured) {
+                        continue;
+                    }
+                }
+                let mut image = frame_pool.take();
+                if let Err(e) = converter.convert(&frame, &mut image) {
+                    error!("error converting frame, skipping it: {}", e);
+                    frame_pool.put(image);
+                    continue;
+                }
+                match cam_tx.try_send(Some((image, captured))) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(Some((image, _)))) => {
+                        error!("error sending image: encoder is falling behind");
+                        frame_pool.put(image);
+                    }
+                    Err(e) => error!("error sending image {}", e),
                 }
             }
         }))
     }
 
-    fn encoder_thread(&mut self) -> JoinHandle<()> {
+    fn encoder_thread(&mut self, ready_rx: Receiver<CameraReady>) -> JoinHandle<()> {
         let mut cam_rx = self.cam_rx.take().unwrap();
         let quic_tx = self.quic_tx.clone();
         let quit = self.quit.clone();
-        let width = self.config.width;
-        let height = self.config.height;
         let user_id = self.user_id.clone();
         std::thread::spawn(move || {
+            // The camera thread hangs up without a resolution if the camera failed to open.
+            let Ok((width, height, frame_pool)) = ready_rx.recv() else {
+                return;
+            };
             let _start = Instant::now();
             let mut video_encoder = VideoEncoderBuilder::default()
                 .set_resolution(width, height)
@@ -174,10 +310,12 @@ impl CameraDaemon {
                 let image_age = since_the_epoch().as_millis() - age;
                 if image_age > THRESHOLD_MILLIS {
                     debug!("throwing away old image with age {} ms", image_age);
+                    frame_pool.put(image);
                     continue;
                 }
                 let encoding_time = Instant::now();
                 let frames = video_encoder.encode(sequence, image.as_slice()).unwrap();
+                frame_pool.put(image);
                 sequence += 1;
                 debug!("encoding took {:?}", encoding_time.elapsed());
                 for frame in frames {
@@ -0,0 +1,194 @@
+//! Listing and setting camera controls (brightness, exposure, zoom, ...) from the command line.
+//!
+//! Controls are named like nokhwa's [KnownCameraControl] variants, case-insensitively and with
+//! `-` or `_` allowed between words, e.g. `brightness` or `white-balance`. Values are parsed
+//! according to the control's [ControlValueDescription] and checked against its range, step or
+//! possible values before anything is sent to the camera, as drivers tend to reject bad values
+//! with unhelpful errors or clamp them silently.
+
+use anyhow::{anyhow, bail, Context, Result};
+use nokhwa::pixel_format::YuyvFormat;
+use nokhwa::utils::{
+    all_known_camera_controls, CameraControl, CameraIndex, ControlValueDescription,
+    ControlValueSetter, KnownCameraControl, KnownCameraControlFlag, RequestedFormat,
+    RequestedFormatType,
+};
+use nokhwa::Camera;
+
+/// Looks up a control by name, see the module documentation.
+pub fn parse_control(name: &str) -> Result<KnownCameraControl> {
+    let normalize = |name: &str| name.replace(['-', '_'], "").to_lowercase();
+    let wanted = normalize(name);
+    all_known_camera_controls()
+        .into_iter()
+        .find(|control| normalize(&control.to_string()) == wanted)
+        .ok_or_else(|| {
+            let names: Vec<String> = all_known_camera_controls()
+                .iter()
+                .map(|control| control.to_string().to_lowercase())
+                .collect();
+            anyhow!(
+                "unknown control {:?}, expected one of: {}",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+/// Parses `value` for a control described by `description`, rejecting values outside of its
+/// range, off its step or not among its possible values.
+pub fn parse_value(
+    description: &ControlValueDescription,
+    value: &str,
+) -> Result<ControlValueSetter> {
+    let integer = || {
+        value
+            .parse::<i64>()
+            .with_context(|| format!("expected an integer, got {:?}", value))
+    };
+    let float = || {
+        value
+            .parse::<f64>()
+            .with_context(|| format!("expected a number, got {:?}", value))
+    };
+    match description {
+        ControlValueDescription::Integer { .. } => Ok(ControlValueSetter::Integer(integer()?)),
+        ControlValueDescription::IntegerRange { min, max, step, .. } => {
+            let value = integer()?;
+            if value < *min || value > *max {
+                bail!("{} is out of range, expected {} to {}", value, min, max);
+            }
+            if *step > 1 && (value - min) % step != 0 {
+                bail!("{} is not a multiple of {} from {}", value, step, min);
+            }
+            Ok(ControlValueSetter::Integer(value))
+        }
+        ControlValueDescription::Float { .. } => Ok(ControlValueSetter::Float(float()?)),
+        ControlValueDescription::FloatRange { min, max, .. } => {
+            let value = float()?;
+            if value < *min || value > *max {
+                bail!("{} is out of range, expected {} to {}", value, min, max);
+            }
+            Ok(ControlValueSetter::Float(value))
+        }
+        ControlValueDescription::Boolean { .. } => match value.to_lowercase().as_str() {
+            "true" | "on" | "1" => Ok(ControlValueSetter::Boolean(true)),
+            "false" | "off" | "0" => Ok(ControlValueSetter::Boolean(false)),
+            _ => bail!("expected true or false, got {:?}", value),
+        },
+        ControlValueDescription::Enum { possible, .. } => {
+            let value = integer()?;
+            if !possible.contains(&value) {
+                bail!("{} is not one of the possible values {:?}", value, possible);
+            }
+            Ok(ControlValueSetter::EnumValue(value))
+        }
+        ControlValueDescription::String { .. } => Ok(ControlValueSetter::String(value.to_owned())),
+        description => bail!("setting controls like {} is not supported", description),
+    }
+}
+
+fn open(device: &CameraIndex) -> Result<Camera> {
+    Ok(Camera::new(
+        device.clone(),
+        RequestedFormat::new::<YuyvFormat>(RequestedFormatType::None),
+    )?)
+}
+
+/// Lists the controls the camera `device` supports.
+pub fn list_controls(device: &CameraIndex) -> Result<Vec<CameraControl>> {
+    Ok(open(device)?.camera_controls()?)
+}
+
+/// Sets the control `name` of the camera `device` to `value`, after checking that the control
+/// can be set and the value is valid for it.
+pub fn set_control(device: &CameraIndex, name: &str, value: &str) -> Result<()> {
+    let control = parse_control(name)?;
+    let mut camera = open(device)?;
+    let current = camera
+        .camera_control(control)
+        .with_context(|| format!("camera {} doesn't support {}", device, control))?;
+    for flag in [
+        KnownCameraControlFlag::ReadOnly,
+        KnownCameraControlFlag::Disabled,
+    ] {
+        if current.flag().contains(&flag) {
+            bail!("{} can't be set, it is {}", control, flag);
+        }
+    }
+    let setter = parse_value(current.description(), value)
+        .with_context(|| format!("invalid value for {}", control))?;
+    camera
+        .set_camera_control(control, setter)
+        .with_context(|| format!("camera {} rejected {} = {}", device, control, value))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_control_names() {
+        assert_eq!(
+            parse_control("brightness").unwrap(),
+            KnownCameraControl::Brightness
+        );
+        assert_eq!(
+            parse_control("White-Balance").unwrap(),
+            KnownCameraControl::WhiteBalance
+        );
+        assert_eq!(
+            parse_control("backlight_comp").unwrap(),
+            KnownCameraControl::BacklightComp
+        );
+        assert!(parse_control("shininess").is_err());
+    }
+
+    #[test]
+    fn validates_values_against_the_description() {
+        let brightness = ControlValueDescription::IntegerRange {
+            min: 0,
+            max: 255,
+            value: 128,
+            step: 1,
+            default: 128,
+        };
+        assert_eq!(
+            parse_value(&brightness, "200").unwrap(),
+            ControlValueSetter::Integer(200)
+        );
+        assert!(parse_value(&brightness, "300").is_err());
+        assert!(parse_value(&brightness, "bright").is_err());
+
+        let zoom = ControlValueDescription::IntegerRange {
+            min: 100,
+            max: 500,
+            value: 100,
+            step: 10,
+            default: 100,
+        };
+        assert!(parse_value(&zoom, "250").is_ok());
+        assert!(parse_value(&zoom, "255").is_err());
+
+        let power_line = ControlValueDescription::Enum {
+            value: 1,
+            possible: vec![0, 1, 2],
+            default: 1,
+        };
+        assert_eq!(
+            parse_value(&power_line, "2").unwrap(),
+            ControlValueSetter::EnumValue(2)
+        );
+        assert!(parse_value(&power_line, "3").is_err());
+
+        let auto_exposure = ControlValueDescription::Boolean {
+            value: true,
+            default: true,
+        };
+        assert_eq!(
+            parse_value(&auto_exposure, "off").unwrap(),
+            ControlValueSetter::Boolean(false)
+        );
+    }
+}
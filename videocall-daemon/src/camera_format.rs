@@ -0,0 +1,239 @@
+//! Picks a sensible capture format from what a camera offers, for when the user doesn't ask
+//! for one.
+//!
+//! The policy is the highest resolution up to 1280x720 at 24fps or more, preferring the raw
+//! formats (YUYV, then NV12) over MJPEG because they don't need decoding before the encoder.
+//! Among equal candidates the highest frame rate wins. If no format reaches 24fps, the best one
+//! up to 1280x720 is used regardless of its frame rate.
+//!
+//! When the user does ask for a resolution and frame rate the camera doesn't offer exactly,
+//! [choose_closest] picks the nearest format instead: the closest resolution first, then the
+//! closest frame rate, then the pixel format in the order MJPEG, YUYV, NV12. MJPEG comes first
+//! there since cameras usually offer their widest range of resolutions and frame rates in it.
+
+use nokhwa::utils::{CameraFormat, FrameFormat};
+use serde::Serialize;
+use std::cmp::Reverse;
+
+const MAX_DEFAULT_WIDTH: u32 = 1280;
+const MAX_DEFAULT_HEIGHT: u32 = 720;
+const MIN_DEFAULT_FRAME_RATE: u32 = 24;
+
+/// Lower is better; formats the encoder can't be fed from get `None`.
+fn format_rank(format: FrameFormat) -> Option<u8> {
+    match format {
+        FrameFormat::YUYV => Some(0),
+        FrameFormat::NV12 => Some(1),
+        FrameFormat::MJPEG => Some(2),
+        FrameFormat::GRAY | FrameFormat::RAWRGB => None,
+    }
+}
+
+/// Like [format_rank], in the order [choose_closest] breaks ties with.
+fn closest_format_rank(format: FrameFormat) -> Option<u8> {
+    match format {
+        FrameFormat::MJPEG => Some(0),
+        FrameFormat::YUYV => Some(1),
+        FrameFormat::NV12 => Some(2),
+        FrameFormat::GRAY | FrameFormat::RAWRGB => None,
+    }
+}
+
+/// Chooses the default capture format among `available`, see the module documentation for the
+/// policy. Returns `None` if no format is at most 1280x720 in a usable pixel format.
+pub fn choose_default(available: &[CameraFormat]) -> Option<CameraFormat> {
+    let candidates = available.iter().filter(|format| {
+        format.width() <= MAX_DEFAULT_WIDTH
+            && format.height() <= MAX_DEFAULT_HEIGHT
+            && format_rank(format.format()).is_some()
+    });
+    best(
+        candidates
+            .clone()
+            .filter(|format| format.frame_rate() >= MIN_DEFAULT_FRAME_RATE),
+    )
+    .or_else(|| best(candidates))
+}
+
+fn best<'a>(formats: impl Iterator<Item = &'a CameraFormat>) -> Option<CameraFormat> {
+    formats
+        .max_by_key(|format| {
+            (
+                format.width() * format.height(),
+                Reverse(format_rank(format.format())),
+                format.frame_rate(),
+            )
+        })
+        .copied()
+}
+
+/// Chooses the format among `available` closest to `width`x`height` at `frame_rate`, see the
+/// module documentation for the policy. Returns `None` if no format is in a usable pixel format.
+pub fn choose_closest(
+    available: &[CameraFormat],
+    width: u32,
+    height: u32,
+    frame_rate: u32,
+) -> Option<CameraFormat> {
+    available
+        .iter()
+        .filter_map(|format| Some((format, closest_format_rank(format.format())?)))
+        .min_by_key(|(format, rank)| {
+            (
+                format.width().abs_diff(width) + format.height().abs_diff(height),
+                format.frame_rate().abs_diff(frame_rate),
+                // At the same distance, the higher frame rate.
+                Reverse(format.frame_rate()),
+                *rank,
+            )
+        })
+        .map(|(format, _)| *format)
+}
+
+/// A camera format as listed by `info --list-formats --json`.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct FormatDescription {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    /// Pixel format, e.g. `YUYV` or `MJPEG`.
+    pub fourcc: String,
+}
+
+impl From<&CameraFormat> for FormatDescription {
+    fn from(format: &CameraFormat) -> Self {
+        Self {
+            width: format.width(),
+            height: format.height(),
+            fps: format.frame_rate(),
+            fourcc: format.format().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn format(width: u32, height: u32, format: FrameFormat, fps: u32) -> CameraFormat {
+        CameraFormat::new_from(width, height, format, fps)
+    }
+
+    #[test]
+    fn typical_webcam() {
+        // 1080p only in MJPEG, raw formats are limited by USB bandwidth.
+        let available = [
+            format(1920, 1080, FrameFormat::MJPEG, 30),
+            format(1280, 720, FrameFormat::MJPEG, 30),
+            format(1280, 720, FrameFormat::YUYV, 10),
+            format(640, 480, FrameFormat::YUYV, 30),
+            format(640, 480, FrameFormat::MJPEG, 30),
+        ];
+        assert_eq!(
+            choose_default(&available),
+            Some(format(1280, 720, FrameFormat::MJPEG, 30))
+        );
+    }
+
+    #[test]
+    fn prefers_raw_formats_at_equal_resolution() {
+        let available = [
+            format(1280, 720, FrameFormat::MJPEG, 60),
+            format(1280, 720, FrameFormat::NV12, 30),
+            format(1280, 720, FrameFormat::YUYV, 30),
+        ];
+        assert_eq!(
+            choose_default(&available),
+            Some(format(1280, 720, FrameFormat::YUYV, 30))
+        );
+    }
+
+    #[test]
+    fn prefers_highest_frame_rate_of_the_same_format() {
+        let available = [
+            format(640, 480, FrameFormat::YUYV, 30),
+            format(640, 480, FrameFormat::YUYV, 60),
+            format(640, 480, FrameFormat::YUYV, 24),
+        ];
+        assert_eq!(
+            choose_default(&available),
+            Some(format(640, 480, FrameFormat::YUYV, 60))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_low_frame_rates() {
+        let available = [
+            format(1280, 720, FrameFormat::YUYV, 10),
+            format(640, 480, FrameFormat::YUYV, 15),
+            format(3840, 2160, FrameFormat::MJPEG, 30),
+        ];
+        assert_eq!(
+            choose_default(&available),
+            Some(format(1280, 720, FrameFormat::YUYV, 10))
+        );
+    }
+
+    #[test]
+    fn nothing_usable() {
+        let available = [
+            format(1920, 1080, FrameFormat::YUYV, 30),
+            format(640, 480, FrameFormat::GRAY, 30),
+        ];
+        assert_eq!(choose_default(&available), None);
+        assert_eq!(choose_default(&[]), None);
+    }
+
+    #[test]
+    fn closest_to_the_requested_format() {
+        let available = [
+            format(1920, 1080, FrameFormat::MJPEG, 30),
+            format(1280, 720, FrameFormat::MJPEG, 30),
+            format(1280, 720, FrameFormat::YUYV, 10),
+            format(640, 480, FrameFormat::YUYV, 30),
+            format(640, 480, FrameFormat::GRAY, 25),
+        ];
+        // The exact format when offered.
+        assert_eq!(
+            choose_closest(&available, 1280, 720, 10),
+            Some(format(1280, 720, FrameFormat::YUYV, 10))
+        );
+        // Resolution matters more than frame rate.
+        assert_eq!(
+            choose_closest(&available, 1280, 720, 15),
+            Some(format(1280, 720, FrameFormat::YUYV, 10))
+        );
+        assert_eq!(
+            choose_closest(&available, 800, 600, 25),
+            Some(format(640, 480, FrameFormat::YUYV, 30))
+        );
+        assert_eq!(choose_closest(&available[4..], 640, 480, 25), None);
+    }
+
+    #[test]
+    fn closest_breaks_ties_by_frame_rate_then_pixel_format() {
+        let available = [
+            format(640, 480, FrameFormat::NV12, 30),
+            format(640, 480, FrameFormat::YUYV, 20),
+            format(640, 480, FrameFormat::YUYV, 30),
+            format(640, 480, FrameFormat::MJPEG, 30),
+        ];
+        assert_eq!(
+            choose_closest(&available, 640, 480, 25),
+            Some(format(640, 480, FrameFormat::MJPEG, 30))
+        );
+        assert_eq!(
+            choose_closest(&available[..3], 640, 480, 25),
+            Some(format(640, 480, FrameFormat::YUYV, 30))
+        );
+    }
+
+    #[test]
+    fn describes_formats_as_json() {
+        let description = FormatDescription::from(&format(1280, 720, FrameFormat::MJPEG, 30));
+        assert_eq!(
+            serde_json::to_string(&description).unwrap(),
+            r#"{"width":1280,"height":720,"fps":30,"fourcc":"MJPEG"}"#
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Canonical string form of a camera identifier, for CLI arguments and saved configuration.
+//!
+//! nokhwa's [CameraIndex] is either a numeric index or a string id (e.g. a device path or URL).
+//! Its own `Display` prints both the same way, so the string id `"0"` can't be told apart from
+//! index 0. [CameraId] writes indices as plain numbers and string ids verbatim, except for string
+//! ids that would read back as something else, which get an `id:` prefix:
+//!
+//! | CameraIndex          | string       |
+//! |----------------------|--------------|
+//! | `Index(0)`           | `0`          |
+//! | `String("/dev/v4l")` | `/dev/v4l`   |
+//! | `String("0")`        | `id:0`       |
+//! | `String("id:cam")`   | `id:id:cam`  |
+
+use anyhow::{bail, Result};
+use nokhwa::utils::{CameraIndex, CameraInfo};
+use std::convert::Infallible;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+const STRING_ID_PREFIX: &str = "id:";
+
+/// A [CameraIndex] with a lossless `Display`/`FromStr` round trip.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CameraId(pub CameraIndex);
+
+impl fmt::Display for CameraId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            CameraIndex::Index(index) => write!(f, "{}", index),
+            CameraIndex::String(id)
+                if id.parse::<u32>().is_ok() || id.starts_with(STRING_ID_PREFIX) =>
+            {
+                write!(f, "{}{}", STRING_ID_PREFIX, id)
+            }
+            CameraIndex::String(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl FromStr for CameraId {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let index = if let Some(id) = s.strip_prefix(STRING_ID_PREFIX) {
+            CameraIndex::String(id.to_string())
+        } else if let Ok(index) = s.parse::<u32>() {
+            CameraIndex::Index(index)
+        } else {
+            CameraIndex::String(s.to_string())
+        };
+        Ok(CameraId(index))
+    }
+}
+
+impl From<CameraIndex> for CameraId {
+    fn from(index: CameraIndex) -> Self {
+        CameraId(index)
+    }
+}
+
+/// A camera to stream in addition to the main one, written `LABEL=DEVICE` with the device in
+/// the [CameraId] form, e.g. `side=1` or `top=/dev/video2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabeledCamera {
+    pub label: String,
+    pub device: CameraId,
+}
+
+impl FromStr for LabeledCamera {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((label, device)) if !label.is_empty() && !device.is_empty() => Ok(LabeledCamera {
+                label: label.to_string(),
+                device: device.parse().unwrap(),
+            }),
+            _ => Err(format!("expected LABEL=DEVICE, got {:?}", s)),
+        }
+    }
+}
+
+/// Finds the camera among `cameras` whose name contains `name`, ignoring case. Fails, listing
+/// the cameras, unless exactly one matches.
+pub fn find_camera_by_name(cameras: &[CameraInfo], name: &str) -> Result<CameraIndex> {
+    let wanted = name.to_lowercase();
+    let matches: Vec<&CameraInfo> = cameras
+        .iter()
+        .filter(|camera| camera.human_name().to_lowercase().contains(&wanted))
+        .collect();
+    let list = |cameras: &[&CameraInfo]| {
+        cameras.iter().fold(String::new(), |mut list, camera| {
+            let _ = write!(
+                list,
+                "\n  {}: {}",
+                CameraId(camera.index().clone()),
+                camera.human_name()
+            );
+            list
+        })
+    };
+    match matches.as_slice() {
+        [camera] => Ok(camera.index().clone()),
+        [] => bail!(
+            "no camera name contains {:?}, the cameras are:{}",
+            name,
+            list(&cameras.iter().collect::<Vec<_>>())
+        ),
+        matches => bail!(
+            "more than one camera name contains {:?}:{}",
+            name,
+            list(matches)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for index in [
+            CameraIndex::Index(0),
+            CameraIndex::Index(42),
+            CameraIndex::String("/dev/video0".to_string()),
+            CameraIndex::String("rtsp://camera.local/stream".to_string()),
+            CameraIndex::String("0".to_string()),
+            CameraIndex::String("id:front".to_string()),
+            CameraIndex::String(String::new()),
+        ] {
+            let id = CameraId(index);
+            assert_eq!(id.to_string().parse::<CameraId>().unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn parses_numbers_as_indices() {
+        assert_eq!("3".parse::<CameraId>().unwrap().0, CameraIndex::Index(3));
+        assert_eq!(
+            "id:3".parse::<CameraId>().unwrap().0,
+            CameraIndex::String("3".to_string())
+        );
+        assert_eq!(
+            "FaceTime HD Camera".parse::<CameraId>().unwrap().0,
+            CameraIndex::String("FaceTime HD Camera".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_labeled_cameras() {
+        assert_eq!(
+            "side=1".parse::<LabeledCamera>().unwrap(),
+            LabeledCamera {
+                label: "side".to_string(),
+                device: CameraId(CameraIndex::Index(1)),
+            }
+        );
+        // Only the first `=` separates the label.
+        assert_eq!(
+            "top=rtsp://cam/?a=b"
+                .parse::<LabeledCamera>()
+                .unwrap()
+                .device,
+            CameraId(CameraIndex::String("rtsp://cam/?a=b".to_string()))
+        );
+        assert!("side".parse::<LabeledCamera>().is_err());
+        assert!("=1".parse::<LabeledCamera>().is_err());
+    }
+
+    #[test]
+    fn finds_cameras_by_name() {
+        let cameras = [
+            CameraInfo::new("Logitech BRIO", "", "", CameraIndex::Index(0)),
+            CameraInfo::new("FaceTime HD Camera", "", "", CameraIndex::Index(1)),
+            CameraInfo::new("Logitech C920", "", "", CameraIndex::Index(2)),
+        ];
+        assert_eq!(
+            find_camera_by_name(&cameras, "brio").unwrap(),
+            CameraIndex::Index(0)
+        );
+        assert_eq!(
+            find_camera_by_name(&cameras, "facetime").unwrap(),
+            CameraIndex::Index(1)
+        );
+        let ambiguous = find_camera_by_name(&cameras, "logitech").unwrap_err();
+        assert!(ambiguous.to_string().contains("2: Logitech C920"));
+        assert!(find_camera_by_name(&cameras, "Razer").is_err());
+    }
+}
@@ -0,0 +1,548 @@
+//! Pixel format conversion from camera layouts to the planar I420 that the encoder consumes.
+//!
+//! YUYV is what most webcams capture in, and has optimized paths. NV12, BGR24 and RGB24 (which
+//! MJPEG frames are decoded to) have plain scalar conversions written as loops over exact chunks
+//! that the compiler can vectorize. They take the stride of the source rows in bytes, as
+//! backends may pad rows.
+//!
+//! [yuyv_to_i420] converts each row with tight loops over exact chunks that the compiler
+//! auto-vectorizes, and on x86_64 with explicit SSE2 for the bulk of each row. With the `rayon`
+//! feature enabled, the `par_` variants split the image into horizontal bands of row pairs and
+//! convert them on the rayon thread pool. All paths produce output bit-identical to
+//! [yuyv_to_i420_reference]. `cargo bench --bench convert` compares their speed.
+
+use anyhow::{bail, Result};
+
+/// Size in bytes of an I420 frame of the given resolution.
+pub fn i420_len(width: usize, height: usize) -> usize {
+    width * height + 2 * (width / 2) * (height / 2)
+}
+
+fn check_yuyv_args(yuyv: &[u8], dest: &[u8], width: usize, height: usize) -> Result<()> {
+    if width == 0 || height == 0 || width & 1 != 0 || height & 1 != 0 {
+        bail!(
+            "width and height must be even and nonzero, got {}x{}",
+            width,
+            height
+        );
+    }
+    if yuyv.len() < width * height * 2 {
+        bail!(
+            "YUYV buffer is too small: {} < {}",
+            yuyv.len(),
+            width * height * 2
+        );
+    }
+    if dest.len() < i420_len(width, height) {
+        bail!(
+            "destination buffer is too small: {} < {}",
+            dest.len(),
+            i420_len(width, height)
+        );
+    }
+    Ok(())
+}
+
+/// Splits an I420 buffer into its Y, U and V planes.
+fn i420_planes(dest: &mut [u8], width: usize, height: usize) -> (&mut [u8], &mut [u8], &mut [u8]) {
+    let (y_plane, uv_plane) = dest[..i420_len(width, height)].split_at_mut(width * height);
+    let (u_plane, v_plane) = uv_plane.split_at_mut(uv_plane.len() / 2);
+    (y_plane, u_plane, v_plane)
+}
+
+/// Converts one pair of YUYV rows. Chroma is taken from the first row of the pair.
+#[inline]
+fn yuyv_row_pair_to_i420(
+    src: &[u8],
+    y_rows: &mut [u8],
+    u_row: &mut [u8],
+    v_row: &mut [u8],
+    width: usize,
+) {
+    let (first, second) = src.split_at(width * 2);
+    let (y_first, y_second) = y_rows.split_at_mut(width);
+    yuyv_row_to_yuv(first, y_first, u_row, v_row);
+    yuyv_row_to_y(second, y_second);
+}
+
+/// Splits a YUYV row into luma and chroma.
+#[inline]
+fn yuyv_row_to_yuv(src: &[u8], y_row: &mut [u8], u_row: &mut [u8], v_row: &mut [u8]) {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    let (src, y_row, u_row, v_row) = {
+        // SAFETY: SSE2 is enabled at compile time and the slices are checked by the caller.
+        let done = unsafe { sse2::yuyv_row_to_yuv(src, y_row, u_row, v_row) };
+        (
+            &src[done * 2..],
+            &mut y_row[done..],
+            &mut u_row[done / 2..],
+            &mut v_row[done / 2..],
+        )
+    };
+    // Iterating over exact chunks rather than indexing lets the compiler drop the bounds checks.
+    for (((px, y), u), v) in src
+        .chunks_exact(4)
+        .zip(y_row.chunks_exact_mut(2))
+        .zip(u_row.iter_mut())
+        .zip(v_row.iter_mut())
+    {
+        y[0] = px[0];
+        y[1] = px[2];
+        *u = px[1];
+        *v = px[3];
+    }
+}
+
+/// Extracts the luma of a YUYV row, for the rows whose chroma is dropped.
+#[inline]
+fn yuyv_row_to_y(src: &[u8], y_row: &mut [u8]) {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    let (src, y_row) = {
+        // SAFETY: SSE2 is enabled at compile time and the slices are checked by the caller.
+        let done = unsafe { sse2::yuyv_row_to_y(src, y_row) };
+        (&src[done * 2..], &mut y_row[done..])
+    };
+    for (px, y) in src.chunks_exact(2).zip(y_row.iter_mut()) {
+        *y = px[0];
+    }
+}
+
+/// SSE2 versions of the row conversions, which is part of the x86_64 baseline. They convert
+/// as many whole blocks of pixels as fit in the row and return how many pixels that was; the
+/// scalar loops finish the rest.
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+mod sse2 {
+    use std::arch::x86_64::*;
+
+    /// Pixels per iteration of [yuyv_row_to_yuv], i.e. 64 bytes of YUYV.
+    const YUV_BLOCK: usize = 32;
+    /// Pixels per iteration of [yuyv_row_to_y], i.e. 32 bytes of YUYV.
+    const Y_BLOCK: usize = 16;
+
+    /// # Safety
+    ///
+    /// `y_row` must hold `src.len() / 2` bytes and `u_row` and `v_row` `src.len() / 4` each.
+    #[inline]
+    pub(super) unsafe fn yuyv_row_to_yuv(
+        src: &[u8],
+        y_row: &mut [u8],
+        u_row: &mut [u8],
+        v_row: &mut [u8],
+    ) -> usize {
+        let pixels = src.len() / 2;
+        let blocks = pixels / YUV_BLOCK;
+        debug_assert!(y_row.len() >= blocks * YUV_BLOCK);
+        debug_assert!(
+            u_row.len() >= blocks * YUV_BLOCK / 2 && v_row.len() >= blocks * YUV_BLOCK / 2
+        );
+        let low_bytes = _mm_set1_epi16(0x00ff);
+        let src = src.as_ptr() as *const __m128i;
+        let y_row = y_row.as_mut_ptr() as *mut __m128i;
+        let u_row = u_row.as_mut_ptr() as *mut __m128i;
+        let v_row = v_row.as_mut_ptr() as *mut __m128i;
+        for block in 0..blocks {
+            // Each 16-byte load holds 8 pixels as Y U Y V Y U Y V ...
+            let a = _mm_loadu_si128(src.add(block * 4));
+            let b = _mm_loadu_si128(src.add(block * 4 + 1));
+            let c = _mm_loadu_si128(src.add(block * 4 + 2));
+            let d = _mm_loadu_si128(src.add(block * 4 + 3));
+            // Luma is in the even bytes.
+            let y_ab = _mm_packus_epi16(_mm_and_si128(a, low_bytes), _mm_and_si128(b, low_bytes));
+            let y_cd = _mm_packus_epi16(_mm_and_si128(c, low_bytes), _mm_and_si128(d, low_bytes));
+            _mm_storeu_si128(y_row.add(block * 2), y_ab);
+            _mm_storeu_si128(y_row.add(block * 2 + 1), y_cd);
+            // Chroma is in the odd bytes, as U V U V ...
+            let uv_ab = _mm_packus_epi16(_mm_srli_epi16(a, 8), _mm_srli_epi16(b, 8));
+            let uv_cd = _mm_packus_epi16(_mm_srli_epi16(c, 8), _mm_srli_epi16(d, 8));
+            let u = _mm_packus_epi16(
+                _mm_and_si128(uv_ab, low_bytes),
+                _mm_and_si128(uv_cd, low_bytes),
+            );
+            let v = _mm_packus_epi16(_mm_srli_epi16(uv_ab, 8), _mm_srli_epi16(uv_cd, 8));
+            _mm_storeu_si128(u_row.add(block), u);
+            _mm_storeu_si128(v_row.add(block), v);
+        }
+        blocks * YUV_BLOCK
+    }
+
+    /// # Safety
+    ///
+    /// `y_row` must hold `src.len() / 2` bytes.
+    #[inline]
+    pub(super) unsafe fn yuyv_row_to_y(src: &[u8], y_row: &mut [u8]) -> usize {
+        let blocks = src.len() / 2 / Y_BLOCK;
+        debug_assert!(y_row.len() >= blocks * Y_BLOCK);
+        let low_bytes = _mm_set1_epi16(0x00ff);
+        let src = src.as_ptr() as *const __m128i;
+        let y_row = y_row.as_mut_ptr() as *mut __m128i;
+        for block in 0..blocks {
+            let a = _mm_loadu_si128(src.add(block * 2));
+            let b = _mm_loadu_si128(src.add(block * 2 + 1));
+            let y = _mm_packus_epi16(_mm_and_si128(a, low_bytes), _mm_and_si128(b, low_bytes));
+            _mm_storeu_si128(y_row.add(block), y);
+        }
+        blocks * Y_BLOCK
+    }
+}
+
+/// Straightforward per-pixel conversion, which the optimized paths are tested and benchmarked
+/// against.
+pub fn yuyv_to_i420_reference(
+    yuyv: &[u8],
+    dest: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Result<()> {
+    check_yuyv_args(yuyv, dest, width, height)?;
+    let (y_plane, u_plane, v_plane) = i420_planes(dest, width, height);
+    for row in 0..height {
+        for x in 0..width {
+            y_plane[row * width + x] = yuyv[(row * width + x) * 2];
+        }
+        if row % 2 == 0 {
+            for x in 0..width / 2 {
+                let px = (row * width + 2 * x) * 2;
+                u_plane[row / 2 * width / 2 + x] = yuyv[px + 1];
+                v_plane[row / 2 * width / 2 + x] = yuyv[px + 3];
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Converts a packed YUYV (4:2:2) frame into planar I420 (4:2:0) in `dest`.
+pub fn yuyv_to_i420(yuyv: &[u8], dest: &mut [u8], width: usize, height: usize) -> Result<()> {
+    check_yuyv_args(yuyv, dest, width, height)?;
+    let (y_plane, u_plane, v_plane) = i420_planes(dest, width, height);
+    for (((src, y_rows), u_row), v_row) in yuyv
+        .chunks_exact(width * 4)
+        .zip(y_plane.chunks_exact_mut(width * 2))
+        .zip(u_plane.chunks_exact_mut(width / 2))
+        .zip(v_plane.chunks_exact_mut(width / 2))
+    {
+        yuyv_row_pair_to_i420(src, y_rows, u_row, v_row, width);
+    }
+    Ok(())
+}
+
+/// Checks the arguments of the strided conversions, whose source has `rows` rows of `row_bytes`
+/// bytes `stride` apart.
+fn check_args(
+    src: &[u8],
+    stride: usize,
+    row_bytes: usize,
+    rows: usize,
+    dest: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<()> {
+    if width == 0 || height == 0 || width & 1 != 0 || height & 1 != 0 {
+        bail!(
+            "width and height must be even and nonzero, got {}x{}",
+            width,
+            height
+        );
+    }
+    if stride < row_bytes {
+        bail!("stride is too small: {} < {}", stride, row_bytes);
+    }
+    // The last row needn't be padded.
+    let src_len = if rows == 0 {
+        0
+    } else {
+        stride * (rows - 1) + row_bytes
+    };
+    if src.len() < src_len {
+        bail!("source buffer is too small: {} < {}", src.len(), src_len);
+    }
+    if dest.len() < i420_len(width, height) {
+        bail!(
+            "destination buffer is too small: {} < {}",
+            dest.len(),
+            i420_len(width, height)
+        );
+    }
+    Ok(())
+}
+
+/// Converts a semi-planar NV12 (4:2:0) frame into planar I420 in `dest`. The interleaved UV
+/// plane follows the Y plane, both with rows `stride` bytes apart.
+pub fn nv12_to_i420(
+    nv12: &[u8],
+    stride: usize,
+    dest: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Result<()> {
+    check_args(nv12, stride, width, height * 3 / 2, dest, width, height)?;
+    let (y_plane, u_plane, v_plane) = i420_planes(dest, width, height);
+    for (src, y_row) in nv12.chunks(stride).zip(y_plane.chunks_exact_mut(width)) {
+        y_row.copy_from_slice(&src[..width]);
+    }
+    for ((src, u_row), v_row) in nv12[stride * height..]
+        .chunks(stride)
+        .zip(u_plane.chunks_exact_mut(width / 2))
+        .zip(v_plane.chunks_exact_mut(width / 2))
+    {
+        nv12_uv_row_to_i420(&src[..width], u_row, v_row);
+    }
+    Ok(())
+}
+
+/// Splits one row of interleaved NV12 chroma into its U and V rows.
+#[inline]
+fn nv12_uv_row_to_i420(uv_row: &[u8], u_row: &mut [u8], v_row: &mut [u8]) {
+    for ((uv, u), v) in uv_row
+        .chunks_exact(2)
+        .zip(u_row.iter_mut())
+        .zip(v_row.iter_mut())
+    {
+        *u = uv[0];
+        *v = uv[1];
+    }
+}
+
+/// Converts a packed BGR24 frame, with rows `stride` bytes apart, into planar I420 in `dest`.
+pub fn bgr24_to_i420(
+    bgr: &[u8],
+    stride: usize,
+    dest: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Result<()> {
+    check_args(bgr, stride, width * 3, height, dest, width, height)?;
+    rgb_to_i420::<2, 1, 0>(bgr, stride, dest, width, height);
+    Ok(())
+}
+
+/// Converts a packed RGB24 frame, with rows `stride` bytes apart, into planar I420 in `dest`.
+pub fn rgb24_to_i420(
+    rgb: &[u8],
+    stride: usize,
+    dest: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Result<()> {
+    check_args(rgb, stride, width * 3, height, dest, width, height)?;
+    rgb_to_i420::<0, 1, 2>(rgb, stride, dest, width, height);
+    Ok(())
+}
+
+/// Converts packed 24-bit RGB with the channels at offsets `R`, `G` and `B` of each pixel, using
+/// BT.601 limited range like the camera YUV formats. Chroma is the average of each 2x2 block.
+fn rgb_to_i420<const R: usize, const G: usize, const B: usize>(
+    src: &[u8],
+    stride: usize,
+    dest: &mut [u8],
+    width: usize,
+    height: usize,
+) {
+    let (y_plane, u_plane, v_plane) = i420_planes(dest, width, height);
+    let y = |px: &[u8]| {
+        let (r, g, b) = (px[R] as i32, px[G] as i32, px[B] as i32);
+        (((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8
+    };
+    for (((rows, y_rows), u_row), v_row) in src
+        .chunks(stride * 2)
+        .zip(y_plane.chunks_exact_mut(width * 2))
+        .zip(u_plane.chunks_exact_mut(width / 2))
+        .zip(v_plane.chunks_exact_mut(width / 2))
+    {
+        let (first, second) = (&rows[..width * 3], &rows[stride..stride + width * 3]);
+        let (y_first, y_second) = y_rows.split_at_mut(width);
+        for (((((a, b), y_a), y_b), u), v) in first
+            .chunks_exact(6)
+            .zip(second.chunks_exact(6))
+            .zip(y_first.chunks_exact_mut(2))
+            .zip(y_second.chunks_exact_mut(2))
+            .zip(u_row.iter_mut())
+            .zip(v_row.iter_mut())
+        {
+            y_a[0] = y(&a[..3]);
+            y_a[1] = y(&a[3..]);
+            y_b[0] = y(&b[..3]);
+            y_b[1] = y(&b[3..]);
+            let sum = |channel: usize| {
+                (a[channel] as i32
+                    + a[channel + 3] as i32
+                    + b[channel] as i32
+                    + b[channel + 3] as i32
+                    + 2)
+                    / 4
+            };
+            let (r, g, b) = (sum(R), sum(G), sum(B));
+            *u = (((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8;
+            *v = (((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8;
+        }
+    }
+}
+
+/// Same as [yuyv_to_i420], with the row pairs converted in parallel.
+#[cfg(feature = "rayon")]
+pub fn par_yuyv_to_i420(yuyv: &[u8], dest: &mut [u8], width: usize, height: usize) -> Result<()> {
+    use rayon::prelude::*;
+
+    check_yuyv_args(yuyv, dest, width, height)?;
+    let (y_plane, u_plane, v_plane) = i420_planes(dest, width, height);
+    yuyv[..width * height * 2]
+        .par_chunks_exact(width * 4)
+        .zip(y_plane.par_chunks_exact_mut(width * 2))
+        .zip(u_plane.par_chunks_exact_mut(width / 2))
+        .zip(v_plane.par_chunks_exact_mut(width / 2))
+        // Keep bands large enough that scheduling doesn't dominate the per-row work.
+        .with_min_len(16)
+        .for_each(|(((src, y_rows), u_row), v_row)| {
+            yuyv_row_pair_to_i420(src, y_rows, u_row, v_row, width);
+        });
+    Ok(())
+}
+
+/// Same as [nv12_to_i420], with the rows converted in parallel.
+#[cfg(feature = "rayon")]
+pub fn par_nv12_to_i420(
+    nv12: &[u8],
+    stride: usize,
+    dest: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Result<()> {
+    use rayon::prelude::*;
+
+    check_args(nv12, stride, width, height * 3 / 2, dest, width, height)?;
+    let (y_plane, u_plane, v_plane) = i420_planes(dest, width, height);
+    let (y_src, uv_src) = nv12.split_at(stride * height);
+    y_src
+        .par_chunks(stride)
+        .zip(y_plane.par_chunks_exact_mut(width))
+        // Rows are only copied, so bands need to be larger than for YUYV to be worth scheduling.
+        .with_min_len(64)
+        .for_each(|(src, y_row)| y_row.copy_from_slice(&src[..width]));
+    uv_src
+        .par_chunks(stride)
+        .zip(u_plane.par_chunks_exact_mut(width / 2))
+        .zip(v_plane.par_chunks_exact_mut(width / 2))
+        .with_min_len(32)
+        .for_each(|((src, u_row), v_row)| nv12_uv_row_to_i420(&src[..width], u_row, v_row));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_frame(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height * 2)
+            .map(|i| (i * 7 + i / 13) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn yuyv_to_i420_small_frame() {
+        // 4x2 frame: Y values 1..8, chroma of the second row is dropped.
+        let yuyv = [
+            1, 10, 2, 11, 3, 20, 4, 21, //
+            5, 30, 6, 31, 7, 40, 8, 41,
+        ];
+        let mut dest = vec![0u8; i420_len(4, 2)];
+        yuyv_to_i420(&yuyv, &mut dest, 4, 2).unwrap();
+        assert_eq!(dest, [1, 2, 3, 4, 5, 6, 7, 8, 10, 20, 11, 21]);
+    }
+
+    #[test]
+    fn yuyv_to_i420_rejects_bad_sizes() {
+        let mut dest = vec![0u8; i420_len(4, 2)];
+        assert!(yuyv_to_i420(&[0u8; 16], &mut dest, 3, 2).is_err());
+        assert!(yuyv_to_i420(&[0u8; 16], &mut dest, 0, 2).is_err());
+        assert!(yuyv_to_i420(&[0u8; 16], &mut dest, 4, 0).is_err());
+        assert!(yuyv_to_i420(&[0u8; 8], &mut dest, 4, 2).is_err());
+        assert!(yuyv_to_i420(&[0u8; 16], &mut dest[..8], 4, 2).is_err());
+    }
+
+    #[test]
+    fn nv12_to_i420_small_frame() {
+        // 4x2 frame with 2 bytes of padding per row.
+        let nv12 = [
+            1, 2, 3, 4, 0, 0, //
+            5, 6, 7, 8, 0, 0, //
+            10, 11, 20, 21,
+        ];
+        let mut dest = vec![0u8; i420_len(4, 2)];
+        nv12_to_i420(&nv12, 6, &mut dest, 4, 2).unwrap();
+        assert_eq!(dest, [1, 2, 3, 4, 5, 6, 7, 8, 10, 20, 11, 21]);
+        assert!(nv12_to_i420(&nv12, 3, &mut dest, 4, 2).is_err());
+        assert!(nv12_to_i420(&nv12[..15], 6, &mut dest, 4, 2).is_err());
+        assert!(nv12_to_i420(&nv12, 6, &mut dest, 0, 2).is_err());
+        assert!(nv12_to_i420(&nv12, 6, &mut dest, 4, 0).is_err());
+    }
+
+    #[test]
+    fn rgb_to_i420_small_frame() {
+        // 2x2 frames of white, black, red and blue pixels, with chroma averaged over the block.
+        let rgb = [
+            255, 255, 255, 0, 0, 0, 0, 0, //
+            255, 0, 0, 0, 0, 255, 0, 0,
+        ];
+        let bgr = [
+            255, 255, 255, 0, 0, 0, 0, 0, //
+            0, 0, 255, 255, 0, 0, 0, 0,
+        ];
+        let expected = [235, 16, 82, 41, 147, 152];
+        let mut dest = vec![0u8; i420_len(2, 2)];
+        rgb24_to_i420(&rgb, 8, &mut dest, 2, 2).unwrap();
+        assert_eq!(dest, expected);
+        bgr24_to_i420(&bgr, 8, &mut dest, 2, 2).unwrap();
+        assert_eq!(dest, expected);
+        assert!(rgb24_to_i420(&rgb[..13], 8, &mut dest, 2, 2).is_err());
+        assert!(rgb24_to_i420(&rgb, 8, &mut dest, 0, 2).is_err());
+    }
+
+    #[test]
+    fn yuyv_to_i420_matches_reference() {
+        // Widths around the SSE2 block sizes exercise the scalar tails.
+        for (width, height) in [
+            (2, 2),
+            (30, 2),
+            (32, 4),
+            (34, 2),
+            (94, 6),
+            (640, 480),
+            (1282, 722),
+        ] {
+            let yuyv = test_frame(width, height);
+            let mut reference = vec![0u8; i420_len(width, height)];
+            let mut fast = vec![0u8; i420_len(width, height)];
+            yuyv_to_i420_reference(&yuyv, &mut reference, width, height).unwrap();
+            yuyv_to_i420(&yuyv, &mut fast, width, height).unwrap();
+            assert_eq!(reference, fast, "{}x{}", width, height);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_yuyv_to_i420_matches_scalar() {
+        for (width, height) in [(2, 2), (640, 480), (1920, 1080), (1282, 722)] {
+            let yuyv = test_frame(width, height);
+            let mut scalar = vec![0u8; i420_len(width, height)];
+            let mut parallel = vec![0u8; i420_len(width, height)];
+            yuyv_to_i420(&yuyv, &mut scalar, width, height).unwrap();
+            par_yuyv_to_i420(&yuyv, &mut parallel, width, height).unwrap();
+            assert_eq!(scalar, parallel, "{}x{}", width, height);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_nv12_to_i420_matches_scalar() {
+        for (width, height) in [(2, 2), (640, 480), (1920, 1080), (1282, 722)] {
+            // Padded rows, except for the last one.
+            let stride = width + 16;
+            let nv12 = test_frame(stride, height);
+            let nv12 = &nv12[..stride * (height * 3 / 2 - 1) + width];
+            let mut scalar = vec![0u8; i420_len(width, height)];
+            let mut parallel = vec![0u8; i420_len(width, height)];
+            nv12_to_i420(nv12, stride, &mut scalar, width, height).unwrap();
+            par_nv12_to_i420(nv12, stride, &mut parallel, width, height).unwrap();
+            assert_eq!(scalar, parallel, "{}x{}", width, height);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! DSCP (Differentiated Services) marking of the daemon's QUIC traffic.
+//!
+//! Networks that honor DSCP can prioritize marked packets under contention, e.g. `EF`
+//! (expedited forwarding) for real-time audio or `AF41` for interactive video. Elsewhere the
+//! marking is stripped or ignored, which is harmless.
+//!
+//! quinn sets the whole IP TOS byte of every packet it sends to its ECN bits, which would
+//! clear a DSCP set on its socket. [DscpSocket] therefore sends the packets itself from a socket
+//! marked with the DSCP and gives up ECN. The marking applies per socket, not per packet: audio
+//! and video share one QUIC connection, so they get the same class.
+//!
+//! Supported on Linux, macOS and the BSDs. Windows accepts the socket option but generally
+//! doesn't apply it unless a QoS policy allows the process to.
+
+use anyhow::{bail, Result};
+use quinn::udp::{RecvMeta, Transmit, UdpState};
+use quinn::AsyncUdpSocket;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::fmt;
+use std::io::{self, IoSliceMut};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::task::{ready, Context, Poll};
+use tokio::io::ReadBuf;
+
+/// A 6-bit DSCP value, parsed from a name like `EF`, `AF41` or `CS1`, or from a number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dscp(u8);
+
+const NAMED: [(&str, u8); 21] = [
+    ("CS0", 0),
+    ("CS1", 8),
+    ("AF11", 10),
+    ("AF12", 12),
+    ("AF13", 14),
+    ("CS2", 16),
+    ("AF21", 18),
+    ("AF22", 20),
+    ("AF23", 22),
+    ("CS3", 24),
+    ("AF31", 26),
+    ("AF32", 28),
+    ("AF33", 30),
+    ("CS4", 32),
+    ("AF41", 34),
+    ("AF42", 36),
+    ("AF43", 38),
+    ("CS5", 40),
+    ("EF", 46),
+    ("CS6", 48),
+    ("CS7", 56),
+];
+
+impl Dscp {
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// The DSCP in the upper six bits of the IPv4 TOS / IPv6 traffic class byte.
+    fn tos(&self) -> u32 {
+        (self.0 as u32) << 2
+    }
+}
+
+impl FromStr for Dscp {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some((_, value)) = NAMED.iter().find(|(name, _)| name.eq_ignore_ascii_case(s)) {
+            return Ok(Dscp(*value));
+        }
+        match s.parse::<u8>() {
+            Ok(value) if value < 64 => Ok(Dscp(value)),
+            _ => bail!(
+                "invalid DSCP {:?}, expected a name like EF or AF41, or 0-63",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Dscp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match NAMED.iter().find(|(_, value)| *value == self.0) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// A UDP socket for quinn whose packets all carry the same DSCP, see the module documentation.
+#[derive(Debug)]
+pub struct DscpSocket {
+    io: tokio::net::UdpSocket,
+}
+
+impl DscpSocket {
+    /// Binds an unspecified local address of the same family as `remote` and marks it with
+    /// `dscp`. Must be called from within a tokio runtime.
+    pub fn bind_for(remote: SocketAddr, dscp: Dscp) -> io::Result<Self> {
+        let (domain, local): (Domain, SocketAddr) = if remote.is_ipv4() {
+            (Domain::IPV4, "0.0.0.0:0".parse().unwrap())
+        } else {
+            (Domain::IPV6, "[::]:0".parse().unwrap())
+        };
+        let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))?;
+        if remote.is_ipv4() {
+            socket.set_tos(dscp.tos())?;
+        } else {
+            set_traffic_class(&socket, dscp)?;
+        }
+        socket.set_nonblocking(true)?;
+        socket.bind(&local.into())?;
+        let io = tokio::net::UdpSocket::from_std(socket.into())?;
+        Ok(Self { io })
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+fn set_traffic_class(socket: &Socket, dscp: Dscp) -> io::Result<()> {
+    socket.set_tclass_v6(dscp.tos())
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+fn set_traffic_class(_socket: &Socket, _dscp: Dscp) -> io::Result<()> {
+    tracing::warn!("DSCP marking of IPv6 traffic is not supported on this platform");
+    Ok(())
+}
+
+impl AsyncUdpSocket for DscpSocket {
+    fn poll_send(
+        &self,
+        _state: &UdpState,
+        cx: &mut Context,
+        transmits: &[Transmit],
+    ) -> Poll<io::Result<usize>> {
+        let mut sent = 0;
+        for transmit in transmits {
+            // Transmits may hold several datagrams when quinn detected segmentation offload.
+            let segment_size = transmit.segment_size.unwrap_or(transmit.contents.len());
+            for (segment, datagram) in transmit.contents.chunks(segment_size.max(1)).enumerate() {
+                match self.io.poll_send_to(cx, datagram, transmit.destination) {
+                    Poll::Ready(Ok(_)) => {}
+                    // Like quinn's own socket, drop datagrams that fail to send instead of
+                    // failing the endpoint. QUIC recovers them as lost packets.
+                    Poll::Ready(Err(e)) => tracing::debug!("failed to send datagram: {}", e),
+                    // quinn retries whole transmits, which would send the segments that went out
+                    // twice, so a partly sent transmit counts as sent and the rest is dropped.
+                    Poll::Pending if segment > 0 => return Poll::Ready(Ok(sent + 1)),
+                    // Report what went out; quinn retries the rest.
+                    Poll::Pending if sent > 0 => return Poll::Ready(Ok(sent)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            sent += 1;
+        }
+        Poll::Ready(Ok(sent))
+    }
+
+    fn poll_recv(
+        &self,
+        cx: &mut Context,
+        bufs: &mut [IoSliceMut<'_>],
+        meta: &mut [RecvMeta],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = ReadBuf::new(&mut bufs[0]);
+        let addr = ready!(self.io.poll_recv_from(cx, &mut buf))?;
+        let len = buf.filled().len();
+        meta[0] = RecvMeta {
+            addr,
+            len,
+            stride: len,
+            ecn: None,
+            dst_ip: None,
+        };
+        Poll::Ready(Ok(1))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.local_addr()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_names_and_numbers() {
+        assert_eq!("EF".parse::<Dscp>().unwrap(), Dscp(46));
+        assert_eq!("af41".parse::<Dscp>().unwrap(), Dscp(34));
+        assert_eq!("10".parse::<Dscp>().unwrap(), Dscp(10));
+        assert!("64".parse::<Dscp>().is_err());
+        assert!("AF44".parse::<Dscp>().is_err());
+        assert_eq!(Dscp(46).to_string(), "EF");
+        assert_eq!(Dscp(5).to_string(), "5");
+    }
+
+    #[tokio::test]
+    async fn marks_the_socket() {
+        let remote: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        let socket = DscpSocket::bind_for(remote, Dscp(46)).unwrap();
+        let tos = socket2::SockRef::from(&socket.io).tos().unwrap();
+        assert_eq!(tos, 46 << 2);
+    }
+
+    #[tokio::test]
+    async fn sends_segmented_transmits_as_separate_datagrams() {
+        let receiver = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let destination = receiver.local_addr().unwrap();
+        let socket = DscpSocket::bind_for(destination, Dscp(34)).unwrap();
+        let transmit = Transmit {
+            destination,
+            ecn: None,
+            contents: b"abcdef".to_vec().into(),
+            segment_size: Some(4),
+            src_ip: None,
+        };
+        let state = UdpState::new();
+        let sent = std::future::poll_fn(|cx| {
+            socket.poll_send(&state, cx, std::slice::from_ref(&transmit))
+        })
+        .await
+        .unwrap();
+        assert_eq!(sent, 1);
+
+        let mut buf = [0u8; 16];
+        let len = receiver.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"abcd");
+        let len = receiver.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"ef");
+    }
+}
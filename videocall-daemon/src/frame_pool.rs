@@ -0,0 +1,93 @@
+//! Recycles frame buffers between the camera and encoder threads so that capturing doesn't
+//! allocate a new frame every time.
+
+use std::sync::{Arc, Mutex};
+
+/// A bounded pool of buffers of a fixed frame size.
+///
+/// Returning buffers is optional: [take](Self::take) allocates when the pool is empty, and a
+/// buffer that is never [put](Self::put) back is simply freed.
+#[derive(Clone, Debug)]
+pub struct FramePool {
+    buffers: Arc<Mutex<Vec<Vec<u8>>>>,
+    frame_size: usize,
+    capacity: usize,
+}
+
+impl FramePool {
+    /// Creates an empty pool holding at most `capacity` buffers of `frame_size` bytes.
+    pub fn new(frame_size: usize, capacity: usize) -> Self {
+        Self {
+            buffers: Arc::new(Mutex::new(Vec::with_capacity(capacity))),
+            frame_size,
+            capacity,
+        }
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// Takes a buffer of `frame_size` bytes from the pool, allocating one if the pool is empty.
+    /// The contents of a recycled buffer are whatever the previous frame left in it.
+    pub fn take(&self) -> Vec<u8> {
+        self.buffers
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| vec![0u8; self.frame_size])
+    }
+
+    /// Hands a buffer back to the pool.  It is dropped if the pool is full or if its size doesn't
+    /// match the pool's frame size.
+    pub fn put(&self, buffer: Vec<u8>) {
+        if buffer.len() != self.frame_size {
+            return;
+        }
+        let mut buffers = self.buffers.lock().unwrap();
+        if buffers.len() < self.capacity {
+            buffers.push(buffer);
+        }
+    }
+
+    /// Number of buffers currently available in the pool.
+    pub fn available(&self) -> usize {
+        self.buffers.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recycles_buffers() {
+        let pool = FramePool::new(16, 2);
+        let mut buffer = pool.take();
+        assert_eq!(buffer.len(), 16);
+        buffer[0] = 42;
+        let ptr = buffer.as_ptr();
+        pool.put(buffer);
+        assert_eq!(pool.available(), 1);
+        let buffer = pool.take();
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert_eq!(buffer[0], 42);
+        assert_eq!(pool.available(), 0);
+    }
+
+    #[test]
+    fn is_bounded() {
+        let pool = FramePool::new(16, 2);
+        for _ in 0..3 {
+            pool.put(vec![0u8; 16]);
+        }
+        assert_eq!(pool.available(), 2);
+    }
+
+    #[test]
+    fn drops_buffers_of_the_wrong_size() {
+        let pool = FramePool::new(16, 2);
+        pool.put(vec![0u8; 8]);
+        assert_eq!(pool.available(), 0);
+    }
+}
@@ -0,0 +1,102 @@
+//! Dropping captured frames to send at a lower frame rate than the camera captures at, for when
+//! the camera doesn't offer the wanted rate or a lower rate saves bandwidth.
+
+// Credit of the first frame, and after a gap in capture. Three quarters of a frame keeps the frame
+// and puts the decisions that follow a quarter frame away from the threshold at ratios like 2:1,
+// where starting from a whole frame would leave every other one exactly on it.
+const INITIAL_CREDIT: f64 = 0.75;
+
+/// Decides which captured frames to keep to send at a target frame rate.
+#[derive(Debug)]
+pub struct FrameDecimator {
+    target_fps: f64,
+    credit: f64,
+    last_capture_ms: Option<u128>,
+}
+
+impl FrameDecimator {
+    pub fn new(target_fps: u32) -> Self {
+        Self {
+            target_fps: target_fps as f64,
+            credit: 0.0,
+            last_capture_ms: None,
+        }
+    }
+
+    /// Whether to keep the frame captured at `capture_ms`, in milliseconds on any clock that
+    /// doesn't go backwards. The first frame is always kept.
+    ///
+    /// Credit accrues for the time between captures at the target rate, and a frame is kept once
+    /// half a frame's worth has built up, spending a whole frame's. Rounding to the nearest frame
+    /// rather than waiting for a whole one keeps the kept frames evenly spaced when capture times
+    /// jitter, and the remainder carries over, so the rate is exact over any longer window.
+    pub fn keep(&mut self, capture_ms: u128) -> bool {
+        self.credit = match self.last_capture_ms {
+            Some(last) => {
+                let elapsed = capture_ms.saturating_sub(last) as f64 / 1000.0;
+                let earned = elapsed * self.target_fps;
+                // Start over after a gap of a whole frame or more, so a stall in capture isn't
+                // followed by a burst.
+                if earned >= 1.0 {
+                    INITIAL_CREDIT
+                } else {
+                    self.credit + earned
+                }
+            }
+            None => INITIAL_CREDIT,
+        };
+        self.last_capture_ms = Some(capture_ms);
+        if self.credit >= 0.5 {
+            self.credit -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Capture times of `count` frames at 30fps, alternately a millisecond early and late.
+    fn jittery_30fps(count: u128) -> impl Iterator<Item = u128> {
+        (0..count).map(|i| i * 1000 / 30 + 10 + i % 2 * 2 - 1)
+    }
+
+    fn kept(target_fps: u32, captures: impl Iterator<Item = u128>) -> Vec<bool> {
+        let mut decimator = FrameDecimator::new(target_fps);
+        captures.map(|capture| decimator.keep(capture)).collect()
+    }
+
+    #[test]
+    fn halves_the_frame_rate_evenly() {
+        // Every other frame, despite the jitter.
+        let kept = kept(15, jittery_30fps(300));
+        assert!(kept.chunks(2).all(|pair| pair == [true, false]));
+    }
+
+    #[test]
+    fn keeps_the_target_rate_over_time() {
+        for (target_fps, expected) in [(10, 100), (24, 240), (30, 300), (60, 300)] {
+            let kept = kept(target_fps, jittery_30fps(300));
+            let count = kept.iter().filter(|keep| **keep).count() as i64;
+            assert!(
+                (count - expected).abs() <= 1,
+                "{} fps: {} frames",
+                target_fps,
+                count
+            );
+        }
+    }
+
+    #[test]
+    fn no_burst_after_a_stall() {
+        let mut decimator = FrameDecimator::new(15);
+        assert!(decimator.keep(0));
+        // Nothing captured for a second, then frames at 30fps again.
+        assert!(decimator.keep(1000));
+        assert!(!decimator.keep(1033));
+        assert!(decimator.keep(1067));
+    }
+}
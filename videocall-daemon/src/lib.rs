@@ -1,5 +1,12 @@
 pub mod camera;
+pub mod camera_controls;
+pub mod camera_format;
+pub mod camera_id;
+pub mod convert;
+pub mod dscp;
 pub mod fake_cert_verifier;
+pub mod frame_pool;
+pub mod frame_rate;
 pub mod microphone;
 pub mod quic;
 pub mod video_encoder;
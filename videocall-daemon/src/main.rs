@@ -1,6 +1,7 @@
 use clap::Parser;
 mod modes;
 
+use modes::controls::controls;
 use modes::info::get_info;
 use modes::stream::stream;
 use tracing::info;
@@ -49,10 +50,13 @@ async fn main() {
 
     match opt.mode {
         Mode::Streaming(s) => {
-            stream(s).await;
+            stream(*s).await;
         }
         Mode::Info(i) => {
             get_info(i).await;
         }
+        Mode::Controls(c) => {
+            controls(c).await;
+        }
     };
 }
@@ -0,0 +1,23 @@
+use videocall_daemon::{
+    camera_controls::{list_controls, set_control},
+    quic::Controls,
+};
+
+pub async fn controls(opt: Controls) {
+    let result = match opt {
+        Controls::List { device } => list_controls(&device.0).map(|controls| {
+            for control in controls {
+                println!("{}", control);
+            }
+        }),
+        Controls::Set {
+            device,
+            control,
+            value,
+        } => set_control(&device.0, &control, &value),
+    };
+    if let Err(e) = result {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
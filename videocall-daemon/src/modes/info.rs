@@ -1,5 +1,75 @@
-use videocall_daemon::quic::Info;
+use anyhow::{bail, Result};
+use nokhwa::utils::ApiBackend;
+use serde::Serialize;
+use std::cmp::Reverse;
+use videocall_daemon::{
+    camera::camera_formats, camera_format::FormatDescription, camera_id::CameraId, quic::Info,
+};
 
-pub async fn get_info(_info: Info) {
-    panic!("Not implemented yet");
+#[derive(Serialize)]
+struct CameraDescription {
+    id: String,
+    name: String,
+    description: String,
+}
+
+pub async fn get_info(info: Info) {
+    if let Err(e) = print_info(&info) {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn print_info(info: &Info) -> Result<()> {
+    if info.list_cameras {
+        let cameras = nokhwa::query(ApiBackend::Auto)?;
+        if info.json {
+            let cameras: Vec<CameraDescription> = cameras
+                .iter()
+                .map(|camera| CameraDescription {
+                    id: CameraId(camera.index().clone()).to_string(),
+                    name: camera.human_name(),
+                    description: camera.description().to_string(),
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&cameras)?);
+        } else {
+            for camera in cameras {
+                println!(
+                    "{}: {}",
+                    CameraId(camera.index().clone()),
+                    camera.human_name()
+                );
+            }
+        }
+    }
+    if let Some(device) = &info.list_formats {
+        let mut formats = camera_formats(&device.0)?;
+        formats.sort_by_key(|format| {
+            (
+                format.format().to_string(),
+                Reverse(format.width() * format.height()),
+                Reverse(format.frame_rate()),
+            )
+        });
+        if info.json {
+            let formats: Vec<FormatDescription> =
+                formats.iter().map(FormatDescription::from).collect();
+            println!("{}", serde_json::to_string_pretty(&formats)?);
+        } else {
+            for format in formats {
+                println!(
+                    "{} {}x{} @ {}fps",
+                    format.format(),
+                    format.width(),
+                    format.height(),
+                    format.frame_rate()
+                );
+            }
+        }
+    }
+    if info.list_resolutions.is_some() {
+        bail!("--list-resolutions is not implemented yet");
+    }
+    Ok(())
 }
@@ -1,2 +1,3 @@
+pub mod controls;
 pub mod info;
 pub mod stream;
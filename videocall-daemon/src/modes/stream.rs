@@ -1,40 +1,100 @@
+use anyhow::Result;
+use nokhwa::utils::{CameraFormat, CameraIndex, FrameFormat};
 use tokio::sync::mpsc::channel;
 use videocall_daemon::{
-    camera::{CameraConfig, CameraDaemon},
+    camera::{closest_camera_format, default_camera_format, CameraConfig, CameraDaemon},
     microphone::MicrophoneDaemon,
     quic::{Client, Streaming},
 };
 
 pub async fn stream(opt: Streaming) {
-    // Parse resolution
-    let resolution: Vec<&str> = opt.resolution.split('x').collect();
-    if resolution.len() != 2 {
-        panic!("invalid resolution: {}", opt.resolution);
-    }
-    let width = resolution[0].parse::<u32>().expect("invalid width");
-    let height = resolution[1].parse::<u32>().expect("invalid height");
-    let framerate = opt.fps;
-    // validate framerate
-    if framerate != 10 && framerate != 15 && framerate != 30 && framerate != 60 {
-        panic!("invalid framerate: {}", framerate);
-    }
+    let requested = match (&opt.resolution, opt.fps) {
+        (Some(resolution_arg), Some(framerate)) => {
+            // Parse resolution
+            let resolution: Vec<&str> = resolution_arg.split('x').collect();
+            if resolution.len() != 2 {
+                panic!("invalid resolution: {}", resolution_arg);
+            }
+            let width = resolution[0].parse::<u32>().expect("invalid width");
+            let height = resolution[1].parse::<u32>().expect("invalid height");
+            // validate framerate
+            if !opt.closest_format
+                && framerate != 10
+                && framerate != 15
+                && framerate != 30
+                && framerate != 60
+            {
+                panic!("invalid framerate: {}", framerate);
+            }
+            Some((width, height, framerate))
+        }
+        _ => None,
+    };
+    let closest_format = opt.closest_format;
+    let target_fps = opt.target_fps;
+    let camera_config = |video_device: CameraIndex| -> Result<CameraConfig> {
+        let format = match requested {
+            Some((width, height, framerate)) if closest_format => {
+                let format = closest_camera_format(&video_device, width, height, framerate)?;
+                tracing::info!("using camera format {} for camera {}", format, video_device);
+                format
+            }
+            Some((width, height, framerate)) => {
+                CameraFormat::new_from(width, height, FrameFormat::YUYV, framerate)
+            }
+            None => {
+                let format = default_camera_format(&video_device)?;
+                tracing::info!("using camera format {} for camera {}", format, video_device);
+                format
+            }
+        };
+        Ok(CameraConfig {
+            width: format.width(),
+            height: format.height(),
+            framerate: format.frame_rate(),
+            frame_format: format.format(),
+            video_device,
+            target_fps,
+        })
+    };
+    let video_device = opt.video_device().expect("failed to find the camera");
+    let main_camera_config = camera_config(video_device).expect("failed to choose a camera format");
+    let extra_cameras: Vec<_> = opt
+        .extra_cameras
+        .iter()
+        .map(|camera| (camera.clone(), opt.extra_camera_user_id(camera)))
+        .collect();
     let user_id = opt.user_id.clone();
     let meeting_id = opt.meeting_id.clone();
-    let video_device_index = opt.video_device_index;
     let audio_device = opt.audio_device.clone();
     let mut client = Client::new(opt);
     client.connect().await.expect("failed to connect");
 
-    let camera_config = CameraConfig {
-        width,
-        height,
-        framerate,
-        frame_format: nokhwa::utils::FrameFormat::YUYV,
-        video_device_index,
-    };
     let (quic_tx, mut quic_rx) = channel::<Vec<u8>>(10);
-    let mut camera = CameraDaemon::from_config(camera_config, user_id.clone(), quic_tx.clone());
+    let mut camera =
+        CameraDaemon::from_config(main_camera_config, user_id.clone(), quic_tx.clone());
     camera.start().expect("failed to start camera");
+    // Extra cameras are best effort: one that fails is reported and the others keep streaming.
+    let mut cameras = vec![camera];
+    for (extra_camera, extra_user_id) in extra_cameras {
+        let started = camera_config(extra_camera.device.0).and_then(|config| {
+            let mut camera =
+                CameraDaemon::from_config(config, extra_user_id.clone(), quic_tx.clone());
+            camera.start()?;
+            Ok(camera)
+        });
+        match started {
+            Ok(camera) => {
+                tracing::info!(
+                    "streaming camera {} as {}",
+                    extra_camera.label,
+                    extra_user_id
+                );
+                cameras.push(camera);
+            }
+            Err(e) => tracing::error!("failed to start camera {}: {}", extra_camera.label, e),
+        }
+    }
     let mut microphone = MicrophoneDaemon::default();
     if let Some(audio_device) = audio_device {
         microphone
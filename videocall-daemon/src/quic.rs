@@ -1,9 +1,12 @@
 use std::sync::Arc;
 
+use crate::camera_id::{find_camera_by_name, CameraId, LabeledCamera};
+use crate::dscp::{Dscp, DscpSocket};
 use anyhow::Error;
 use clap::{Args, Parser, Subcommand};
+use nokhwa::utils::{ApiBackend, CameraIndex};
 use protobuf::Message;
-use quinn::Connection;
+use quinn::{Connection, Endpoint, EndpointConfig, TokioRuntime};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::{
     sync::mpsc::{self, Sender},
@@ -32,10 +35,14 @@ pub struct Opt {
 #[derive(Subcommand, Debug)]
 pub enum Mode {
     /// Streaming mode with all the current options.
-    Streaming(Streaming),
+    Streaming(Box<Streaming>),
 
     /// Information mode to list cameras, formats, and resolutions.
     Info(Info),
+
+    /// List or set camera controls such as brightness, exposure and zoom.
+    #[clap(subcommand)]
+    Controls(Controls),
 }
 
 #[derive(Args, Debug)]
@@ -54,19 +61,62 @@ pub struct Streaming {
     #[clap(long = "meeting-id")]
     pub meeting_id: String,
 
-    #[clap(long = "video-device-index")]
-    pub video_device_index: usize,
+    /// Camera to stream: its index, or its device id. Prefix numeric device ids with `id:`.
+    #[clap(
+        long = "video-device-index",
+        required_unless_present = "camera_name",
+        conflicts_with = "camera_name"
+    )]
+    pub video_device_index: Option<CameraId>,
+
+    /// Camera to stream, by part of its name, e.g. "brio" for a Logitech BRIO, ignoring case.
+    /// Fails if no camera or more than one matches.
+    #[clap(long = "camera-name")]
+    pub camera_name: Option<String>,
+
+    /// Another camera to stream along with the main one, as LABEL=DEVICE (e.g. side=1). Can be
+    /// repeated. Each one joins the meeting as its own participant, named <user-id>-<label>.
+    #[clap(long = "extra-camera")]
+    pub extra_cameras: Vec<LabeledCamera>,
 
     #[clap(long = "audio-device")]
     pub audio_device: Option<String>,
 
-    /// Resolution in WIDTHxHEIGHT format (e.g., 1920x1080)
-    #[clap(long = "resolution")]
-    pub resolution: String,
+    /// Resolution in WIDTHxHEIGHT format (e.g., 1920x1080). Chosen from the camera's
+    /// capabilities when omitted together with --fps.
+    #[clap(long = "resolution", requires = "fps")]
+    pub resolution: Option<String>,
 
     /// Frames per second (e.g. 10, 30, 60)
-    #[clap(long = "fps")]
-    pub fps: u32,
+    #[clap(long = "fps", requires = "resolution")]
+    pub fps: Option<u32>,
+
+    /// Use the camera format closest to --resolution and --fps when the camera doesn't offer
+    /// exactly that, instead of failing.
+    #[clap(long = "closest-format", requires = "resolution")]
+    pub closest_format: bool,
+
+    /// Frames per second to send, when lower than the camera captures at. Captured frames are
+    /// dropped evenly to reach it, e.g. every other one to send 15 fps from a 30 fps camera.
+    #[clap(long = "target-fps", value_parser = clap::value_parser!(u32).range(1..))]
+    pub target_fps: Option<u32>,
+
+    /// Seconds to wait for the server to complete the handshake before giving up.
+    #[clap(long = "connect-timeout", default_value_t = 10)]
+    pub connect_timeout: u64,
+
+    /// DSCP to mark all packets with, as a name (e.g. EF, AF41) or a number (0-63). Audio and
+    /// video share the connection, so they get the same class. Linux, macOS and BSD only.
+    #[clap(long = "dscp")]
+    pub dscp: Option<Dscp>,
+}
+
+/// Errors of [Client::connect] that callers may want to handle, e.g. to fall back to another
+/// server.  Other failures are reported as plain [anyhow] errors.
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectError {
+    #[error("connection attempt timed out after {0:?}")]
+    Timeout(Duration),
 }
 
 #[derive(Args, Debug)]
@@ -75,13 +125,58 @@ pub struct Info {
     #[clap(long = "list-cameras")]
     pub list_cameras: bool,
 
-    /// List supported formats for a specific camera.
+    /// List supported formats for a specific camera: its index, or its device id.
     #[clap(long = "list-formats")]
-    pub list_formats: Option<usize>,
+    pub list_formats: Option<CameraId>,
 
     /// List supported resolutions for a specific camera and format.
     #[clap(long = "list-resolutions")]
     pub list_resolutions: Option<String>, // Camera index and format string
+
+    /// Print the lists as JSON, e.g. for scripts or to paste into bug reports.
+    #[clap(long = "json")]
+    pub json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Controls {
+    /// List the controls of a camera with their current values, ranges and flags.
+    List {
+        /// Camera: its index, or its device id. Prefix numeric device ids with `id:`.
+        #[clap(long = "device")]
+        device: CameraId,
+    },
+
+    /// Set a control of a camera, e.g. `--control brightness --value 128`.
+    Set {
+        /// Camera: its index, or its device id. Prefix numeric device ids with `id:`.
+        #[clap(long = "device")]
+        device: CameraId,
+
+        /// Control name, e.g. brightness, exposure, zoom or white-balance.
+        #[clap(long = "control")]
+        control: String,
+
+        /// New value: a number, true/false for switches, or one of the listed possible values.
+        #[clap(long = "value", allow_hyphen_values = true)]
+        value: String,
+    },
+}
+
+impl Streaming {
+    /// The camera to stream, looked up by name if given with `--camera-name`.
+    pub fn video_device(&self) -> anyhow::Result<CameraIndex> {
+        match (&self.video_device_index, &self.camera_name) {
+            (Some(id), _) => Ok(id.0.clone()),
+            (None, Some(name)) => find_camera_by_name(&nokhwa::query(ApiBackend::Auto)?, name),
+            (None, None) => Err(Error::msg("no camera given")),
+        }
+    }
+
+    /// The user id the extra camera `camera` streams as.
+    pub fn extra_camera_user_id(&self, camera: &LabeledCamera) -> String {
+        format!("{}-{}", self.user_id, camera.label)
+    }
 }
 
 pub struct Client {
@@ -97,6 +192,9 @@ impl Client {
         }
     }
 
+    /// Connects to the server, waiting at most
+    /// [`connect_timeout`](Streaming::connect_timeout) seconds for the handshake, in which case
+    /// the error is a [ConnectError::Timeout].  Dropping the returned future cancels the attempt.
     pub async fn connect(&mut self) -> anyhow::Result<()> {
         let conn = connect_to_server(&self.options).await?;
         let (tx, mut rx) = mpsc::channel::<Vec<u8>>(100);
@@ -158,9 +256,18 @@ impl Client {
         }
     }
 
+    /// Sends heartbeats for the user and each extra camera, which receivers treat as separate
+    /// participants.
     async fn start_heartbeat(&self, conn: Connection, options: &Streaming) {
         let interval = time::interval(Duration::from_secs(1));
-        let email = options.user_id.clone();
+        let emails: Vec<String> = std::iter::once(options.user_id.clone())
+            .chain(
+                options
+                    .extra_cameras
+                    .iter()
+                    .map(|camera| options.extra_camera_user_id(camera)),
+            )
+            .collect();
         tokio::spawn(async move {
             let mut interval = interval;
             loop {
@@ -169,22 +276,24 @@ impl Client {
                     .expect("Time went backwards")
                     .as_millis(); // Get milliseconds since Unix epoch
                 interval.tick().await;
-                let actual_heartbeat = MediaPacket {
-                    media_type: MediaType::HEARTBEAT.into(),
-                    email: email.clone(),
-                    timestamp: now_ms as f64,
-                    ..Default::default()
-                };
-
-                let packet = PacketWrapper {
-                    email: email.clone(),
-                    packet_type: PacketType::MEDIA.into(),
-                    data: actual_heartbeat.write_to_bytes().unwrap(),
-                    ..Default::default()
-                };
-                let data = packet.write_to_bytes().unwrap();
-                if let Err(e) = Self::send(conn.clone(), data).await {
-                    tracing::error!("Failed to send heartbeat: {}", e);
+                for email in &emails {
+                    let actual_heartbeat = MediaPacket {
+                        media_type: MediaType::HEARTBEAT.into(),
+                        email: email.clone(),
+                        timestamp: now_ms as f64,
+                        ..Default::default()
+                    };
+
+                    let packet = PacketWrapper {
+                        email: email.clone(),
+                        packet_type: PacketType::MEDIA.into(),
+                        data: actual_heartbeat.write_to_bytes().unwrap(),
+                        ..Default::default()
+                    };
+                    let data = packet.write_to_bytes().unwrap();
+                    if let Err(e) = Self::send(conn.clone(), data).await {
+                        tracing::error!("Failed to send heartbeat: {}", e);
+                    }
                 }
             }
         });
@@ -221,12 +330,20 @@ async fn connect_to_server(options: &Streaming) -> anyhow::Result<Connection> {
         let client_config = quinn::ClientConfig::new(Arc::new(client_crypto));
         let host = options.url.host_str();
 
-        match quinn::Endpoint::client("[::]:0".parse().unwrap()) {
+        match create_endpoint(*remote, options.dscp) {
             Ok(mut endpoint) => {
                 endpoint.set_default_client_config(client_config);
                 match endpoint.connect(*remote, host.unwrap()) {
                     Ok(conn) => {
-                        let conn = conn.await?;
+                        let timeout = Duration::from_secs(options.connect_timeout);
+                        let conn = match time::timeout(timeout, conn).await {
+                            Ok(conn) => conn?,
+                            Err(_) => {
+                                // Abandon the handshake instead of letting it run in the background.
+                                endpoint.close(0u32.into(), b"connect timeout");
+                                return Err(ConnectError::Timeout(timeout).into());
+                            }
+                        };
                         info!("Connected successfully");
                         return Ok(conn);
                     }
@@ -243,3 +360,18 @@ async fn connect_to_server(options: &Streaming) -> anyhow::Result<Connection> {
         }
     }
 }
+
+fn create_endpoint(remote: std::net::SocketAddr, dscp: Option<Dscp>) -> std::io::Result<Endpoint> {
+    match dscp {
+        Some(dscp) => {
+            info!("Marking packets with DSCP {}", dscp);
+            Endpoint::new_with_abstract_socket(
+                EndpointConfig::default(),
+                None,
+                DscpSocket::bind_for(remote, dscp)?,
+                Arc::new(TokioRuntime),
+            )
+        }
+        None => Endpoint::client("[::]:0".parse().unwrap()),
+    }
+}
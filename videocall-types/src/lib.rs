@@ -6,10 +6,20 @@ use yew_websocket::websocket::{Binary, Text};
 impl std::fmt::Display for protos::media_packet::media_packet::MediaType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            protos::media_packet::media_packet::MediaType::MEDIA_TYPE_UNSPECIFIED => {
+                write!(f, "unspecified")
+            }
             protos::media_packet::media_packet::MediaType::AUDIO => write!(f, "audio"),
             protos::media_packet::media_packet::MediaType::VIDEO => write!(f, "video"),
             protos::media_packet::media_packet::MediaType::SCREEN => write!(f, "screen"),
             protos::media_packet::media_packet::MediaType::HEARTBEAT => write!(f, "heartbeat"),
+            protos::media_packet::media_packet::MediaType::DATA => write!(f, "data"),
+            protos::media_packet::media_packet::MediaType::SCREEN_AUDIO => {
+                write!(f, "screen_audio")
+            }
+            protos::media_packet::media_packet::MediaType::KEYFRAME_REQUEST => {
+                write!(f, "keyframe_request")
+            }
         }
     }
 }
@@ -17,6 +27,9 @@ impl std::fmt::Display for protos::media_packet::media_packet::MediaType {
 impl std::fmt::Display for protos::packet_wrapper::packet_wrapper::PacketType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
+            protos::packet_wrapper::packet_wrapper::PacketType::PACKET_TYPE_UNSPECIFIED => {
+                write!(f, "PACKET_TYPE_UNSPECIFIED")
+            }
             protos::packet_wrapper::packet_wrapper::PacketType::AES_KEY => write!(f, "AES_KEY"),
             protos::packet_wrapper::packet_wrapper::PacketType::RSA_PUB_KEY => {
                 write!(f, "RSA_PUB_KEY")
@@ -25,6 +38,9 @@ impl std::fmt::Display for protos::packet_wrapper::packet_wrapper::PacketType {
             protos::packet_wrapper::packet_wrapper::PacketType::CONNECTION => {
                 write!(f, "CONNECTION")
             }
+            protos::packet_wrapper::packet_wrapper::PacketType::CONGESTION => {
+                write!(f, "CONGESTION")
+            }
         }
     }
 }
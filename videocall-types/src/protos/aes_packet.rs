@@ -32,6 +32,9 @@ pub struct AesPacket {
     pub key: ::std::vec::Vec<u8>,
     // @@protoc_insertion_point(field:AesPacket.iv)
     pub iv: ::std::vec::Vec<u8>,
+    ///  Starts at 0 and increases each time the sender rotates its key.
+    // @@protoc_insertion_point(field:AesPacket.generation)
+    pub generation: u32,
     // special fields
     // @@protoc_insertion_point(special_field:AesPacket.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -49,7 +52,7 @@ impl AesPacket {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(2);
+        let mut fields = ::std::vec::Vec::with_capacity(3);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "key",
@@ -61,6 +64,11 @@ impl AesPacket {
             |m: &AesPacket| { &m.iv },
             |m: &mut AesPacket| { &mut m.iv },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "generation",
+            |m: &AesPacket| { &m.generation },
+            |m: &mut AesPacket| { &mut m.generation },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<AesPacket>(
             "AesPacket",
             fields,
@@ -85,6 +93,9 @@ impl ::protobuf::Message for AesPacket {
                 18 => {
                     self.iv = is.read_bytes()?;
                 },
+                24 => {
+                    self.generation = is.read_uint32()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -103,6 +114,9 @@ impl ::protobuf::Message for AesPacket {
         if !self.iv.is_empty() {
             my_size += ::protobuf::rt::bytes_size(2, &self.iv);
         }
+        if self.generation != 0 {
+            my_size += ::protobuf::rt::uint32_size(3, self.generation);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
@@ -115,6 +129,9 @@ impl ::protobuf::Message for AesPacket {
         if !self.iv.is_empty() {
             os.write_bytes(2, &self.iv)?;
         }
+        if self.generation != 0 {
+            os.write_uint32(3, self.generation)?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -134,6 +151,7 @@ impl ::protobuf::Message for AesPacket {
     fn clear(&mut self) {
         self.key.clear();
         self.iv.clear();
+        self.generation = 0;
         self.special_fields.clear();
     }
 
@@ -141,6 +159,7 @@ impl ::protobuf::Message for AesPacket {
         static instance: AesPacket = AesPacket {
             key: ::std::vec::Vec::new(),
             iv: ::std::vec::Vec::new(),
+            generation: 0,
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -165,16 +184,21 @@ impl ::protobuf::reflect::ProtobufValue for AesPacket {
 }
 
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x16types/aes_packet.proto\"-\n\tAesPacket\x12\x10\n\x03key\x18\x01\
-    \x20\x01(\x0cR\x03key\x12\x0e\n\x02iv\x18\x02\x20\x01(\x0cR\x02ivJ\x98\
-    \x01\n\x06\x12\x04\0\0\x05\x01\n\x08\n\x01\x0c\x12\x03\0\0\x12\n\n\n\x02\
-    \x04\0\x12\x04\x02\0\x05\x01\n\n\n\x03\x04\0\x01\x12\x03\x02\x08\x11\n\
-    \x0b\n\x04\x04\0\x02\0\x12\x03\x03\x02\x10\n\x0c\n\x05\x04\0\x02\0\x05\
-    \x12\x03\x03\x02\x07\n\x0c\n\x05\x04\0\x02\0\x01\x12\x03\x03\x08\x0b\n\
-    \x0c\n\x05\x04\0\x02\0\x03\x12\x03\x03\x0e\x0f\n\x0b\n\x04\x04\0\x02\x01\
-    \x12\x03\x04\x02\x0f\n\x0c\n\x05\x04\0\x02\x01\x05\x12\x03\x04\x02\x07\n\
-    \x0c\n\x05\x04\0\x02\x01\x01\x12\x03\x04\x08\n\n\x0c\n\x05\x04\0\x02\x01\
-    \x03\x12\x03\x04\r\x0eb\x06proto3\
+    \n\x16types/aes_packet.proto\"M\n\tAesPacket\x12\x10\n\x03key\x18\x01\
+    \x20\x01(\x0cR\x03key\x12\x0e\n\x02iv\x18\x02\x20\x01(\x0cR\x02iv\x12\
+    \x1e\n\ngeneration\x18\x03\x20\x01(\rR\ngenerationJ\x92\x02\n\x06\x12\
+    \x04\0\0\x07\x01\n\x08\n\x01\x0c\x12\x03\0\0\x12\n\n\n\x02\x04\0\x12\x04\
+    \x02\0\x07\x01\n\n\n\x03\x04\0\x01\x12\x03\x02\x08\x11\n\x0b\n\x04\x04\0\
+    \x02\0\x12\x03\x03\x02\x10\n\x0c\n\x05\x04\0\x02\0\x05\x12\x03\x03\x02\
+    \x07\n\x0c\n\x05\x04\0\x02\0\x01\x12\x03\x03\x08\x0b\n\x0c\n\x05\x04\0\
+    \x02\0\x03\x12\x03\x03\x0e\x0f\n\x0b\n\x04\x04\0\x02\x01\x12\x03\x04\x02\
+    \x0f\n\x0c\n\x05\x04\0\x02\x01\x05\x12\x03\x04\x02\x07\n\x0c\n\x05\x04\0\
+    \x02\x01\x01\x12\x03\x04\x08\n\n\x0c\n\x05\x04\0\x02\x01\x03\x12\x03\x04\
+    \r\x0e\nN\n\x04\x04\0\x02\x02\x12\x03\x06\x02\x18\x1aA\x20Starts\x20at\
+    \x200\x20and\x20increases\x20each\x20time\x20the\x20sender\x20rotates\
+    \x20its\x20key.\n\n\x0c\n\x05\x04\0\x02\x02\x05\x12\x03\x06\x02\x08\n\
+    \x0c\n\x05\x04\0\x02\x02\x01\x12\x03\x06\t\x13\n\x0c\n\x05\x04\0\x02\x02\
+    \x03\x12\x03\x06\x16\x17b\x06proto3\
 ";
 
 /// `FileDescriptorProto` object which was a source for this generated file
@@ -0,0 +1,220 @@
+// This file is generated by rust-protobuf 3.7.1. Do not edit
+// .proto file is parsed by protoc --rs_out=...
+// @generated
+
+// https://github.com/rust-lang/rust-clippy/issues/702
+#![allow(unknown_lints)]
+#![allow(clippy::all)]
+
+#![allow(unused_attributes)]
+#![cfg_attr(rustfmt, rustfmt::skip)]
+
+#![allow(dead_code)]
+#![allow(missing_docs)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(trivial_casts)]
+#![allow(unused_results)]
+#![allow(unused_mut)]
+
+//! Generated file from `types/congestion_packet.proto`
+
+/// Generated files are compatible only with the same version
+/// of protobuf runtime.
+const _PROTOBUF_VERSION_CHECK: () = ::protobuf::VERSION_3_7_1;
+
+///  Sent by the server when a receiver's connection falls behind on the media of a sender.  It is
+///  relayed to the whole room and only acted on by the sender it names, which caps its video
+///  bitrate accordingly.
+// @@protoc_insertion_point(message:CongestionPacket)
+#[derive(PartialEq,Clone,Default,Debug)]
+pub struct CongestionPacket {
+    // message fields
+    ///  Email of the sender to slow down.
+    // @@protoc_insertion_point(field:CongestionPacket.target)
+    pub target: ::std::string::String,
+    ///  Video bitrate in bits per second that the receiver's connection can take from the sender.
+    // @@protoc_insertion_point(field:CongestionPacket.max_bitrate)
+    pub max_bitrate: u32,
+    // special fields
+    // @@protoc_insertion_point(special_field:CongestionPacket.special_fields)
+    pub special_fields: ::protobuf::SpecialFields,
+}
+
+impl<'a> ::std::default::Default for &'a CongestionPacket {
+    fn default() -> &'a CongestionPacket {
+        <CongestionPacket as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl CongestionPacket {
+    pub fn new() -> CongestionPacket {
+        ::std::default::Default::default()
+    }
+
+    fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
+        let mut fields = ::std::vec::Vec::with_capacity(2);
+        let mut oneofs = ::std::vec::Vec::with_capacity(0);
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "target",
+            |m: &CongestionPacket| { &m.target },
+            |m: &mut CongestionPacket| { &mut m.target },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "max_bitrate",
+            |m: &CongestionPacket| { &m.max_bitrate },
+            |m: &mut CongestionPacket| { &mut m.max_bitrate },
+        ));
+        ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<CongestionPacket>(
+            "CongestionPacket",
+            fields,
+            oneofs,
+        )
+    }
+}
+
+impl ::protobuf::Message for CongestionPacket {
+    const NAME: &'static str = "CongestionPacket";
+
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::Result<()> {
+        while let Some(tag) = is.read_raw_tag_or_eof()? {
+            match tag {
+                10 => {
+                    self.target = is.read_string()?;
+                },
+                16 => {
+                    self.max_bitrate = is.read_uint32()?;
+                },
+                tag => {
+                    ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u64 {
+        let mut my_size = 0;
+        if !self.target.is_empty() {
+            my_size += ::protobuf::rt::string_size(1, &self.target);
+        }
+        if self.max_bitrate != 0 {
+            my_size += ::protobuf::rt::uint32_size(2, self.max_bitrate);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
+        self.special_fields.cached_size().set(my_size as u32);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::Result<()> {
+        if !self.target.is_empty() {
+            os.write_string(1, &self.target)?;
+        }
+        if self.max_bitrate != 0 {
+            os.write_uint32(2, self.max_bitrate)?;
+        }
+        os.write_unknown_fields(self.special_fields.unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn special_fields(&self) -> &::protobuf::SpecialFields {
+        &self.special_fields
+    }
+
+    fn mut_special_fields(&mut self) -> &mut ::protobuf::SpecialFields {
+        &mut self.special_fields
+    }
+
+    fn new() -> CongestionPacket {
+        CongestionPacket::new()
+    }
+
+    fn clear(&mut self) {
+        self.target.clear();
+        self.max_bitrate = 0;
+        self.special_fields.clear();
+    }
+
+    fn default_instance() -> &'static CongestionPacket {
+        static instance: CongestionPacket = CongestionPacket {
+            target: ::std::string::String::new(),
+            max_bitrate: 0,
+            special_fields: ::protobuf::SpecialFields::new(),
+        };
+        &instance
+    }
+}
+
+impl ::protobuf::MessageFull for CongestionPacket {
+    fn descriptor() -> ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::Lazy::new();
+        descriptor.get(|| file_descriptor().message_by_package_relative_name("CongestionPacket").unwrap()).clone()
+    }
+}
+
+impl ::std::fmt::Display for CongestionPacket {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for CongestionPacket {
+    type RuntimeType = ::protobuf::reflect::rt::RuntimeTypeMessage<Self>;
+}
+
+static file_descriptor_proto_data: &'static [u8] = b"\
+    \n\x1dtypes/congestion_packet.proto\"K\n\x10CongestionPacket\x12\x16\n\
+    \x06target\x18\x01\x20\x01(\tR\x06target\x12\x1f\n\x0bmax_bitrate\x18\
+    \x02\x20\x01(\rR\nmaxBitrateJ\xed\x03\n\x06\x12\x04\0\0\n\x01\n\x08\n\
+    \x01\x0c\x12\x03\0\0\x12\n\xdc\x01\n\x02\x04\0\x12\x04\x05\0\n\x01\x1a\
+    \xcf\x01\x20Sent\x20by\x20the\x20server\x20when\x20a\x20receiver's\x20co\
+    nnection\x20falls\x20behind\x20on\x20the\x20media\x20of\x20a\x20sender.\
+    \x20\x20It\x20is\n\x20relayed\x20to\x20the\x20whole\x20room\x20and\x20on\
+    ly\x20acted\x20on\x20by\x20the\x20sender\x20it\x20names,\x20which\x20cap\
+    s\x20its\x20video\n\x20bitrate\x20accordingly.\n\n\n\n\x03\x04\0\x01\x12\
+    \x03\x05\x08\x18\n0\n\x04\x04\0\x02\0\x12\x03\x07\x02\x14\x1a#\x20Email\
+    \x20of\x20the\x20sender\x20to\x20slow\x20down.\n\n\x0c\n\x05\x04\0\x02\0\
+    \x05\x12\x03\x07\x02\x08\n\x0c\n\x05\x04\0\x02\0\x01\x12\x03\x07\t\x0f\n\
+    \x0c\n\x05\x04\0\x02\0\x03\x12\x03\x07\x12\x13\nh\n\x04\x04\0\x02\x01\
+    \x12\x03\t\x02\x19\x1a[\x20Video\x20bitrate\x20in\x20bits\x20per\x20seco\
+    nd\x20that\x20the\x20receiver's\x20connection\x20can\x20take\x20from\x20\
+    the\x20sender.\n\n\x0c\n\x05\x04\0\x02\x01\x05\x12\x03\t\x02\x08\n\x0c\n\
+    \x05\x04\0\x02\x01\x01\x12\x03\t\t\x14\n\x0c\n\x05\x04\0\x02\x01\x03\x12\
+    \x03\t\x17\x18b\x06proto3\
+";
+
+/// `FileDescriptorProto` object which was a source for this generated file
+fn file_descriptor_proto() -> &'static ::protobuf::descriptor::FileDescriptorProto {
+    static file_descriptor_proto_lazy: ::protobuf::rt::Lazy<::protobuf::descriptor::FileDescriptorProto> = ::protobuf::rt::Lazy::new();
+    file_descriptor_proto_lazy.get(|| {
+        ::protobuf::Message::parse_from_bytes(file_descriptor_proto_data).unwrap()
+    })
+}
+
+/// `FileDescriptor` object which allows dynamic access to files
+pub fn file_descriptor() -> &'static ::protobuf::reflect::FileDescriptor {
+    static generated_file_descriptor_lazy: ::protobuf::rt::Lazy<::protobuf::reflect::GeneratedFileDescriptor> = ::protobuf::rt::Lazy::new();
+    static file_descriptor: ::protobuf::rt::Lazy<::protobuf::reflect::FileDescriptor> = ::protobuf::rt::Lazy::new();
+    file_descriptor.get(|| {
+        let generated_file_descriptor = generated_file_descriptor_lazy.get(|| {
+            let mut deps = ::std::vec::Vec::with_capacity(0);
+            let mut messages = ::std::vec::Vec::with_capacity(1);
+            messages.push(CongestionPacket::generated_message_descriptor_data());
+            let mut enums = ::std::vec::Vec::with_capacity(0);
+            ::protobuf::reflect::GeneratedFileDescriptor::new_generated(
+                file_descriptor_proto(),
+                deps,
+                messages,
+                enums,
+            )
+        });
+        ::protobuf::reflect::FileDescriptor::new_generated_2(generated_file_descriptor)
+    })
+}
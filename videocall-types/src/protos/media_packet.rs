@@ -44,6 +44,15 @@ pub struct MediaPacket {
     pub audio_metadata: ::protobuf::MessageField<AudioMetadata>,
     // @@protoc_insertion_point(field:MediaPacket.video_metadata)
     pub video_metadata: ::protobuf::MessageField<VideoMetadata>,
+    ///  Set when the sender stopped this track; carries no media data.
+    // @@protoc_insertion_point(field:MediaPacket.end_of_stream)
+    pub end_of_stream: bool,
+    // @@protoc_insertion_point(field:MediaPacket.heartbeat_metadata)
+    pub heartbeat_metadata: ::protobuf::MessageField<HeartbeatMetadata>,
+    // @@protoc_insertion_point(field:MediaPacket.app_data_metadata)
+    pub app_data_metadata: ::protobuf::MessageField<AppDataMetadata>,
+    // @@protoc_insertion_point(field:MediaPacket.keyframe_request_metadata)
+    pub keyframe_request_metadata: ::protobuf::MessageField<KeyframeRequestMetadata>,
     // special fields
     // @@protoc_insertion_point(special_field:MediaPacket.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -61,7 +70,7 @@ impl MediaPacket {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(8);
+        let mut fields = ::std::vec::Vec::with_capacity(12);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "media_type",
@@ -103,6 +112,26 @@ impl MediaPacket {
             |m: &MediaPacket| { &m.video_metadata },
             |m: &mut MediaPacket| { &mut m.video_metadata },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "end_of_stream",
+            |m: &MediaPacket| { &m.end_of_stream },
+            |m: &mut MediaPacket| { &mut m.end_of_stream },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_message_field_accessor::<_, HeartbeatMetadata>(
+            "heartbeat_metadata",
+            |m: &MediaPacket| { &m.heartbeat_metadata },
+            |m: &mut MediaPacket| { &mut m.heartbeat_metadata },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_message_field_accessor::<_, AppDataMetadata>(
+            "app_data_metadata",
+            |m: &MediaPacket| { &m.app_data_metadata },
+            |m: &mut MediaPacket| { &mut m.app_data_metadata },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_message_field_accessor::<_, KeyframeRequestMetadata>(
+            "keyframe_request_metadata",
+            |m: &MediaPacket| { &m.keyframe_request_metadata },
+            |m: &mut MediaPacket| { &mut m.keyframe_request_metadata },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<MediaPacket>(
             "MediaPacket",
             fields,
@@ -145,6 +174,18 @@ impl ::protobuf::Message for MediaPacket {
                 66 => {
                     ::protobuf::rt::read_singular_message_into_field(is, &mut self.video_metadata)?;
                 },
+                72 => {
+                    self.end_of_stream = is.read_bool()?;
+                },
+                82 => {
+                    ::protobuf::rt::read_singular_message_into_field(is, &mut self.heartbeat_metadata)?;
+                },
+                90 => {
+                    ::protobuf::rt::read_singular_message_into_field(is, &mut self.app_data_metadata)?;
+                },
+                98 => {
+                    ::protobuf::rt::read_singular_message_into_field(is, &mut self.keyframe_request_metadata)?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -157,7 +198,7 @@ impl ::protobuf::Message for MediaPacket {
     #[allow(unused_variables)]
     fn compute_size(&self) -> u64 {
         let mut my_size = 0;
-        if self.media_type != ::protobuf::EnumOrUnknown::new(media_packet::MediaType::VIDEO) {
+        if self.media_type != ::protobuf::EnumOrUnknown::new(media_packet::MediaType::MEDIA_TYPE_UNSPECIFIED) {
             my_size += ::protobuf::rt::int32_size(1, self.media_type.value());
         }
         if !self.email.is_empty() {
@@ -183,13 +224,28 @@ impl ::protobuf::Message for MediaPacket {
             let len = v.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint64_size(len) + len;
         }
+        if self.end_of_stream != false {
+            my_size += 1 + 1;
+        }
+        if let Some(v) = self.heartbeat_metadata.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint64_size(len) + len;
+        }
+        if let Some(v) = self.app_data_metadata.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint64_size(len) + len;
+        }
+        if let Some(v) = self.keyframe_request_metadata.as_ref() {
+            let len = v.compute_size();
+            my_size += 1 + ::protobuf::rt::compute_raw_varint64_size(len) + len;
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
     }
 
     fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::Result<()> {
-        if self.media_type != ::protobuf::EnumOrUnknown::new(media_packet::MediaType::VIDEO) {
+        if self.media_type != ::protobuf::EnumOrUnknown::new(media_packet::MediaType::MEDIA_TYPE_UNSPECIFIED) {
             os.write_enum(1, ::protobuf::EnumOrUnknown::value(&self.media_type))?;
         }
         if !self.email.is_empty() {
@@ -213,6 +269,18 @@ impl ::protobuf::Message for MediaPacket {
         if let Some(v) = self.video_metadata.as_ref() {
             ::protobuf::rt::write_message_field_with_cached_size(8, v, os)?;
         }
+        if self.end_of_stream != false {
+            os.write_bool(9, self.end_of_stream)?;
+        }
+        if let Some(v) = self.heartbeat_metadata.as_ref() {
+            ::protobuf::rt::write_message_field_with_cached_size(10, v, os)?;
+        }
+        if let Some(v) = self.app_data_metadata.as_ref() {
+            ::protobuf::rt::write_message_field_with_cached_size(11, v, os)?;
+        }
+        if let Some(v) = self.keyframe_request_metadata.as_ref() {
+            ::protobuf::rt::write_message_field_with_cached_size(12, v, os)?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -230,7 +298,7 @@ impl ::protobuf::Message for MediaPacket {
     }
 
     fn clear(&mut self) {
-        self.media_type = ::protobuf::EnumOrUnknown::new(media_packet::MediaType::VIDEO);
+        self.media_type = ::protobuf::EnumOrUnknown::new(media_packet::MediaType::MEDIA_TYPE_UNSPECIFIED);
         self.email.clear();
         self.data.clear();
         self.frame_type.clear();
@@ -238,6 +306,10 @@ impl ::protobuf::Message for MediaPacket {
         self.duration = 0.;
         self.audio_metadata.clear();
         self.video_metadata.clear();
+        self.end_of_stream = false;
+        self.heartbeat_metadata.clear();
+        self.app_data_metadata.clear();
+        self.keyframe_request_metadata.clear();
         self.special_fields.clear();
     }
 
@@ -251,6 +323,10 @@ impl ::protobuf::Message for MediaPacket {
             duration: 0.,
             audio_metadata: ::protobuf::MessageField::none(),
             video_metadata: ::protobuf::MessageField::none(),
+            end_of_stream: false,
+            heartbeat_metadata: ::protobuf::MessageField::none(),
+            app_data_metadata: ::protobuf::MessageField::none(),
+            keyframe_request_metadata: ::protobuf::MessageField::none(),
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -279,14 +355,22 @@ pub mod media_packet {
     #[derive(Clone,Copy,PartialEq,Eq,Debug,Hash)]
     // @@protoc_insertion_point(enum:MediaPacket.MediaType)
     pub enum MediaType {
+        // @@protoc_insertion_point(enum_value:MediaPacket.MediaType.MEDIA_TYPE_UNSPECIFIED)
+        MEDIA_TYPE_UNSPECIFIED = 0,
         // @@protoc_insertion_point(enum_value:MediaPacket.MediaType.VIDEO)
-        VIDEO = 0,
+        VIDEO = 1,
         // @@protoc_insertion_point(enum_value:MediaPacket.MediaType.AUDIO)
-        AUDIO = 1,
+        AUDIO = 2,
         // @@protoc_insertion_point(enum_value:MediaPacket.MediaType.SCREEN)
-        SCREEN = 2,
+        SCREEN = 3,
         // @@protoc_insertion_point(enum_value:MediaPacket.MediaType.HEARTBEAT)
-        HEARTBEAT = 3,
+        HEARTBEAT = 4,
+        // @@protoc_insertion_point(enum_value:MediaPacket.MediaType.DATA)
+        DATA = 5,
+        // @@protoc_insertion_point(enum_value:MediaPacket.MediaType.SCREEN_AUDIO)
+        SCREEN_AUDIO = 6,
+        // @@protoc_insertion_point(enum_value:MediaPacket.MediaType.KEYFRAME_REQUEST)
+        KEYFRAME_REQUEST = 7,
     }
 
     impl ::protobuf::Enum for MediaType {
@@ -298,29 +382,41 @@ pub mod media_packet {
 
         fn from_i32(value: i32) -> ::std::option::Option<MediaType> {
             match value {
-                0 => ::std::option::Option::Some(MediaType::VIDEO),
-                1 => ::std::option::Option::Some(MediaType::AUDIO),
-                2 => ::std::option::Option::Some(MediaType::SCREEN),
-                3 => ::std::option::Option::Some(MediaType::HEARTBEAT),
+                0 => ::std::option::Option::Some(MediaType::MEDIA_TYPE_UNSPECIFIED),
+                1 => ::std::option::Option::Some(MediaType::VIDEO),
+                2 => ::std::option::Option::Some(MediaType::AUDIO),
+                3 => ::std::option::Option::Some(MediaType::SCREEN),
+                4 => ::std::option::Option::Some(MediaType::HEARTBEAT),
+                5 => ::std::option::Option::Some(MediaType::DATA),
+                6 => ::std::option::Option::Some(MediaType::SCREEN_AUDIO),
+                7 => ::std::option::Option::Some(MediaType::KEYFRAME_REQUEST),
                 _ => ::std::option::Option::None
             }
         }
 
         fn from_str(str: &str) -> ::std::option::Option<MediaType> {
             match str {
+                "MEDIA_TYPE_UNSPECIFIED" => ::std::option::Option::Some(MediaType::MEDIA_TYPE_UNSPECIFIED),
                 "VIDEO" => ::std::option::Option::Some(MediaType::VIDEO),
                 "AUDIO" => ::std::option::Option::Some(MediaType::AUDIO),
                 "SCREEN" => ::std::option::Option::Some(MediaType::SCREEN),
                 "HEARTBEAT" => ::std::option::Option::Some(MediaType::HEARTBEAT),
+                "DATA" => ::std::option::Option::Some(MediaType::DATA),
+                "SCREEN_AUDIO" => ::std::option::Option::Some(MediaType::SCREEN_AUDIO),
+                "KEYFRAME_REQUEST" => ::std::option::Option::Some(MediaType::KEYFRAME_REQUEST),
                 _ => ::std::option::Option::None
             }
         }
 
         const VALUES: &'static [MediaType] = &[
+            MediaType::MEDIA_TYPE_UNSPECIFIED,
             MediaType::VIDEO,
             MediaType::AUDIO,
             MediaType::SCREEN,
             MediaType::HEARTBEAT,
+            MediaType::DATA,
+            MediaType::SCREEN_AUDIO,
+            MediaType::KEYFRAME_REQUEST,
         ];
     }
 
@@ -338,7 +434,7 @@ pub mod media_packet {
 
     impl ::std::default::Default for MediaType {
         fn default() -> Self {
-            MediaType::VIDEO
+            MediaType::MEDIA_TYPE_UNSPECIFIED
         }
     }
 
@@ -531,6 +627,9 @@ pub struct VideoMetadata {
     // message fields
     // @@protoc_insertion_point(field:VideoMetadata.sequence)
     pub sequence: u64,
+    ///  WebCodecs codec string the frame was encoded with; empty means vp09.00.10.08.
+    // @@protoc_insertion_point(field:VideoMetadata.codec)
+    pub codec: ::std::string::String,
     // special fields
     // @@protoc_insertion_point(special_field:VideoMetadata.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -548,13 +647,18 @@ impl VideoMetadata {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(1);
+        let mut fields = ::std::vec::Vec::with_capacity(2);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "sequence",
             |m: &VideoMetadata| { &m.sequence },
             |m: &mut VideoMetadata| { &mut m.sequence },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "codec",
+            |m: &VideoMetadata| { &m.codec },
+            |m: &mut VideoMetadata| { &mut m.codec },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<VideoMetadata>(
             "VideoMetadata",
             fields,
@@ -576,6 +680,9 @@ impl ::protobuf::Message for VideoMetadata {
                 8 => {
                     self.sequence = is.read_uint64()?;
                 },
+                18 => {
+                    self.codec = is.read_string()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -591,6 +698,9 @@ impl ::protobuf::Message for VideoMetadata {
         if self.sequence != 0 {
             my_size += ::protobuf::rt::uint64_size(1, self.sequence);
         }
+        if !self.codec.is_empty() {
+            my_size += ::protobuf::rt::string_size(2, &self.codec);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
@@ -600,6 +710,9 @@ impl ::protobuf::Message for VideoMetadata {
         if self.sequence != 0 {
             os.write_uint64(1, self.sequence)?;
         }
+        if !self.codec.is_empty() {
+            os.write_string(2, &self.codec)?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -618,12 +731,14 @@ impl ::protobuf::Message for VideoMetadata {
 
     fn clear(&mut self) {
         self.sequence = 0;
+        self.codec.clear();
         self.special_fields.clear();
     }
 
     fn default_instance() -> &'static VideoMetadata {
         static instance: VideoMetadata = VideoMetadata {
             sequence: 0,
+            codec: ::std::string::String::new(),
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -647,72 +762,627 @@ impl ::protobuf::reflect::ProtobufValue for VideoMetadata {
     type RuntimeType = ::protobuf::reflect::rt::RuntimeTypeMessage<Self>;
 }
 
+///  Presence state of the sender, carried by HEARTBEAT packets.
+// @@protoc_insertion_point(message:HeartbeatMetadata)
+#[derive(PartialEq,Clone,Default,Debug)]
+pub struct HeartbeatMetadata {
+    // message fields
+    // @@protoc_insertion_point(field:HeartbeatMetadata.audio_enabled)
+    pub audio_enabled: bool,
+    // @@protoc_insertion_point(field:HeartbeatMetadata.video_enabled)
+    pub video_enabled: bool,
+    // @@protoc_insertion_point(field:HeartbeatMetadata.screen_enabled)
+    pub screen_enabled: bool,
+    ///  WebCodecs codec strings of the video codecs the sender can decode.
+    // @@protoc_insertion_point(field:HeartbeatMetadata.video_codecs)
+    pub video_codecs: ::std::vec::Vec<::std::string::String>,
+    ///  32-bit FNV-1a hash of the sender's display name, so receivers can tell when it changes
+    ///  without it being sent every second. 0 when the sender has none.
+    // @@protoc_insertion_point(field:HeartbeatMetadata.display_name_hash)
+    pub display_name_hash: u32,
+    ///  Whether the sender's microphone currently picks up speech, as a hint for active speaker
+    ///  detection before its audio is decoded.
+    // @@protoc_insertion_point(field:HeartbeatMetadata.speaking)
+    pub speaking: bool,
+    // special fields
+    // @@protoc_insertion_point(special_field:HeartbeatMetadata.special_fields)
+    pub special_fields: ::protobuf::SpecialFields,
+}
+
+impl<'a> ::std::default::Default for &'a HeartbeatMetadata {
+    fn default() -> &'a HeartbeatMetadata {
+        <HeartbeatMetadata as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl HeartbeatMetadata {
+    pub fn new() -> HeartbeatMetadata {
+        ::std::default::Default::default()
+    }
+
+    fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
+        let mut fields = ::std::vec::Vec::with_capacity(6);
+        let mut oneofs = ::std::vec::Vec::with_capacity(0);
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "audio_enabled",
+            |m: &HeartbeatMetadata| { &m.audio_enabled },
+            |m: &mut HeartbeatMetadata| { &mut m.audio_enabled },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "video_enabled",
+            |m: &HeartbeatMetadata| { &m.video_enabled },
+            |m: &mut HeartbeatMetadata| { &mut m.video_enabled },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "screen_enabled",
+            |m: &HeartbeatMetadata| { &m.screen_enabled },
+            |m: &mut HeartbeatMetadata| { &mut m.screen_enabled },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_vec_simpler_accessor::<_, _>(
+            "video_codecs",
+            |m: &HeartbeatMetadata| { &m.video_codecs },
+            |m: &mut HeartbeatMetadata| { &mut m.video_codecs },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "display_name_hash",
+            |m: &HeartbeatMetadata| { &m.display_name_hash },
+            |m: &mut HeartbeatMetadata| { &mut m.display_name_hash },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "speaking",
+            |m: &HeartbeatMetadata| { &m.speaking },
+            |m: &mut HeartbeatMetadata| { &mut m.speaking },
+        ));
+        ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<HeartbeatMetadata>(
+            "HeartbeatMetadata",
+            fields,
+            oneofs,
+        )
+    }
+}
+
+impl ::protobuf::Message for HeartbeatMetadata {
+    const NAME: &'static str = "HeartbeatMetadata";
+
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::Result<()> {
+        while let Some(tag) = is.read_raw_tag_or_eof()? {
+            match tag {
+                8 => {
+                    self.audio_enabled = is.read_bool()?;
+                },
+                16 => {
+                    self.video_enabled = is.read_bool()?;
+                },
+                24 => {
+                    self.screen_enabled = is.read_bool()?;
+                },
+                34 => {
+                    self.video_codecs.push(is.read_string()?);
+                },
+                40 => {
+                    self.display_name_hash = is.read_uint32()?;
+                },
+                48 => {
+                    self.speaking = is.read_bool()?;
+                },
+                tag => {
+                    ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u64 {
+        let mut my_size = 0;
+        if self.audio_enabled != false {
+            my_size += 1 + 1;
+        }
+        if self.video_enabled != false {
+            my_size += 1 + 1;
+        }
+        if self.screen_enabled != false {
+            my_size += 1 + 1;
+        }
+        for value in &self.video_codecs {
+            my_size += ::protobuf::rt::string_size(4, &value);
+        };
+        if self.display_name_hash != 0 {
+            my_size += ::protobuf::rt::uint32_size(5, self.display_name_hash);
+        }
+        if self.speaking != false {
+            my_size += 1 + 1;
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
+        self.special_fields.cached_size().set(my_size as u32);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::Result<()> {
+        if self.audio_enabled != false {
+            os.write_bool(1, self.audio_enabled)?;
+        }
+        if self.video_enabled != false {
+            os.write_bool(2, self.video_enabled)?;
+        }
+        if self.screen_enabled != false {
+            os.write_bool(3, self.screen_enabled)?;
+        }
+        for v in &self.video_codecs {
+            os.write_string(4, &v)?;
+        };
+        if self.display_name_hash != 0 {
+            os.write_uint32(5, self.display_name_hash)?;
+        }
+        if self.speaking != false {
+            os.write_bool(6, self.speaking)?;
+        }
+        os.write_unknown_fields(self.special_fields.unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn special_fields(&self) -> &::protobuf::SpecialFields {
+        &self.special_fields
+    }
+
+    fn mut_special_fields(&mut self) -> &mut ::protobuf::SpecialFields {
+        &mut self.special_fields
+    }
+
+    fn new() -> HeartbeatMetadata {
+        HeartbeatMetadata::new()
+    }
+
+    fn clear(&mut self) {
+        self.audio_enabled = false;
+        self.video_enabled = false;
+        self.screen_enabled = false;
+        self.video_codecs.clear();
+        self.display_name_hash = 0;
+        self.speaking = false;
+        self.special_fields.clear();
+    }
+
+    fn default_instance() -> &'static HeartbeatMetadata {
+        static instance: HeartbeatMetadata = HeartbeatMetadata {
+            audio_enabled: false,
+            video_enabled: false,
+            screen_enabled: false,
+            video_codecs: ::std::vec::Vec::new(),
+            display_name_hash: 0,
+            speaking: false,
+            special_fields: ::protobuf::SpecialFields::new(),
+        };
+        &instance
+    }
+}
+
+impl ::protobuf::MessageFull for HeartbeatMetadata {
+    fn descriptor() -> ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::Lazy::new();
+        descriptor.get(|| file_descriptor().message_by_package_relative_name("HeartbeatMetadata").unwrap()).clone()
+    }
+}
+
+impl ::std::fmt::Display for HeartbeatMetadata {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for HeartbeatMetadata {
+    type RuntimeType = ::protobuf::reflect::rt::RuntimeTypeMessage<Self>;
+}
+
+///  Routing of DATA packets, whose payload is opaque to the client library.
+// @@protoc_insertion_point(message:AppDataMetadata)
+#[derive(PartialEq,Clone,Default,Debug)]
+pub struct AppDataMetadata {
+    // message fields
+    ///  Application-chosen channel number, so independent features can share the connection.
+    // @@protoc_insertion_point(field:AppDataMetadata.channel)
+    pub channel: u32,
+    // special fields
+    // @@protoc_insertion_point(special_field:AppDataMetadata.special_fields)
+    pub special_fields: ::protobuf::SpecialFields,
+}
+
+impl<'a> ::std::default::Default for &'a AppDataMetadata {
+    fn default() -> &'a AppDataMetadata {
+        <AppDataMetadata as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl AppDataMetadata {
+    pub fn new() -> AppDataMetadata {
+        ::std::default::Default::default()
+    }
+
+    fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
+        let mut fields = ::std::vec::Vec::with_capacity(1);
+        let mut oneofs = ::std::vec::Vec::with_capacity(0);
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "channel",
+            |m: &AppDataMetadata| { &m.channel },
+            |m: &mut AppDataMetadata| { &mut m.channel },
+        ));
+        ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<AppDataMetadata>(
+            "AppDataMetadata",
+            fields,
+            oneofs,
+        )
+    }
+}
+
+impl ::protobuf::Message for AppDataMetadata {
+    const NAME: &'static str = "AppDataMetadata";
+
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::Result<()> {
+        while let Some(tag) = is.read_raw_tag_or_eof()? {
+            match tag {
+                8 => {
+                    self.channel = is.read_uint32()?;
+                },
+                tag => {
+                    ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u64 {
+        let mut my_size = 0;
+        if self.channel != 0 {
+            my_size += ::protobuf::rt::uint32_size(1, self.channel);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
+        self.special_fields.cached_size().set(my_size as u32);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::Result<()> {
+        if self.channel != 0 {
+            os.write_uint32(1, self.channel)?;
+        }
+        os.write_unknown_fields(self.special_fields.unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn special_fields(&self) -> &::protobuf::SpecialFields {
+        &self.special_fields
+    }
+
+    fn mut_special_fields(&mut self) -> &mut ::protobuf::SpecialFields {
+        &mut self.special_fields
+    }
+
+    fn new() -> AppDataMetadata {
+        AppDataMetadata::new()
+    }
+
+    fn clear(&mut self) {
+        self.channel = 0;
+        self.special_fields.clear();
+    }
+
+    fn default_instance() -> &'static AppDataMetadata {
+        static instance: AppDataMetadata = AppDataMetadata {
+            channel: 0,
+            special_fields: ::protobuf::SpecialFields::new(),
+        };
+        &instance
+    }
+}
+
+impl ::protobuf::MessageFull for AppDataMetadata {
+    fn descriptor() -> ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::Lazy::new();
+        descriptor.get(|| file_descriptor().message_by_package_relative_name("AppDataMetadata").unwrap()).clone()
+    }
+}
+
+impl ::std::fmt::Display for AppDataMetadata {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for AppDataMetadata {
+    type RuntimeType = ::protobuf::reflect::rt::RuntimeTypeMessage<Self>;
+}
+
+///  Carried by KEYFRAME_REQUEST packets, which are relayed to every peer but only answered by the
+///  target, with a key frame of its camera and of its screen share.
+// @@protoc_insertion_point(message:KeyframeRequestMetadata)
+#[derive(PartialEq,Clone,Default,Debug)]
+pub struct KeyframeRequestMetadata {
+    // message fields
+    ///  User id of the peer that should send a key frame.
+    // @@protoc_insertion_point(field:KeyframeRequestMetadata.target)
+    pub target: ::std::string::String,
+    // special fields
+    // @@protoc_insertion_point(special_field:KeyframeRequestMetadata.special_fields)
+    pub special_fields: ::protobuf::SpecialFields,
+}
+
+impl<'a> ::std::default::Default for &'a KeyframeRequestMetadata {
+    fn default() -> &'a KeyframeRequestMetadata {
+        <KeyframeRequestMetadata as ::protobuf::Message>::default_instance()
+    }
+}
+
+impl KeyframeRequestMetadata {
+    pub fn new() -> KeyframeRequestMetadata {
+        ::std::default::Default::default()
+    }
+
+    fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
+        let mut fields = ::std::vec::Vec::with_capacity(1);
+        let mut oneofs = ::std::vec::Vec::with_capacity(0);
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "target",
+            |m: &KeyframeRequestMetadata| { &m.target },
+            |m: &mut KeyframeRequestMetadata| { &mut m.target },
+        ));
+        ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<KeyframeRequestMetadata>(
+            "KeyframeRequestMetadata",
+            fields,
+            oneofs,
+        )
+    }
+}
+
+impl ::protobuf::Message for KeyframeRequestMetadata {
+    const NAME: &'static str = "KeyframeRequestMetadata";
+
+    fn is_initialized(&self) -> bool {
+        true
+    }
+
+    fn merge_from(&mut self, is: &mut ::protobuf::CodedInputStream<'_>) -> ::protobuf::Result<()> {
+        while let Some(tag) = is.read_raw_tag_or_eof()? {
+            match tag {
+                10 => {
+                    self.target = is.read_string()?;
+                },
+                tag => {
+                    ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
+                },
+            };
+        }
+        ::std::result::Result::Ok(())
+    }
+
+    // Compute sizes of nested messages
+    #[allow(unused_variables)]
+    fn compute_size(&self) -> u64 {
+        let mut my_size = 0;
+        if !self.target.is_empty() {
+            my_size += ::protobuf::rt::string_size(1, &self.target);
+        }
+        my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
+        self.special_fields.cached_size().set(my_size as u32);
+        my_size
+    }
+
+    fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::Result<()> {
+        if !self.target.is_empty() {
+            os.write_string(1, &self.target)?;
+        }
+        os.write_unknown_fields(self.special_fields.unknown_fields())?;
+        ::std::result::Result::Ok(())
+    }
+
+    fn special_fields(&self) -> &::protobuf::SpecialFields {
+        &self.special_fields
+    }
+
+    fn mut_special_fields(&mut self) -> &mut ::protobuf::SpecialFields {
+        &mut self.special_fields
+    }
+
+    fn new() -> KeyframeRequestMetadata {
+        KeyframeRequestMetadata::new()
+    }
+
+    fn clear(&mut self) {
+        self.target.clear();
+        self.special_fields.clear();
+    }
+
+    fn default_instance() -> &'static KeyframeRequestMetadata {
+        static instance: KeyframeRequestMetadata = KeyframeRequestMetadata {
+            target: ::std::string::String::new(),
+            special_fields: ::protobuf::SpecialFields::new(),
+        };
+        &instance
+    }
+}
+
+impl ::protobuf::MessageFull for KeyframeRequestMetadata {
+    fn descriptor() -> ::protobuf::reflect::MessageDescriptor {
+        static descriptor: ::protobuf::rt::Lazy<::protobuf::reflect::MessageDescriptor> = ::protobuf::rt::Lazy::new();
+        descriptor.get(|| file_descriptor().message_by_package_relative_name("KeyframeRequestMetadata").unwrap()).clone()
+    }
+}
+
+impl ::std::fmt::Display for KeyframeRequestMetadata {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        ::protobuf::text_format::fmt(self, f)
+    }
+}
+
+impl ::protobuf::reflect::ProtobufValue for KeyframeRequestMetadata {
+    type RuntimeType = ::protobuf::reflect::rt::RuntimeTypeMessage<Self>;
+}
+
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x18types/media_packet.proto\"\xf3\x02\n\x0bMediaPacket\x125\n\nmedia_\
+    \n\x18types/media_packet.proto\"\xbd\x05\n\x0bMediaPacket\x125\n\nmedia_\
     type\x18\x01\x20\x01(\x0e2\x16.MediaPacket.MediaTypeR\tmediaType\x12\x14\
     \n\x05email\x18\x02\x20\x01(\tR\x05email\x12\x12\n\x04data\x18\x03\x20\
     \x01(\x0cR\x04data\x12\x1d\n\nframe_type\x18\x04\x20\x01(\tR\tframeType\
     \x12\x1c\n\ttimestamp\x18\x05\x20\x01(\x01R\ttimestamp\x12\x1a\n\x08dura\
     tion\x18\x06\x20\x01(\x01R\x08duration\x125\n\x0eaudio_metadata\x18\x07\
     \x20\x01(\x0b2\x0e.AudioMetadataR\raudioMetadata\x125\n\x0evideo_metadat\
-    a\x18\x08\x20\x01(\x0b2\x0e.VideoMetadataR\rvideoMetadata\"<\n\tMediaTyp\
-    e\x12\t\n\x05VIDEO\x10\0\x12\t\n\x05AUDIO\x10\x01\x12\n\n\x06SCREEN\x10\
-    \x02\x12\r\n\tHEARTBEAT\x10\x03\"\xcc\x01\n\rAudioMetadata\x12!\n\x0caud\
-    io_format\x18\x01\x20\x01(\tR\x0baudioFormat\x127\n\x18audio_number_of_c\
-    hannels\x18\x02\x20\x01(\rR\x15audioNumberOfChannels\x123\n\x16audio_num\
-    ber_of_frames\x18\x03\x20\x01(\rR\x13audioNumberOfFrames\x12*\n\x11audio\
-    _sample_rate\x18\x04\x20\x01(\x02R\x0faudioSampleRate\"+\n\rVideoMetadat\
-    a\x12\x1a\n\x08sequence\x18\x01\x20\x01(\x04R\x08sequenceJ\xfd\x07\n\x06\
-    \x12\x04\0\0\x1c\x01\n\x08\n\x01\x0c\x12\x03\0\0\x12\n\n\n\x02\x04\0\x12\
-    \x04\x02\0\x11\x01\n\n\n\x03\x04\0\x01\x12\x03\x02\x08\x13\n\x0c\n\x04\
-    \x04\0\x04\0\x12\x04\x03\x02\x08\x03\n\x0c\n\x05\x04\0\x04\0\x01\x12\x03\
-    \x03\x07\x10\n\r\n\x06\x04\0\x04\0\x02\0\x12\x03\x04\x04\x0e\n\x0e\n\x07\
-    \x04\0\x04\0\x02\0\x01\x12\x03\x04\x04\t\n\x0e\n\x07\x04\0\x04\0\x02\0\
-    \x02\x12\x03\x04\x0c\r\n\r\n\x06\x04\0\x04\0\x02\x01\x12\x03\x05\x04\x0e\
-    \n\x0e\n\x07\x04\0\x04\0\x02\x01\x01\x12\x03\x05\x04\t\n\x0e\n\x07\x04\0\
-    \x04\0\x02\x01\x02\x12\x03\x05\x0c\r\n\r\n\x06\x04\0\x04\0\x02\x02\x12\
-    \x03\x06\x04\x0f\n\x0e\n\x07\x04\0\x04\0\x02\x02\x01\x12\x03\x06\x04\n\n\
-    \x0e\n\x07\x04\0\x04\0\x02\x02\x02\x12\x03\x06\r\x0e\n\r\n\x06\x04\0\x04\
-    \0\x02\x03\x12\x03\x07\x04\x12\n\x0e\n\x07\x04\0\x04\0\x02\x03\x01\x12\
-    \x03\x07\x04\r\n\x0e\n\x07\x04\0\x04\0\x02\x03\x02\x12\x03\x07\x10\x11\n\
-    \x0b\n\x04\x04\0\x02\0\x12\x03\t\x02\x1b\n\x0c\n\x05\x04\0\x02\0\x06\x12\
-    \x03\t\x02\x0b\n\x0c\n\x05\x04\0\x02\0\x01\x12\x03\t\x0c\x16\n\x0c\n\x05\
-    \x04\0\x02\0\x03\x12\x03\t\x19\x1a\n\x0b\n\x04\x04\0\x02\x01\x12\x03\n\
-    \x02\x13\n\x0c\n\x05\x04\0\x02\x01\x05\x12\x03\n\x02\x08\n\x0c\n\x05\x04\
-    \0\x02\x01\x01\x12\x03\n\t\x0e\n\x0c\n\x05\x04\0\x02\x01\x03\x12\x03\n\
-    \x11\x12\n\x0b\n\x04\x04\0\x02\x02\x12\x03\x0b\x02\x11\n\x0c\n\x05\x04\0\
-    \x02\x02\x05\x12\x03\x0b\x02\x07\n\x0c\n\x05\x04\0\x02\x02\x01\x12\x03\
-    \x0b\x08\x0c\n\x0c\n\x05\x04\0\x02\x02\x03\x12\x03\x0b\x0f\x10\n\x0b\n\
-    \x04\x04\0\x02\x03\x12\x03\x0c\x02\x18\n\x0c\n\x05\x04\0\x02\x03\x05\x12\
-    \x03\x0c\x02\x08\n\x0c\n\x05\x04\0\x02\x03\x01\x12\x03\x0c\t\x13\n\x0c\n\
-    \x05\x04\0\x02\x03\x03\x12\x03\x0c\x16\x17\n\x0b\n\x04\x04\0\x02\x04\x12\
-    \x03\r\x02\x17\n\x0c\n\x05\x04\0\x02\x04\x05\x12\x03\r\x02\x08\n\x0c\n\
-    \x05\x04\0\x02\x04\x01\x12\x03\r\t\x12\n\x0c\n\x05\x04\0\x02\x04\x03\x12\
-    \x03\r\x15\x16\n\x0b\n\x04\x04\0\x02\x05\x12\x03\x0e\x02\x16\n\x0c\n\x05\
-    \x04\0\x02\x05\x05\x12\x03\x0e\x02\x08\n\x0c\n\x05\x04\0\x02\x05\x01\x12\
-    \x03\x0e\t\x11\n\x0c\n\x05\x04\0\x02\x05\x03\x12\x03\x0e\x14\x15\n\x0b\n\
-    \x04\x04\0\x02\x06\x12\x03\x0f\x02#\n\x0c\n\x05\x04\0\x02\x06\x06\x12\
-    \x03\x0f\x02\x0f\n\x0c\n\x05\x04\0\x02\x06\x01\x12\x03\x0f\x10\x1e\n\x0c\
-    \n\x05\x04\0\x02\x06\x03\x12\x03\x0f!\"\n\x0b\n\x04\x04\0\x02\x07\x12\
-    \x03\x10\x02#\n\x0c\n\x05\x04\0\x02\x07\x06\x12\x03\x10\x02\x0f\n\x0c\n\
-    \x05\x04\0\x02\x07\x01\x12\x03\x10\x10\x1e\n\x0c\n\x05\x04\0\x02\x07\x03\
-    \x12\x03\x10!\"\n\n\n\x02\x04\x01\x12\x04\x13\0\x18\x01\n\n\n\x03\x04\
-    \x01\x01\x12\x03\x13\x08\x15\n\x0b\n\x04\x04\x01\x02\0\x12\x03\x14\x02\
-    \x1a\n\x0c\n\x05\x04\x01\x02\0\x05\x12\x03\x14\x02\x08\n\x0c\n\x05\x04\
-    \x01\x02\0\x01\x12\x03\x14\t\x15\n\x0c\n\x05\x04\x01\x02\0\x03\x12\x03\
-    \x14\x18\x19\n\x0b\n\x04\x04\x01\x02\x01\x12\x03\x15\x02&\n\x0c\n\x05\
-    \x04\x01\x02\x01\x05\x12\x03\x15\x02\x08\n\x0c\n\x05\x04\x01\x02\x01\x01\
-    \x12\x03\x15\t!\n\x0c\n\x05\x04\x01\x02\x01\x03\x12\x03\x15$%\n\x0b\n\
-    \x04\x04\x01\x02\x02\x12\x03\x16\x02$\n\x0c\n\x05\x04\x01\x02\x02\x05\
-    \x12\x03\x16\x02\x08\n\x0c\n\x05\x04\x01\x02\x02\x01\x12\x03\x16\t\x1f\n\
-    \x0c\n\x05\x04\x01\x02\x02\x03\x12\x03\x16\"#\n\x0b\n\x04\x04\x01\x02\
-    \x03\x12\x03\x17\x02\x1e\n\x0c\n\x05\x04\x01\x02\x03\x05\x12\x03\x17\x02\
-    \x07\n\x0c\n\x05\x04\x01\x02\x03\x01\x12\x03\x17\x08\x19\n\x0c\n\x05\x04\
-    \x01\x02\x03\x03\x12\x03\x17\x1c\x1d\n\n\n\x02\x04\x02\x12\x04\x1a\0\x1c\
-    \x01\n\n\n\x03\x04\x02\x01\x12\x03\x1a\x08\x15\n\x0b\n\x04\x04\x02\x02\0\
-    \x12\x03\x1b\x02\x16\n\x0c\n\x05\x04\x02\x02\0\x05\x12\x03\x1b\x02\x08\n\
-    \x0c\n\x05\x04\x02\x02\0\x01\x12\x03\x1b\t\x11\n\x0c\n\x05\x04\x02\x02\0\
-    \x03\x12\x03\x1b\x14\x15b\x06proto3\
+    a\x18\x08\x20\x01(\x0b2\x0e.VideoMetadataR\rvideoMetadata\x12\"\n\rend_o\
+    f_stream\x18\t\x20\x01(\x08R\x0bendOfStream\x12A\n\x12heartbeat_metadata\
+    \x18\n\x20\x01(\x0b2\x12.HeartbeatMetadataR\x11heartbeatMetadata\x12<\n\
+    \x11app_data_metadata\x18\x0b\x20\x01(\x0b2\x10.AppDataMetadataR\x0fappD\
+    ataMetadata\x12T\n\x19keyframe_request_metadata\x18\x0c\x20\x01(\x0b2\
+    \x18.KeyframeRequestMetadataR\x17keyframeRequestMetadata\"\x8a\x01\n\tMe\
+    diaType\x12\x1a\n\x16MEDIA_TYPE_UNSPECIFIED\x10\0\x12\t\n\x05VIDEO\x10\
+    \x01\x12\t\n\x05AUDIO\x10\x02\x12\n\n\x06SCREEN\x10\x03\x12\r\n\tHEARTBE\
+    AT\x10\x04\x12\x08\n\x04DATA\x10\x05\x12\x10\n\x0cSCREEN_AUDIO\x10\x06\
+    \x12\x14\n\x10KEYFRAME_REQUEST\x10\x07\"\xcc\x01\n\rAudioMetadata\x12!\n\
+    \x0caudio_format\x18\x01\x20\x01(\tR\x0baudioFormat\x127\n\x18audio_numb\
+    er_of_channels\x18\x02\x20\x01(\rR\x15audioNumberOfChannels\x123\n\x16au\
+    dio_number_of_frames\x18\x03\x20\x01(\rR\x13audioNumberOfFrames\x12*\n\
+    \x11audio_sample_rate\x18\x04\x20\x01(\x02R\x0faudioSampleRate\"A\n\rVid\
+    eoMetadata\x12\x1a\n\x08sequence\x18\x01\x20\x01(\x04R\x08sequence\x12\
+    \x14\n\x05codec\x18\x02\x20\x01(\tR\x05codec\"\xef\x01\n\x11HeartbeatMet\
+    adata\x12#\n\raudio_enabled\x18\x01\x20\x01(\x08R\x0caudioEnabled\x12#\n\
+    \rvideo_enabled\x18\x02\x20\x01(\x08R\x0cvideoEnabled\x12%\n\x0escreen_e\
+    nabled\x18\x03\x20\x01(\x08R\rscreenEnabled\x12!\n\x0cvideo_codecs\x18\
+    \x04\x20\x03(\tR\x0bvideoCodecs\x12*\n\x11display_name_hash\x18\x05\x20\
+    \x01(\rR\x0fdisplayNameHash\x12\x1a\n\x08speaking\x18\x06\x20\x01(\x08R\
+    \x08speaking\"+\n\x0fAppDataMetadata\x12\x18\n\x07channel\x18\x01\x20\
+    \x01(\rR\x07channel\"1\n\x17KeyframeRequestMetadata\x12\x16\n\x06target\
+    \x18\x01\x20\x01(\tR\x06targetJ\x8e\x19\n\x06\x12\x04\0\0G\x01\n\x08\n\
+    \x01\x0c\x12\x03\0\0\x12\n\n\n\x02\x04\0\x12\x04\x02\0\x1e\x01\n\n\n\x03\
+    \x04\0\x01\x12\x03\x02\x08\x13\n\x0c\n\x04\x04\0\x04\0\x12\x04\x03\x02\
+    \x10\x03\n\x0c\n\x05\x04\0\x04\0\x01\x12\x03\x03\x07\x10\nY\n\x06\x04\0\
+    \x04\0\x02\0\x12\x03\x05\x04\x1f\x1aJ\x20Never\x20sent;\x20an\x20unset\
+    \x20media_type\x20reads\x20as\x20this\x20instead\x20of\x20as\x20a\x20rea\
+    l\x20type.\n\n\x0e\n\x07\x04\0\x04\0\x02\0\x01\x12\x03\x05\x04\x1a\n\x0e\
+    \n\x07\x04\0\x04\0\x02\0\x02\x12\x03\x05\x1d\x1e\n\r\n\x06\x04\0\x04\0\
+    \x02\x01\x12\x03\x06\x04\x0e\n\x0e\n\x07\x04\0\x04\0\x02\x01\x01\x12\x03\
+    \x06\x04\t\n\x0e\n\x07\x04\0\x04\0\x02\x01\x02\x12\x03\x06\x0c\r\n\r\n\
+    \x06\x04\0\x04\0\x02\x02\x12\x03\x07\x04\x0e\n\x0e\n\x07\x04\0\x04\0\x02\
+    \x02\x01\x12\x03\x07\x04\t\n\x0e\n\x07\x04\0\x04\0\x02\x02\x02\x12\x03\
+    \x07\x0c\r\n\r\n\x06\x04\0\x04\0\x02\x03\x12\x03\x08\x04\x0f\n\x0e\n\x07\
+    \x04\0\x04\0\x02\x03\x01\x12\x03\x08\x04\n\n\x0e\n\x07\x04\0\x04\0\x02\
+    \x03\x02\x12\x03\x08\r\x0e\n\r\n\x06\x04\0\x04\0\x02\x04\x12\x03\t\x04\
+    \x12\n\x0e\n\x07\x04\0\x04\0\x02\x04\x01\x12\x03\t\x04\r\n\x0e\n\x07\x04\
+    \0\x04\0\x02\x04\x02\x12\x03\t\x10\x11\nC\n\x06\x04\0\x04\0\x02\x05\x12\
+    \x03\x0b\x04\r\x1a4\x20Application-defined\x20messages,\x20see\x20AppDat\
+    aMetadata.\n\n\x0e\n\x07\x04\0\x04\0\x02\x05\x01\x12\x03\x0b\x04\x08\n\
+    \x0e\n\x07\x04\0\x04\0\x02\x05\x02\x12\x03\x0b\x0b\x0c\nH\n\x06\x04\0\
+    \x04\0\x02\x06\x12\x03\r\x04\x15\x1a9\x20System\x20or\x20tab\x20audio\
+    \x20captured\x20along\x20with\x20a\x20screen\x20share.\n\n\x0e\n\x07\x04\
+    \0\x04\0\x02\x06\x01\x12\x03\r\x04\x10\n\x0e\n\x07\x04\0\x04\0\x02\x06\
+    \x02\x12\x03\r\x13\x14\nN\n\x06\x04\0\x04\0\x02\x07\x12\x03\x0f\x04\x19\
+    \x1a?\x20Asks\x20a\x20peer\x20to\x20send\x20a\x20key\x20frame,\x20see\
+    \x20KeyframeRequestMetadata.\n\n\x0e\n\x07\x04\0\x04\0\x02\x07\x01\x12\
+    \x03\x0f\x04\x14\n\x0e\n\x07\x04\0\x04\0\x02\x07\x02\x12\x03\x0f\x17\x18\
+    \n\x0b\n\x04\x04\0\x02\0\x12\x03\x11\x02\x1b\n\x0c\n\x05\x04\0\x02\0\x06\
+    \x12\x03\x11\x02\x0b\n\x0c\n\x05\x04\0\x02\0\x01\x12\x03\x11\x0c\x16\n\
+    \x0c\n\x05\x04\0\x02\0\x03\x12\x03\x11\x19\x1a\n\x0b\n\x04\x04\0\x02\x01\
+    \x12\x03\x12\x02\x13\n\x0c\n\x05\x04\0\x02\x01\x05\x12\x03\x12\x02\x08\n\
+    \x0c\n\x05\x04\0\x02\x01\x01\x12\x03\x12\t\x0e\n\x0c\n\x05\x04\0\x02\x01\
+    \x03\x12\x03\x12\x11\x12\n\x0b\n\x04\x04\0\x02\x02\x12\x03\x13\x02\x11\n\
+    \x0c\n\x05\x04\0\x02\x02\x05\x12\x03\x13\x02\x07\n\x0c\n\x05\x04\0\x02\
+    \x02\x01\x12\x03\x13\x08\x0c\n\x0c\n\x05\x04\0\x02\x02\x03\x12\x03\x13\
+    \x0f\x10\n\x0b\n\x04\x04\0\x02\x03\x12\x03\x14\x02\x18\n\x0c\n\x05\x04\0\
+    \x02\x03\x05\x12\x03\x14\x02\x08\n\x0c\n\x05\x04\0\x02\x03\x01\x12\x03\
+    \x14\t\x13\n\x0c\n\x05\x04\0\x02\x03\x03\x12\x03\x14\x16\x17\n\x0b\n\x04\
+    \x04\0\x02\x04\x12\x03\x15\x02\x17\n\x0c\n\x05\x04\0\x02\x04\x05\x12\x03\
+    \x15\x02\x08\n\x0c\n\x05\x04\0\x02\x04\x01\x12\x03\x15\t\x12\n\x0c\n\x05\
+    \x04\0\x02\x04\x03\x12\x03\x15\x15\x16\n\x0b\n\x04\x04\0\x02\x05\x12\x03\
+    \x16\x02\x16\n\x0c\n\x05\x04\0\x02\x05\x05\x12\x03\x16\x02\x08\n\x0c\n\
+    \x05\x04\0\x02\x05\x01\x12\x03\x16\t\x11\n\x0c\n\x05\x04\0\x02\x05\x03\
+    \x12\x03\x16\x14\x15\n\x0b\n\x04\x04\0\x02\x06\x12\x03\x17\x02#\n\x0c\n\
+    \x05\x04\0\x02\x06\x06\x12\x03\x17\x02\x0f\n\x0c\n\x05\x04\0\x02\x06\x01\
+    \x12\x03\x17\x10\x1e\n\x0c\n\x05\x04\0\x02\x06\x03\x12\x03\x17!\"\n\x0b\
+    \n\x04\x04\0\x02\x07\x12\x03\x18\x02#\n\x0c\n\x05\x04\0\x02\x07\x06\x12\
+    \x03\x18\x02\x0f\n\x0c\n\x05\x04\0\x02\x07\x01\x12\x03\x18\x10\x1e\n\x0c\
+    \n\x05\x04\0\x02\x07\x03\x12\x03\x18!\"\nM\n\x04\x04\0\x02\x08\x12\x03\
+    \x1a\x02\x19\x1a@\x20Set\x20when\x20the\x20sender\x20stopped\x20this\x20\
+    track;\x20carries\x20no\x20media\x20data.\n\n\x0c\n\x05\x04\0\x02\x08\
+    \x05\x12\x03\x1a\x02\x06\n\x0c\n\x05\x04\0\x02\x08\x01\x12\x03\x1a\x07\
+    \x14\n\x0c\n\x05\x04\0\x02\x08\x03\x12\x03\x1a\x17\x18\n\x0b\n\x04\x04\0\
+    \x02\t\x12\x03\x1b\x02,\n\x0c\n\x05\x04\0\x02\t\x06\x12\x03\x1b\x02\x13\
+    \n\x0c\n\x05\x04\0\x02\t\x01\x12\x03\x1b\x14&\n\x0c\n\x05\x04\0\x02\t\
+    \x03\x12\x03\x1b)+\n\x0b\n\x04\x04\0\x02\n\x12\x03\x1c\x02)\n\x0c\n\x05\
+    \x04\0\x02\n\x06\x12\x03\x1c\x02\x11\n\x0c\n\x05\x04\0\x02\n\x01\x12\x03\
+    \x1c\x12#\n\x0c\n\x05\x04\0\x02\n\x03\x12\x03\x1c&(\n\x0b\n\x04\x04\0\
+    \x02\x0b\x12\x03\x1d\x029\n\x0c\n\x05\x04\0\x02\x0b\x06\x12\x03\x1d\x02\
+    \x19\n\x0c\n\x05\x04\0\x02\x0b\x01\x12\x03\x1d\x1a3\n\x0c\n\x05\x04\0\
+    \x02\x0b\x03\x12\x03\x1d68\n\n\n\x02\x04\x01\x12\x04\x20\0%\x01\n\n\n\
+    \x03\x04\x01\x01\x12\x03\x20\x08\x15\n\x0b\n\x04\x04\x01\x02\0\x12\x03!\
+    \x02\x1a\n\x0c\n\x05\x04\x01\x02\0\x05\x12\x03!\x02\x08\n\x0c\n\x05\x04\
+    \x01\x02\0\x01\x12\x03!\t\x15\n\x0c\n\x05\x04\x01\x02\0\x03\x12\x03!\x18\
+    \x19\n\x0b\n\x04\x04\x01\x02\x01\x12\x03\"\x02&\n\x0c\n\x05\x04\x01\x02\
+    \x01\x05\x12\x03\"\x02\x08\n\x0c\n\x05\x04\x01\x02\x01\x01\x12\x03\"\t!\
+    \n\x0c\n\x05\x04\x01\x02\x01\x03\x12\x03\"$%\n\x0b\n\x04\x04\x01\x02\x02\
+    \x12\x03#\x02$\n\x0c\n\x05\x04\x01\x02\x02\x05\x12\x03#\x02\x08\n\x0c\n\
+    \x05\x04\x01\x02\x02\x01\x12\x03#\t\x1f\n\x0c\n\x05\x04\x01\x02\x02\x03\
+    \x12\x03#\"#\n\x0b\n\x04\x04\x01\x02\x03\x12\x03$\x02\x1e\n\x0c\n\x05\
+    \x04\x01\x02\x03\x05\x12\x03$\x02\x07\n\x0c\n\x05\x04\x01\x02\x03\x01\
+    \x12\x03$\x08\x19\n\x0c\n\x05\x04\x01\x02\x03\x03\x12\x03$\x1c\x1d\n\n\n\
+    \x02\x04\x02\x12\x04'\0+\x01\n\n\n\x03\x04\x02\x01\x12\x03'\x08\x15\n\
+    \x0b\n\x04\x04\x02\x02\0\x12\x03(\x02\x16\n\x0c\n\x05\x04\x02\x02\0\x05\
+    \x12\x03(\x02\x08\n\x0c\n\x05\x04\x02\x02\0\x01\x12\x03(\t\x11\n\x0c\n\
+    \x05\x04\x02\x02\0\x03\x12\x03(\x14\x15\n\\\n\x04\x04\x02\x02\x01\x12\
+    \x03*\x02\x13\x1aO\x20WebCodecs\x20codec\x20string\x20the\x20frame\x20wa\
+    s\x20encoded\x20with;\x20empty\x20means\x20vp09.00.10.08.\n\n\x0c\n\x05\
+    \x04\x02\x02\x01\x05\x12\x03*\x02\x08\n\x0c\n\x05\x04\x02\x02\x01\x01\
+    \x12\x03*\t\x0e\n\x0c\n\x05\x04\x02\x02\x01\x03\x12\x03*\x11\x12\nI\n\
+    \x02\x04\x03\x12\x04.\0:\x01\x1a=\x20Presence\x20state\x20of\x20the\x20s\
+    ender,\x20carried\x20by\x20HEARTBEAT\x20packets.\n\n\n\n\x03\x04\x03\x01\
+    \x12\x03.\x08\x19\n\x0b\n\x04\x04\x03\x02\0\x12\x03/\x02\x19\n\x0c\n\x05\
+    \x04\x03\x02\0\x05\x12\x03/\x02\x06\n\x0c\n\x05\x04\x03\x02\0\x01\x12\
+    \x03/\x07\x14\n\x0c\n\x05\x04\x03\x02\0\x03\x12\x03/\x17\x18\n\x0b\n\x04\
+    \x04\x03\x02\x01\x12\x030\x02\x19\n\x0c\n\x05\x04\x03\x02\x01\x05\x12\
+    \x030\x02\x06\n\x0c\n\x05\x04\x03\x02\x01\x01\x12\x030\x07\x14\n\x0c\n\
+    \x05\x04\x03\x02\x01\x03\x12\x030\x17\x18\n\x0b\n\x04\x04\x03\x02\x02\
+    \x12\x031\x02\x1a\n\x0c\n\x05\x04\x03\x02\x02\x05\x12\x031\x02\x06\n\x0c\
+    \n\x05\x04\x03\x02\x02\x01\x12\x031\x07\x15\n\x0c\n\x05\x04\x03\x02\x02\
+    \x03\x12\x031\x18\x19\nQ\n\x04\x04\x03\x02\x03\x12\x033\x02#\x1aD\x20Web\
+    Codecs\x20codec\x20strings\x20of\x20the\x20video\x20codecs\x20the\x20sen\
+    der\x20can\x20decode.\n\n\x0c\n\x05\x04\x03\x02\x03\x04\x12\x033\x02\n\n\
+    \x0c\n\x05\x04\x03\x02\x03\x05\x12\x033\x0b\x11\n\x0c\n\x05\x04\x03\x02\
+    \x03\x01\x12\x033\x12\x1e\n\x0c\n\x05\x04\x03\x02\x03\x03\x12\x033!\"\n\
+    \xa7\x01\n\x04\x04\x03\x02\x04\x12\x036\x02\x1f\x1a\x99\x01\x2032-bit\
+    \x20FNV-1a\x20hash\x20of\x20the\x20sender's\x20display\x20name,\x20so\
+    \x20receivers\x20can\x20tell\x20when\x20it\x20changes\n\x20without\x20it\
+    \x20being\x20sent\x20every\x20second.\x200\x20when\x20the\x20sender\x20h\
+    as\x20none.\n\n\x0c\n\x05\x04\x03\x02\x04\x05\x12\x036\x02\x08\n\x0c\n\
+    \x05\x04\x03\x02\x04\x01\x12\x036\t\x1a\n\x0c\n\x05\x04\x03\x02\x04\x03\
+    \x12\x036\x1d\x1e\n\x8f\x01\n\x04\x04\x03\x02\x05\x12\x039\x02\x14\x1a\
+    \x81\x01\x20Whether\x20the\x20sender's\x20microphone\x20currently\x20pic\
+    ks\x20up\x20speech,\x20as\x20a\x20hint\x20for\x20active\x20speaker\n\x20\
+    detection\x20before\x20its\x20audio\x20is\x20decoded.\n\n\x0c\n\x05\x04\
+    \x03\x02\x05\x05\x12\x039\x02\x06\n\x0c\n\x05\x04\x03\x02\x05\x01\x12\
+    \x039\x07\x0f\n\x0c\n\x05\x04\x03\x02\x05\x03\x12\x039\x12\x13\nU\n\x02\
+    \x04\x04\x12\x04=\0@\x01\x1aI\x20Routing\x20of\x20DATA\x20packets,\x20wh\
+    ose\x20payload\x20is\x20opaque\x20to\x20the\x20client\x20library.\n\n\n\
+    \n\x03\x04\x04\x01\x12\x03=\x08\x17\nc\n\x04\x04\x04\x02\0\x12\x03?\x02\
+    \x15\x1aV\x20Application-chosen\x20channel\x20number,\x20so\x20independe\
+    nt\x20features\x20can\x20share\x20the\x20connection.\n\n\x0c\n\x05\x04\
+    \x04\x02\0\x05\x12\x03?\x02\x08\n\x0c\n\x05\x04\x04\x02\0\x01\x12\x03?\t\
+    \x10\n\x0c\n\x05\x04\x04\x02\0\x03\x12\x03?\x13\x14\n\xad\x01\n\x02\x04\
+    \x05\x12\x04D\0G\x01\x1a\xa0\x01\x20Carried\x20by\x20KEYFRAME_REQUEST\
+    \x20packets,\x20which\x20are\x20relayed\x20to\x20every\x20peer\x20but\
+    \x20only\x20answered\x20by\x20the\n\x20target,\x20with\x20a\x20key\x20fr\
+    ame\x20of\x20its\x20camera\x20and\x20of\x20its\x20screen\x20share.\n\n\n\
+    \n\x03\x04\x05\x01\x12\x03D\x08\x1f\n@\n\x04\x04\x05\x02\0\x12\x03F\x02\
+    \x14\x1a3\x20User\x20id\x20of\x20the\x20peer\x20that\x20should\x20send\
+    \x20a\x20key\x20frame.\n\n\x0c\n\x05\x04\x05\x02\0\x05\x12\x03F\x02\x08\
+    \n\x0c\n\x05\x04\x05\x02\0\x01\x12\x03F\t\x0f\n\x0c\n\x05\x04\x05\x02\0\
+    \x03\x12\x03F\x12\x13b\x06proto3\
 ";
 
 /// `FileDescriptorProto` object which was a source for this generated file
@@ -730,10 +1400,13 @@ pub fn file_descriptor() -> &'static ::protobuf::reflect::FileDescriptor {
     file_descriptor.get(|| {
         let generated_file_descriptor = generated_file_descriptor_lazy.get(|| {
             let mut deps = ::std::vec::Vec::with_capacity(0);
-            let mut messages = ::std::vec::Vec::with_capacity(3);
+            let mut messages = ::std::vec::Vec::with_capacity(6);
             messages.push(MediaPacket::generated_message_descriptor_data());
             messages.push(AudioMetadata::generated_message_descriptor_data());
             messages.push(VideoMetadata::generated_message_descriptor_data());
+            messages.push(HeartbeatMetadata::generated_message_descriptor_data());
+            messages.push(AppDataMetadata::generated_message_descriptor_data());
+            messages.push(KeyframeRequestMetadata::generated_message_descriptor_data());
             let mut enums = ::std::vec::Vec::with_capacity(1);
             enums.push(media_packet::MediaType::generated_enum_descriptor_data());
             ::protobuf::reflect::GeneratedFileDescriptor::new_generated(
@@ -1,6 +1,7 @@
 // @generated
 
 pub mod aes_packet;
+pub mod congestion_packet;
 pub mod connection_packet;
 pub mod media_packet;
 pub mod packet_wrapper;
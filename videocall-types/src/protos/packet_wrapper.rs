@@ -34,6 +34,9 @@ pub struct PacketWrapper {
     pub email: ::std::string::String,
     // @@protoc_insertion_point(field:PacketWrapper.data)
     pub data: ::std::vec::Vec<u8>,
+    ///  Generation of the sender's AES key that MEDIA data is encrypted with, see AesPacket.
+    // @@protoc_insertion_point(field:PacketWrapper.key_generation)
+    pub key_generation: u32,
     // special fields
     // @@protoc_insertion_point(special_field:PacketWrapper.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -51,7 +54,7 @@ impl PacketWrapper {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(3);
+        let mut fields = ::std::vec::Vec::with_capacity(4);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "packet_type",
@@ -68,6 +71,11 @@ impl PacketWrapper {
             |m: &PacketWrapper| { &m.data },
             |m: &mut PacketWrapper| { &mut m.data },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "key_generation",
+            |m: &PacketWrapper| { &m.key_generation },
+            |m: &mut PacketWrapper| { &mut m.key_generation },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<PacketWrapper>(
             "PacketWrapper",
             fields,
@@ -95,6 +103,9 @@ impl ::protobuf::Message for PacketWrapper {
                 26 => {
                     self.data = is.read_bytes()?;
                 },
+                32 => {
+                    self.key_generation = is.read_uint32()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -107,7 +118,7 @@ impl ::protobuf::Message for PacketWrapper {
     #[allow(unused_variables)]
     fn compute_size(&self) -> u64 {
         let mut my_size = 0;
-        if self.packet_type != ::protobuf::EnumOrUnknown::new(packet_wrapper::PacketType::RSA_PUB_KEY) {
+        if self.packet_type != ::protobuf::EnumOrUnknown::new(packet_wrapper::PacketType::PACKET_TYPE_UNSPECIFIED) {
             my_size += ::protobuf::rt::int32_size(1, self.packet_type.value());
         }
         if !self.email.is_empty() {
@@ -116,13 +127,16 @@ impl ::protobuf::Message for PacketWrapper {
         if !self.data.is_empty() {
             my_size += ::protobuf::rt::bytes_size(3, &self.data);
         }
+        if self.key_generation != 0 {
+            my_size += ::protobuf::rt::uint32_size(4, self.key_generation);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
     }
 
     fn write_to_with_cached_sizes(&self, os: &mut ::protobuf::CodedOutputStream<'_>) -> ::protobuf::Result<()> {
-        if self.packet_type != ::protobuf::EnumOrUnknown::new(packet_wrapper::PacketType::RSA_PUB_KEY) {
+        if self.packet_type != ::protobuf::EnumOrUnknown::new(packet_wrapper::PacketType::PACKET_TYPE_UNSPECIFIED) {
             os.write_enum(1, ::protobuf::EnumOrUnknown::value(&self.packet_type))?;
         }
         if !self.email.is_empty() {
@@ -131,6 +145,9 @@ impl ::protobuf::Message for PacketWrapper {
         if !self.data.is_empty() {
             os.write_bytes(3, &self.data)?;
         }
+        if self.key_generation != 0 {
+            os.write_uint32(4, self.key_generation)?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -148,9 +165,10 @@ impl ::protobuf::Message for PacketWrapper {
     }
 
     fn clear(&mut self) {
-        self.packet_type = ::protobuf::EnumOrUnknown::new(packet_wrapper::PacketType::RSA_PUB_KEY);
+        self.packet_type = ::protobuf::EnumOrUnknown::new(packet_wrapper::PacketType::PACKET_TYPE_UNSPECIFIED);
         self.email.clear();
         self.data.clear();
+        self.key_generation = 0;
         self.special_fields.clear();
     }
 
@@ -159,6 +177,7 @@ impl ::protobuf::Message for PacketWrapper {
             packet_type: ::protobuf::EnumOrUnknown::from_i32(0),
             email: ::std::string::String::new(),
             data: ::std::vec::Vec::new(),
+            key_generation: 0,
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -187,14 +206,18 @@ pub mod packet_wrapper {
     #[derive(Clone,Copy,PartialEq,Eq,Debug,Hash)]
     // @@protoc_insertion_point(enum:PacketWrapper.PacketType)
     pub enum PacketType {
+        // @@protoc_insertion_point(enum_value:PacketWrapper.PacketType.PACKET_TYPE_UNSPECIFIED)
+        PACKET_TYPE_UNSPECIFIED = 0,
         // @@protoc_insertion_point(enum_value:PacketWrapper.PacketType.RSA_PUB_KEY)
-        RSA_PUB_KEY = 0,
+        RSA_PUB_KEY = 1,
         // @@protoc_insertion_point(enum_value:PacketWrapper.PacketType.AES_KEY)
-        AES_KEY = 1,
+        AES_KEY = 2,
         // @@protoc_insertion_point(enum_value:PacketWrapper.PacketType.MEDIA)
-        MEDIA = 2,
+        MEDIA = 3,
         // @@protoc_insertion_point(enum_value:PacketWrapper.PacketType.CONNECTION)
-        CONNECTION = 3,
+        CONNECTION = 4,
+        // @@protoc_insertion_point(enum_value:PacketWrapper.PacketType.CONGESTION)
+        CONGESTION = 5,
     }
 
     impl ::protobuf::Enum for PacketType {
@@ -206,29 +229,35 @@ pub mod packet_wrapper {
 
         fn from_i32(value: i32) -> ::std::option::Option<PacketType> {
             match value {
-                0 => ::std::option::Option::Some(PacketType::RSA_PUB_KEY),
-                1 => ::std::option::Option::Some(PacketType::AES_KEY),
-                2 => ::std::option::Option::Some(PacketType::MEDIA),
-                3 => ::std::option::Option::Some(PacketType::CONNECTION),
+                0 => ::std::option::Option::Some(PacketType::PACKET_TYPE_UNSPECIFIED),
+                1 => ::std::option::Option::Some(PacketType::RSA_PUB_KEY),
+                2 => ::std::option::Option::Some(PacketType::AES_KEY),
+                3 => ::std::option::Option::Some(PacketType::MEDIA),
+                4 => ::std::option::Option::Some(PacketType::CONNECTION),
+                5 => ::std::option::Option::Some(PacketType::CONGESTION),
                 _ => ::std::option::Option::None
             }
         }
 
         fn from_str(str: &str) -> ::std::option::Option<PacketType> {
             match str {
+                "PACKET_TYPE_UNSPECIFIED" => ::std::option::Option::Some(PacketType::PACKET_TYPE_UNSPECIFIED),
                 "RSA_PUB_KEY" => ::std::option::Option::Some(PacketType::RSA_PUB_KEY),
                 "AES_KEY" => ::std::option::Option::Some(PacketType::AES_KEY),
                 "MEDIA" => ::std::option::Option::Some(PacketType::MEDIA),
                 "CONNECTION" => ::std::option::Option::Some(PacketType::CONNECTION),
+                "CONGESTION" => ::std::option::Option::Some(PacketType::CONGESTION),
                 _ => ::std::option::Option::None
             }
         }
 
         const VALUES: &'static [PacketType] = &[
+            PacketType::PACKET_TYPE_UNSPECIFIED,
             PacketType::RSA_PUB_KEY,
             PacketType::AES_KEY,
             PacketType::MEDIA,
             PacketType::CONNECTION,
+            PacketType::CONGESTION,
         ];
     }
 
@@ -246,7 +275,7 @@ pub mod packet_wrapper {
 
     impl ::std::default::Default for PacketType {
         fn default() -> Self {
-            PacketType::RSA_PUB_KEY
+            PacketType::PACKET_TYPE_UNSPECIFIED
         }
     }
 
@@ -258,32 +287,46 @@ pub mod packet_wrapper {
 }
 
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x1atypes/packet_wrapper.proto\"\xbc\x01\n\rPacketWrapper\x12:\n\x0bpa\
+    \n\x1atypes/packet_wrapper.proto\"\x90\x02\n\rPacketWrapper\x12:\n\x0bpa\
     cket_type\x18\x01\x20\x01(\x0e2\x19.PacketWrapper.PacketTypeR\npacketTyp\
     e\x12\x14\n\x05email\x18\x02\x20\x01(\tR\x05email\x12\x12\n\x04data\x18\
-    \x03\x20\x01(\x0cR\x04data\"E\n\nPacketType\x12\x0f\n\x0bRSA_PUB_KEY\x10\
-    \0\x12\x0b\n\x07AES_KEY\x10\x01\x12\t\n\x05MEDIA\x10\x02\x12\x0e\n\nCONN\
-    ECTION\x10\x03J\xa7\x03\n\x06\x12\x04\0\0\x0c\x01\n\x08\n\x01\x0c\x12\
-    \x03\0\0\x12\n\n\n\x02\x04\0\x12\x04\x02\0\x0c\x01\n\n\n\x03\x04\0\x01\
-    \x12\x03\x02\x08\x15\n\x0c\n\x04\x04\0\x04\0\x12\x04\x03\x02\x08\x03\n\
-    \x0c\n\x05\x04\0\x04\0\x01\x12\x03\x03\x07\x11\n\r\n\x06\x04\0\x04\0\x02\
-    \0\x12\x03\x04\x04\x14\n\x0e\n\x07\x04\0\x04\0\x02\0\x01\x12\x03\x04\x04\
-    \x0f\n\x0e\n\x07\x04\0\x04\0\x02\0\x02\x12\x03\x04\x12\x13\n\r\n\x06\x04\
-    \0\x04\0\x02\x01\x12\x03\x05\x04\x10\n\x0e\n\x07\x04\0\x04\0\x02\x01\x01\
-    \x12\x03\x05\x04\x0b\n\x0e\n\x07\x04\0\x04\0\x02\x01\x02\x12\x03\x05\x0e\
-    \x0f\n\r\n\x06\x04\0\x04\0\x02\x02\x12\x03\x06\x04\x0e\n\x0e\n\x07\x04\0\
-    \x04\0\x02\x02\x01\x12\x03\x06\x04\t\n\x0e\n\x07\x04\0\x04\0\x02\x02\x02\
-    \x12\x03\x06\x0c\r\n\r\n\x06\x04\0\x04\0\x02\x03\x12\x03\x07\x04\x13\n\
-    \x0e\n\x07\x04\0\x04\0\x02\x03\x01\x12\x03\x07\x04\x0e\n\x0e\n\x07\x04\0\
-    \x04\0\x02\x03\x02\x12\x03\x07\x11\x12\n\x0b\n\x04\x04\0\x02\0\x12\x03\t\
-    \x02\x1d\n\x0c\n\x05\x04\0\x02\0\x06\x12\x03\t\x02\x0c\n\x0c\n\x05\x04\0\
-    \x02\0\x01\x12\x03\t\r\x18\n\x0c\n\x05\x04\0\x02\0\x03\x12\x03\t\x1b\x1c\
-    \n\x0b\n\x04\x04\0\x02\x01\x12\x03\n\x02\x13\n\x0c\n\x05\x04\0\x02\x01\
-    \x05\x12\x03\n\x02\x08\n\x0c\n\x05\x04\0\x02\x01\x01\x12\x03\n\t\x0e\n\
-    \x0c\n\x05\x04\0\x02\x01\x03\x12\x03\n\x11\x12\n\x0b\n\x04\x04\0\x02\x02\
-    \x12\x03\x0b\x02\x11\n\x0c\n\x05\x04\0\x02\x02\x05\x12\x03\x0b\x02\x07\n\
-    \x0c\n\x05\x04\0\x02\x02\x01\x12\x03\x0b\x08\x0c\n\x0c\n\x05\x04\0\x02\
-    \x02\x03\x12\x03\x0b\x0f\x10b\x06proto3\
+    \x03\x20\x01(\x0cR\x04data\x12%\n\x0ekey_generation\x18\x04\x20\x01(\rR\
+    \rkeyGeneration\"r\n\nPacketType\x12\x1b\n\x17PACKET_TYPE_UNSPECIFIED\
+    \x10\0\x12\x0f\n\x0bRSA_PUB_KEY\x10\x01\x12\x0b\n\x07AES_KEY\x10\x02\x12\
+    \t\n\x05MEDIA\x10\x03\x12\x0e\n\nCONNECTION\x10\x04\x12\x0e\n\nCONGESTIO\
+    N\x10\x05J\x8e\x06\n\x06\x12\x04\0\0\x12\x01\n\x08\n\x01\x0c\x12\x03\0\0\
+    \x12\n\n\n\x02\x04\0\x12\x04\x02\0\x12\x01\n\n\n\x03\x04\0\x01\x12\x03\
+    \x02\x08\x15\n\x0c\n\x04\x04\0\x04\0\x12\x04\x03\x02\x0c\x03\n\x0c\n\x05\
+    \x04\0\x04\0\x01\x12\x03\x03\x07\x11\nZ\n\x06\x04\0\x04\0\x02\0\x12\x03\
+    \x05\x04\x20\x1aK\x20Never\x20sent;\x20an\x20unset\x20packet_type\x20rea\
+    ds\x20as\x20this\x20instead\x20of\x20as\x20a\x20real\x20type.\n\n\x0e\n\
+    \x07\x04\0\x04\0\x02\0\x01\x12\x03\x05\x04\x1b\n\x0e\n\x07\x04\0\x04\0\
+    \x02\0\x02\x12\x03\x05\x1e\x1f\n\r\n\x06\x04\0\x04\0\x02\x01\x12\x03\x06\
+    \x04\x14\n\x0e\n\x07\x04\0\x04\0\x02\x01\x01\x12\x03\x06\x04\x0f\n\x0e\n\
+    \x07\x04\0\x04\0\x02\x01\x02\x12\x03\x06\x12\x13\n\r\n\x06\x04\0\x04\0\
+    \x02\x02\x12\x03\x07\x04\x10\n\x0e\n\x07\x04\0\x04\0\x02\x02\x01\x12\x03\
+    \x07\x04\x0b\n\x0e\n\x07\x04\0\x04\0\x02\x02\x02\x12\x03\x07\x0e\x0f\n\r\
+    \n\x06\x04\0\x04\0\x02\x03\x12\x03\x08\x04\x0e\n\x0e\n\x07\x04\0\x04\0\
+    \x02\x03\x01\x12\x03\x08\x04\t\n\x0e\n\x07\x04\0\x04\0\x02\x03\x02\x12\
+    \x03\x08\x0c\r\n\r\n\x06\x04\0\x04\0\x02\x04\x12\x03\t\x04\x13\n\x0e\n\
+    \x07\x04\0\x04\0\x02\x04\x01\x12\x03\t\x04\x0e\n\x0e\n\x07\x04\0\x04\0\
+    \x02\x04\x02\x12\x03\t\x11\x12\n:\n\x06\x04\0\x04\0\x02\x05\x12\x03\x0b\
+    \x04\x13\x1a+\x20Sent\x20by\x20the\x20server,\x20see\x20CongestionPacket\
+    .\n\n\x0e\n\x07\x04\0\x04\0\x02\x05\x01\x12\x03\x0b\x04\x0e\n\x0e\n\x07\
+    \x04\0\x04\0\x02\x05\x02\x12\x03\x0b\x11\x12\n\x0b\n\x04\x04\0\x02\0\x12\
+    \x03\r\x02\x1d\n\x0c\n\x05\x04\0\x02\0\x06\x12\x03\r\x02\x0c\n\x0c\n\x05\
+    \x04\0\x02\0\x01\x12\x03\r\r\x18\n\x0c\n\x05\x04\0\x02\0\x03\x12\x03\r\
+    \x1b\x1c\n\x0b\n\x04\x04\0\x02\x01\x12\x03\x0e\x02\x13\n\x0c\n\x05\x04\0\
+    \x02\x01\x05\x12\x03\x0e\x02\x08\n\x0c\n\x05\x04\0\x02\x01\x01\x12\x03\
+    \x0e\t\x0e\n\x0c\n\x05\x04\0\x02\x01\x03\x12\x03\x0e\x11\x12\n\x0b\n\x04\
+    \x04\0\x02\x02\x12\x03\x0f\x02\x11\n\x0c\n\x05\x04\0\x02\x02\x05\x12\x03\
+    \x0f\x02\x07\n\x0c\n\x05\x04\0\x02\x02\x01\x12\x03\x0f\x08\x0c\n\x0c\n\
+    \x05\x04\0\x02\x02\x03\x12\x03\x0f\x0f\x10\nc\n\x04\x04\0\x02\x03\x12\
+    \x03\x11\x02\x1c\x1aV\x20Generation\x20of\x20the\x20sender's\x20AES\x20k\
+    ey\x20that\x20MEDIA\x20data\x20is\x20encrypted\x20with,\x20see\x20AesPac\
+    ket.\n\n\x0c\n\x05\x04\0\x02\x03\x05\x12\x03\x11\x02\x08\n\x0c\n\x05\x04\
+    \0\x02\x03\x01\x12\x03\x11\t\x17\n\x0c\n\x05\x04\0\x02\x03\x03\x12\x03\
+    \x11\x1a\x1bb\x06proto3\
 ";
 
 /// `FileDescriptorProto` object which was a source for this generated file
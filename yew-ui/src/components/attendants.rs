@@ -2,7 +2,9 @@ use crate::components::{canvas_generator, peer_list::PeerList};
 use crate::constants::{CANVAS_LIMIT, USERS_ALLOWED_TO_STREAM, WEBTRANSPORT_HOST};
 use crate::{components::host::Host, constants::ACTIX_WEBSOCKET};
 use log::{error, warn};
-use videocall_client::{MediaDeviceAccess, VideoCallClient, VideoCallClientOptions};
+use videocall_client::{
+    MediaDeviceAccess, TransportPolicy, VideoCallClient, VideoCallClientOptions,
+};
 use videocall_types::protos::media_packet::media_packet::MediaType;
 use wasm_bindgen::JsValue;
 use web_sys::*;
@@ -39,6 +41,7 @@ pub enum Msg {
     MeetingAction(MeetingAction),
     OnPeerAdded(String),
     OnFirstFrame((String, MediaType)),
+    OnStreamEnded((String, MediaType)),
     UserScreenAction(UserScreenAction),
 }
 
@@ -92,7 +95,11 @@ impl AttendantsComponent {
             websocket_url: format!("{ACTIX_WEBSOCKET}/{email}/{id}"),
             webtransport_url: format!("{WEBTRANSPORT_HOST}/{email}/{id}"),
             enable_e2ee: ctx.props().e2ee_enabled,
-            enable_webtransport: ctx.props().webtransport_enabled,
+            transport_policy: if ctx.props().webtransport_enabled {
+                TransportPolicy::PreferWebTransport
+            } else {
+                TransportPolicy::WebSocketOnly
+            },
             on_connected: {
                 let link = ctx.link().clone();
                 Callback::from(move |_| link.send_message(Msg::from(WsAction::Connected)))
@@ -111,8 +118,15 @@ impl AttendantsComponent {
                     link.send_message(Msg::OnFirstFrame((email, media_type)))
                 })
             },
+            on_peer_stream_ended: {
+                let link = ctx.link().clone();
+                Callback::from(move |(email, media_type)| {
+                    link.send_message(Msg::OnStreamEnded((email, media_type)))
+                })
+            },
             get_peer_video_canvas_id: Callback::from(|email| email),
             get_peer_screen_canvas_id: Callback::from(|email| format!("screen-share-{}", &email)),
+            ..Default::default()
         };
         VideoCallClient::new(opts)
     }
@@ -197,6 +211,7 @@ impl Component for AttendantsComponent {
             },
             Msg::OnPeerAdded(_email) => true,
             Msg::OnFirstFrame((_email, media_type)) => matches!(media_type, MediaType::SCREEN),
+            Msg::OnStreamEnded((_email, media_type)) => matches!(media_type, MediaType::SCREEN),
             Msg::MeetingAction(action) => {
                 match action {
                     MeetingAction::ToggleScreenShare => {
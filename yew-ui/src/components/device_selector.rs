@@ -11,6 +11,7 @@ pub enum Msg {
     DevicesLoaded,
     OnCameraSelect(String),
     OnMicSelect(String),
+    OnSpeakerSelect(String),
     LoadDevices(),
 }
 
@@ -18,6 +19,7 @@ pub enum Msg {
 pub struct DeviceSelectorProps {
     pub on_camera_select: Callback<String>,
     pub on_microphone_select: Callback<String>,
+    pub on_speaker_select: Callback<String>,
 }
 
 impl DeviceSelector {
@@ -26,11 +28,19 @@ impl DeviceSelector {
         let link = ctx.link().clone();
         let on_microphone_select = ctx.props().on_microphone_select.clone();
         let on_camera_select = ctx.props().on_camera_select.clone();
-        media_devices.on_loaded = Callback::from(move |_| link.send_message(Msg::DevicesLoaded));
+        let on_speaker_select = ctx.props().on_speaker_select.clone();
+        media_devices.on_loaded = {
+            let link = link.clone();
+            Callback::from(move |_| link.send_message(Msg::DevicesLoaded))
+        };
+        media_devices.on_devices_changed =
+            Callback::from(move |_| link.send_message(Msg::DevicesLoaded));
         media_devices.audio_inputs.on_selected =
             Callback::from(move |device_id| on_microphone_select.emit(device_id));
         media_devices.video_inputs.on_selected =
             Callback::from(move |device_id| on_camera_select.emit(device_id));
+        media_devices.audio_outputs.on_selected =
+            Callback::from(move |device_id| on_speaker_select.emit(device_id));
         media_devices
     }
 }
@@ -70,14 +80,20 @@ impl Component for DeviceSelector {
                 self.media_devices.audio_inputs.select(&mic);
                 true
             }
+            Msg::OnSpeakerSelect(speaker) => {
+                self.media_devices.audio_outputs.select(&speaker);
+                true
+            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         let mics = self.media_devices.audio_inputs.devices();
         let cameras = self.media_devices.video_inputs.devices();
+        let speakers = self.media_devices.audio_outputs.devices();
         let selected_mic = self.media_devices.audio_inputs.selected();
         let selected_camera = self.media_devices.video_inputs.selected();
+        let selected_speaker = self.media_devices.audio_outputs.selected();
         fn selection(event: Event) -> String {
             event
                 .target()
@@ -109,6 +125,19 @@ impl Component for DeviceSelector {
                         </option>
                     }) }
                 </select>
+                if MediaDeviceList::audio_output_selection_supported() {
+                    <br/>
+                    <label for={"speaker-select"}>{ "Speaker:" }</label>
+                    <select id={"speaker-select"} class={"device-selector"}
+                            onchange={ctx.link().callback(|e: Event| Msg::OnSpeakerSelect(selection(e)))}
+                    >
+                        { for speakers.iter().map(|device| html! {
+                            <option value={device.device_id()} selected={selected_speaker == device.device_id()}>
+                                { device.label() }
+                            </option>
+                        }) }
+                    </select>
+                }
             </div>
         }
     }
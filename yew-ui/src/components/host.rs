@@ -19,6 +19,7 @@ pub enum Msg {
     DisableVideo,
     AudioDeviceChanged(String),
     VideoDeviceChanged(String),
+    SpeakerDeviceChanged(String),
 }
 
 pub struct Host {
@@ -52,7 +53,7 @@ impl Component for Host {
         let client = &ctx.props().client;
         Self {
             camera: CameraEncoder::new(client.clone(), VIDEO_ELEMENT_ID),
-            microphone: MicrophoneEncoder::new(client.clone()),
+            microphone: MicrophoneEncoder::new(client.clone(), Callback::noop()),
             screen: ScreenEncoder::new(client.clone()),
             share_screen: ctx.props().share_screen,
             mic_enabled: ctx.props().mic_enabled,
@@ -146,16 +147,25 @@ impl Component for Host {
                 }
                 false
             }
+            Msg::SpeakerDeviceChanged(speaker) => {
+                ctx.props().client.set_audio_output(&speaker);
+                false
+            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         let mic_callback = ctx.link().callback(Msg::AudioDeviceChanged);
         let cam_callback = ctx.link().callback(Msg::VideoDeviceChanged);
+        let speaker_callback = ctx.link().callback(Msg::SpeakerDeviceChanged);
         html! {
             <>
                 <video class="self-camera" autoplay=true id={VIDEO_ELEMENT_ID}></video>
-                <DeviceSelector on_microphone_select={mic_callback} on_camera_select={cam_callback}/>
+                <DeviceSelector
+                    on_microphone_select={mic_callback}
+                    on_camera_select={cam_callback}
+                    on_speaker_select={speaker_callback}
+                />
             </>
         }
     }
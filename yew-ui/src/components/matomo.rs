@@ -26,9 +26,25 @@ impl MatomoTracker {
     }
 
     pub fn track_page_view(&self, title: &str, url: &str) {
+        self.track_page_view_with_dimensions(title, url, &[]);
+    }
+
+    /// Tracks a page view with custom dimensions, given as (dimension id, value) pairs.
+    /// Dimension ids are the positive integers Matomo assigns when a dimension is created
+    /// under Administration > Custom Dimensions; values for unknown ids are ignored by Matomo.
+    /// The dimensions are cleared again afterwards, so they only apply to this page view.
+    pub fn track_page_view_with_dimensions(&self, title: &str, url: &str, dims: &[(u32, String)]) {
         if !Reflect::has(&window(), &"_paq".into()).unwrap_or(false) {
             return;
         }
+        for (id, value) in dims {
+            let array = js_sys::Array::new();
+            array.push(&JsValue::from_str("setCustomDimension"));
+            array.push(&JsValue::from(*id));
+            array.push(&JsValue::from_str(value));
+            self.push(&array.into());
+        }
+
         // Create an array with commands
         let array = js_sys::Array::new();
 
@@ -45,5 +61,13 @@ impl MatomoTracker {
         array.push(&JsValue::from_str("trackPageView"));
         // Call the push method with the command array
         self.push(&array.into());
+
+        // Matomo would otherwise send the dimensions with every later page view.
+        for (id, _) in dims {
+            let array = js_sys::Array::new();
+            array.push(&JsValue::from_str("deleteCustomDimension"));
+            array.push(&JsValue::from(*id));
+            self.push(&array.into());
+        }
     }
 }